@@ -2,8 +2,8 @@
 
 use super::*;
 use crate::blocks::Block;
-use crate::textures::BlockTextureIndex;
-use crate::world::{Axis, Direction};
+use crate::maths::AABB;
+use cgmath::Vector3;
 
 pub const CHUNK_BLOCK_WIDTH: usize = 32;
 pub const CHUNK_SIZE: f32 = CHUNK_BLOCK_WIDTH as f32 * Block::WIDTH;
@@ -20,13 +20,48 @@ pub struct Chunk {
     needs_mesh_update: bool,
     vertex_count: usize,
 
+    /// How many blocks wide each cell of the current mesh is: 1 for full detail, or 2, 4, or 8 for
+    /// meshes made from downsampled blocks.
+    lod_factor: usize,
+
+    /// The level of detail most recently asked for in `logic`. Might not match `lod_factor` yet if
+    /// the mesh is still being made.
+    requested_lod_factor: usize,
+
+    /// The neighbors' levels of detail most recently asked for in `logic`, by `Direction::index`.
+    requested_neighbor_lod_factors: [usize; 6],
+
+    /// Bumped every time a new mesh is asked for, so that meshes that finish after the chunk has
+    /// changed again can be thrown away.
+    mesh_revision: u64,
+
+    /// Which faces of the chunk can see each other. Recomputed along with the mesh whenever a block
+    /// changes.
+    visibility: ChunkVisibility,
+
     /// Constant chunk position based on chunk grid position and chunk size.
     chunk_position: [f32; 3],
 }
 
 impl Chunk {
     pub fn generate(chunk_i: i64, chunk_j: i64, chunk_k: i64, _device: &wgpu::Device) -> Self {
-        let mut c = Self {
+        let mut c = Self::empty(chunk_i, chunk_j, chunk_k);
+
+        for i in 0..CHUNK_BLOCK_WIDTH {
+            for j in 0..CHUNK_BLOCK_WIDTH / 2 {
+                for k in 0..CHUNK_BLOCK_WIDTH {
+                    c.set(i, j, k, Block::from(BlockType::Grass))
+                }
+            }
+        }
+
+        c
+    }
+
+    /// Makes a chunk full of air at the chunk grid position. It has no mesh until the first
+    /// `logic`.
+    pub fn empty(chunk_i: i64, chunk_j: i64, chunk_k: i64) -> Self {
+        Self {
             blocks: [[[Block::from(BlockType::Air); CHUNK_BLOCK_WIDTH]; CHUNK_BLOCK_WIDTH];
                 CHUNK_BLOCK_WIDTH],
             // chunk_i,
@@ -34,6 +69,11 @@ impl Chunk {
             // chunk_k,
             block_mesh_buffer: None,
             vertex_count: 0,
+            visibility: ChunkVisibility::ALL,
+            lod_factor: 1,
+            requested_lod_factor: 0,
+            requested_neighbor_lod_factors: [0; 6],
+            mesh_revision: 0,
 
             // set to true to make the initial mesh on first logic loop
             needs_mesh_update: true,
@@ -43,23 +83,44 @@ impl Chunk {
                 chunk_j as f32 * CHUNK_SIZE,
                 chunk_k as f32 * CHUNK_SIZE,
             ],
-        };
+        }
+    }
 
-        for i in 0..CHUNK_BLOCK_WIDTH {
-            for j in 0..CHUNK_BLOCK_WIDTH / 2 {
-                for k in 0..CHUNK_BLOCK_WIDTH {
-                    c.set(i, j, k, Block::from(BlockType::Grass))
-                }
-            }
+    /// Returns the block at the array position.
+    pub fn at(&self, i: usize, j: usize, k: usize) -> &Block {
+        &self.blocks[i][j][k]
+    }
+
+    /// Returns the box that the chunk occupies in the world.
+    pub fn bounding_box(&self) -> AABB {
+        let half = CHUNK_SIZE / 2.0;
+        AABB {
+            center_pos: Vector3::new(
+                self.chunk_position[0] + half,
+                self.chunk_position[1] + half,
+                self.chunk_position[2] + half,
+            ),
+            half_size: Vector3::new(half, half, half),
         }
+    }
 
-        c
+    /// Returns the box that a chunk at the grid position would occupy, whether or not it's loaded.
+    pub fn bounding_box_at(index: (i64, i64, i64)) -> AABB {
+        let half = CHUNK_SIZE / 2.0;
+        AABB {
+            center_pos: Vector3::new(
+                index.0 as f32 * CHUNK_SIZE + half,
+                index.1 as f32 * CHUNK_SIZE + half,
+                index.2 as f32 * CHUNK_SIZE + half,
+            ),
+            half_size: Vector3::new(half, half, half),
+        }
     }
 
-    // /// Returns the block at the array position.
-    // pub fn at(&self, i: usize, j: usize, k: usize) -> &Block {
-    //     &self.blocks[i][j][k]
-    // }
+    /// Returns which faces of the chunk can see each other through see-through blocks.
+    pub fn visibility(&self) -> ChunkVisibility {
+        self.visibility
+    }
 
     /// Sets the block at the array index.
     pub fn set(&mut self, i: usize, j: usize, k: usize, b: Block) {
@@ -67,11 +128,77 @@ impl Chunk {
         self.needs_mesh_update = true;
     }
 
-    pub fn logic(&mut self, device: &wgpu::Device) {
-        // it'd be cool to put this in the `render` method, but `render` isn't provided a queue
-        if self.needs_mesh_update {
-            self.make_greedy_mesh(device);
+    /// Returns how many blocks wide each cell of the chunk's current mesh is.
+    pub fn lod_factor(&self) -> usize {
+        self.lod_factor
+    }
+
+    /// Updates the chunk's visibility and mesh if its blocks changed or if it or its neighbors
+    /// should be drawn at a different level of detail. `neighbor_lod_factors` are by
+    /// `Direction::index`. Edits to a full-detail chunk are meshed right away so they show up on
+    /// the next frame. Anything else is returned as a MeshJob to be meshed off of the main thread
+    /// and handed back through `finish_mesh`; the old mesh is drawn until then.
+    pub fn logic(
+        &mut self,
+        device: &wgpu::Device,
+        lod_factor: usize,
+        neighbor_lod_factors: [usize; 6],
+    ) -> Option<MeshJob> {
+        let edited = self.needs_mesh_update;
+
+        // only more detailed neighbors change how the chunk is meshed
+        let mut neighbor_lod_factors = neighbor_lod_factors;
+        for factor in neighbor_lod_factors.iter_mut() {
+            *factor = (*factor).min(lod_factor);
+        }
+
+        if edited {
+            self.visibility =
+                ChunkVisibility::compute(|i, j, k| self.blocks[i][j][k].is_see_through());
             self.needs_mesh_update = false;
+        } else if lod_factor == self.requested_lod_factor
+            && neighbor_lod_factors == self.requested_neighbor_lod_factors
+        {
+            return None;
+        }
+
+        let job = self.start_mesh(lod_factor, neighbor_lod_factors);
+
+        if edited && lod_factor == 1 {
+            // it'd be cool to put this in the `render` method, but `render` isn't provided a queue
+            let vertices = job.grid.greedy_mesh(job.origin, 1);
+            self.set_mesh(device, &vertices, 1);
+            None
+        } else {
+            Some(job)
+        }
+    }
+
+    /// Asks for a new mesh at the level of detail. Any mesh asked for before this one is stale
+    /// from now on.
+    fn start_mesh(&mut self, lod_factor: usize, neighbor_lod_factors: [usize; 6]) -> MeshJob {
+        self.requested_lod_factor = lod_factor;
+        self.requested_neighbor_lod_factors = neighbor_lod_factors;
+        self.mesh_revision += 1;
+
+        MeshJob {
+            grid: BlockGrid::from_blocks(&self.blocks),
+            origin: self.chunk_position,
+            lod_factor,
+            neighbor_lod_factors,
+            revision: self.mesh_revision,
+        }
+    }
+
+    /// Returns true if the mesh was made from the chunk's latest MeshJob.
+    fn is_current(&self, mesh: &ChunkMesh) -> bool {
+        mesh.revision == self.mesh_revision
+    }
+
+    /// Uploads a mesh made from a MeshJob, unless the chunk has asked for a newer one since.
+    pub fn finish_mesh(&mut self, device: &wgpu::Device, mesh: ChunkMesh) {
+        if self.is_current(&mesh) {
+            self.set_mesh(device, &mesh.vertices, mesh.lod_factor);
         }
     }
 
@@ -89,324 +216,17 @@ impl Chunk {
         }
     }
 
-    // TODO: create a queue (world-level, probably) that renders new blocks while a chunk adds them
-    // to its own greedy mesh, in case re-creating greedy meshes is too slow
-    fn make_greedy_mesh(&mut self, device: &wgpu::Device) {
-        let mut vertices = Vec::<ChunkMeshVertex>::new();
-
-        vertices.append(&mut self.get_greedy_mesh_along_axis(Axis::X));
-        vertices.append(&mut self.get_greedy_mesh_along_axis(Axis::Y));
-        vertices.append(&mut self.get_greedy_mesh_along_axis(Axis::Z));
-
+    fn set_mesh(&mut self, device: &wgpu::Device, vertices: &[ChunkMeshVertex], lod_factor: usize) {
         // save the number of vertices
         self.vertex_count = vertices.len();
+        self.lod_factor = lod_factor;
 
-        let vertex_slice = &vertices[..];
-        let casted_slice = bytemuck::cast_slice(vertex_slice);
+        let casted_slice = bytemuck::cast_slice(vertices);
 
         // copy vertices to the vertex buffer
         self.block_mesh_buffer =
             Some(device.create_buffer_with_data(casted_slice, wgpu::BufferUsage::VERTEX));
     }
-
-    // TODO: potentially separate traversals by Direction instead of Axis to further cull adjacent
-    // or hidden faces
-    fn get_greedy_mesh_along_axis(&self, along_axis: Axis) -> Vec<ChunkMeshVertex> {
-        let mut vertices = Vec::<ChunkMeshVertex>::new();
-
-        // `layer` is basically the layer along whichever axis we're traversing
-        for layer in 0..CHUNK_BLOCK_WIDTH {
-            let mut visited_mask = [[false; CHUNK_BLOCK_WIDTH]; CHUNK_BLOCK_WIDTH];
-
-            for i in 0..CHUNK_BLOCK_WIDTH {
-                for j in 0..CHUNK_BLOCK_WIDTH {
-                    // the block at the starting point that we have to match
-                    let reference_block = self.get_block_along_layer(along_axis, layer, i, j);
-
-                    // decide if we should just skip over this block for meshing
-                    if reference_block.should_skip_mesh() || visited_mask[i][j] {
-                        continue;
-                    }
-
-                    // just mark this spot as visited while we're on it
-                    visited_mask[i][j] = true;
-
-                    // starting_point should not be mut
-                    let starting_point = (i, j);
-
-                    // inclusive ending! ending_point is also included in the final mesh
-                    let mut ending_point = (i, j);
-
-                    // traverse along the "i" axis first, getting the ending_point's `i` (start
-                    // at/skip i + 1 because the block at i is the reference_block, which doesn't
-                    // need to be matched to itself, silly :))
-                    for (k, flag_row) in visited_mask.iter_mut().enumerate().skip(i + 1) {
-                        // if blocks are still the same type, move the ending_point
-                        if self
-                            .get_block_along_layer(along_axis, layer, k, j)
-                            .block_type
-                            == reference_block.block_type
-                        {
-                            // block types match, so we can move the ending_point!
-                            ending_point.0 = k;
-                            flag_row[j] = true;
-                        } else {
-                            // block types don't match, so don't advance ending_point, don't mark
-                            // this position as visited, do not pass go, do not collect $200, just
-                            // break
-                            break;
-                        }
-                    }
-
-                    // now, we have to traverse along both the "i" the "j" axis (up until
-                    // ending_point's "i" component), making sure all blocks still match
-                    // reference_block's type. if we can move along the "j" axis and all block
-                    // types are still the same, we can advance ending_point.
-                    //
-                    // why "j" axis first? for each iteration, we need to traverse along the i axis
-                    // to accurately decide if we can *extend* the mesh in the direction of j. we
-                    // start at j + 1 because it's already been decided that all blocks along j
-                    // match.
-                    for l in (j + 1)..CHUNK_BLOCK_WIDTH {
-                        // go as far as we can while block types match. if even one of the blocks
-                        // along the i axis here don't match, we have to stop. if all blocks in
-                        // this row match, we can advance ending_point's j component.
-                        //
-                        // remember, ending_point is inclusive, which is why we go up to and equal
-                        // to ending_point.0
-                        let mut should_advance = true;
-                        for (m, flag_row) in visited_mask
-                            .iter_mut()
-                            .enumerate()
-                            .take(ending_point.0 + 1)
-                            .skip(i)
-                        {
-                            if self
-                                .get_block_along_layer(along_axis, layer, m, l)
-                                .block_type
-                                != reference_block.block_type
-                            {
-                                // came across a mismatching block, so signal no advancing and
-                                // leave this loop
-                                should_advance = false;
-                                break;
-                            }
-                            flag_row[l] = true;
-                        }
-
-                        if should_advance {
-                            ending_point.1 = l;
-                        // then we'll move onto the next row
-                        } else {
-                            // if we came across a block that doesn't match in this row, we have to
-                            // reset all visited flags in this row
-                            for row_flags in
-                                visited_mask.iter_mut().take(ending_point.0 + 1).skip(i)
-                            {
-                                row_flags[l] = false;
-                            }
-
-                            // and leave this loop
-                            break;
-                        }
-                    }
-
-                    // now, at this point, we should have a starting_point and ending_point. we can
-                    // use this data to make our mesh:
-
-                    let width = ending_point.0 - starting_point.0 + 1;
-                    let height = ending_point.1 - starting_point.1 + 1;
-
-                    let (direction, other_direction) = match along_axis {
-                        Axis::X => (Direction::East, Direction::West),
-                        Axis::Y => (Direction::Up, Direction::Down),
-                        Axis::Z => (Direction::North, Direction::South),
-                    };
-                    let quad_start = match along_axis {
-                        Axis::X => (layer, starting_point.0, starting_point.1),
-                        Axis::Y => (starting_point.0, layer, starting_point.1),
-                        Axis::Z => (starting_point.0, starting_point.1, layer),
-                    };
-                    if let Ok(texture_layer) = BlockTextureIndex::from_type_and_direction(
-                        reference_block.block_type,
-                        direction,
-                    ) {
-                        vertices.append(&mut self.get_quad_face_vertices(
-                            quad_start,
-                            width,
-                            height,
-                            texture_layer,
-                            direction,
-                        ));
-                    }
-                    if let Ok(other_texture_layer) = BlockTextureIndex::from_type_and_direction(
-                        reference_block.block_type,
-                        other_direction,
-                    ) {
-                        vertices.append(&mut self.get_quad_face_vertices(
-                            quad_start,
-                            width,
-                            height,
-                            other_texture_layer,
-                            other_direction,
-                        ));
-                    }
-                }
-            }
-        }
-
-        vertices
-    }
-
-    fn get_block_along_layer(&self, along_axis: Axis, layer: usize, i: usize, j: usize) -> &Block {
-        match along_axis {
-            Axis::X => &self.blocks[layer][j][i], // becuase "i" is the z axis
-            Axis::Y => &self.blocks[i][layer][j],
-            Axis::Z => &self.blocks[i][j][layer],
-        }
-    }
-
-    fn get_quad_face_vertices(
-        &self,
-        start_grid_pos: (usize, usize, usize),
-        width: usize,
-        height: usize,
-        texture_layer: BlockTextureIndex,
-        face_direction: Direction,
-    ) -> Vec<ChunkMeshVertex> {
-        // the starting block's exact position within the chunk (in other words, relative to the
-        // chunk, not the world)
-        let block_grid_pos = [
-            start_grid_pos.0 as f32 * Block::WIDTH,
-            start_grid_pos.1 as f32 * Block::WIDTH,
-            start_grid_pos.2 as f32 * Block::WIDTH,
-        ];
-
-        // the bottom, south-east vertex position of the cuboid
-        let base_vertex_pos = [
-            self.chunk_position[0] + block_grid_pos[0],
-            self.chunk_position[1] + block_grid_pos[1],
-            self.chunk_position[2] + block_grid_pos[2],
-        ];
-
-        let (quad_width, quad_height) = (width as f32 * Block::WIDTH, height as f32 * Block::WIDTH);
-
-        let (lower_left_pos, lower_right_pos, upper_right_pos, upper_left_pos) =
-            match face_direction {
-                Direction::North => {
-                    let z = base_vertex_pos[2] + Block::WIDTH;
-                    (
-                        [base_vertex_pos[0], base_vertex_pos[1], z],
-                        [base_vertex_pos[0] + quad_width, base_vertex_pos[1], z],
-                        [
-                            base_vertex_pos[0] + quad_width,
-                            base_vertex_pos[1] + quad_height,
-                            z,
-                        ],
-                        [base_vertex_pos[0], base_vertex_pos[1] + quad_height, z],
-                    )
-                }
-                Direction::South => {
-                    let z = base_vertex_pos[2];
-                    (
-                        [base_vertex_pos[0] + quad_width, base_vertex_pos[1], z],
-                        [base_vertex_pos[0], base_vertex_pos[1], z],
-                        [base_vertex_pos[0], base_vertex_pos[1] + quad_height, z],
-                        [
-                            base_vertex_pos[0] + quad_width,
-                            base_vertex_pos[1] + quad_height,
-                            z,
-                        ],
-                    )
-                }
-                Direction::East => {
-                    let x = base_vertex_pos[0];
-                    (
-                        [x, base_vertex_pos[1], base_vertex_pos[2]],
-                        [x, base_vertex_pos[1], base_vertex_pos[2] + quad_width],
-                        [
-                            x,
-                            base_vertex_pos[1] + quad_height,
-                            base_vertex_pos[2] + quad_width,
-                        ],
-                        [x, base_vertex_pos[1] + quad_height, base_vertex_pos[2]],
-                    )
-                }
-                Direction::West => {
-                    let x = base_vertex_pos[0] + Block::WIDTH;
-                    (
-                        [x, base_vertex_pos[1], base_vertex_pos[2] + quad_width],
-                        [x, base_vertex_pos[1], base_vertex_pos[2]],
-                        [x, base_vertex_pos[1] + quad_height, base_vertex_pos[2]],
-                        [
-                            x,
-                            base_vertex_pos[1] + quad_height,
-                            base_vertex_pos[2] + quad_width,
-                        ],
-                    )
-                }
-                Direction::Up => {
-                    let y = base_vertex_pos[1] + Block::WIDTH;
-                    (
-                        [base_vertex_pos[0] + quad_width, y, base_vertex_pos[2]],
-                        [base_vertex_pos[0], y, base_vertex_pos[2]],
-                        [base_vertex_pos[0], y, base_vertex_pos[2] + quad_height],
-                        [
-                            base_vertex_pos[0] + quad_width,
-                            y,
-                            base_vertex_pos[2] + quad_height,
-                        ],
-                    )
-                }
-                Direction::Down => {
-                    let y = base_vertex_pos[1];
-                    (
-                        [base_vertex_pos[0], y, base_vertex_pos[2]],
-                        [base_vertex_pos[0] + quad_width, y, base_vertex_pos[2]],
-                        [
-                            base_vertex_pos[0] + quad_width,
-                            y,
-                            base_vertex_pos[2] + quad_height,
-                        ],
-                        [base_vertex_pos[0], y, base_vertex_pos[2] + quad_height],
-                    )
-                }
-            };
-
-        let texture_layer_coord = texture_layer.to_tex_coord();
-
-        let (lower_left, lower_right, upper_right, upper_left) = (
-            ChunkMeshVertex {
-                position: lower_left_pos,
-                uv_coords: [0.0, height as f32],
-                texture_layer_coord,
-            },
-            ChunkMeshVertex {
-                position: lower_right_pos,
-                uv_coords: [width as f32, height as f32],
-                texture_layer_coord,
-            },
-            ChunkMeshVertex {
-                position: upper_right_pos,
-                uv_coords: [width as f32, 0.0],
-                texture_layer_coord,
-            },
-            ChunkMeshVertex {
-                position: upper_left_pos,
-                uv_coords: [0.0, 0.0],
-                texture_layer_coord,
-            },
-        );
-
-        vec![
-            lower_left,
-            lower_right,
-            upper_left,
-            upper_left,
-            lower_right,
-            upper_right,
-        ]
-    }
 }
 
 #[repr(C)]
@@ -414,7 +234,9 @@ impl Chunk {
 pub struct ChunkMeshVertex {
     pub position: [f32; 3],
     pub uv_coords: [f32; 2],
-    pub texture_layer_coord: f32,
+
+    /// Which layer of the block texture array to draw.
+    pub texture_layer: u32,
 }
 
 impl ChunkMeshVertex {
@@ -440,7 +262,7 @@ impl ChunkMeshVertex {
                 wgpu::VertexAttributeDescriptor {
                     offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
                     shader_location: 2,
-                    format: wgpu::VertexFormat::Float
+                    format: wgpu::VertexFormat::Uint,
                 },
             ],
         }]
@@ -449,3 +271,21 @@ impl ChunkMeshVertex {
 
 unsafe impl bytemuck::Pod for ChunkMeshVertex {}
 unsafe impl bytemuck::Zeroable for ChunkMeshVertex {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_meshes_are_thrown_away() {
+        let mut chunk = Chunk::empty(0, 0, 0);
+
+        // the chunk moves on to a new level of detail before the first mesh is done
+        let stale = chunk.start_mesh(2, [2; 6]).run();
+        let fresh = chunk.start_mesh(4, [4; 6]).run();
+
+        assert!(!chunk.is_current(&stale));
+        assert!(chunk.is_current(&fresh));
+        assert_eq!(fresh.lod_factor, 4);
+    }
+}
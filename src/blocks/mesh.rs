@@ -0,0 +1,638 @@
+use super::*;
+use crate::blocks::Block;
+use crate::textures::BlockTextureIndex;
+use crate::world::{Axis, Direction};
+
+/// Blocks to be meshed away from the main thread, usually at a lower level of detail.
+pub struct MeshJob {
+    pub grid: BlockGrid,
+
+    /// World position of the chunk's bottom, south-east corner.
+    pub origin: [f32; 3],
+
+    /// How many blocks wide each cell of the finished mesh should be.
+    pub lod_factor: usize,
+
+    /// How many blocks wide the cells of each neighboring chunk are, by `Direction::index`, but
+    /// never more than `lod_factor`.
+    pub neighbor_lod_factors: [usize; 6],
+
+    /// The chunk's mesh revision when the job was made.
+    pub revision: u64,
+}
+
+impl MeshJob {
+    /// Downsamples and meshes the blocks. This is the slow part, so it's safe to call from any
+    /// thread.
+    ///
+    /// The outer layer of cells next to a more detailed neighbor is meshed at the neighbor's level
+    /// of detail instead of the chunk's. Both chunks downsample the same blocks the same way there,
+    /// so their surfaces meet at the same height and their quads share corners, without steps or
+    /// T-junctions along the border. What's left of the step is moved one cell into the coarser
+    /// chunk.
+    pub fn run(self) -> ChunkMesh {
+        let mut factors: Vec<usize> = self.neighbor_lod_factors.to_vec();
+        factors.push(self.lod_factor);
+        factors.sort_unstable();
+        factors.dedup();
+
+        let mut vertices = Vec::new();
+        for &factor in factors.iter() {
+            let cells = self.grid.downsampled(factor).only(|i, j, k| {
+                self.cell_factor_at((i * factor, j * factor, k * factor)) == factor
+            });
+            vertices.append(&mut cells.greedy_mesh(self.origin, factor));
+        }
+
+        ChunkMesh {
+            vertices,
+            lod_factor: self.lod_factor,
+            revision: self.revision,
+        }
+    }
+
+    /// Returns how many blocks wide the cell holding the block should be: the chunk's own
+    /// `lod_factor`, unless the block is in a cell on a face of the chunk next to a more detailed
+    /// neighbor. Cells on an edge or corner go with the most detailed neighbor they touch.
+    fn cell_factor_at(&self, block: (usize, usize, usize)) -> usize {
+        let last = self.grid.width() / self.lod_factor - 1;
+        let cell = [
+            block.0 / self.lod_factor,
+            block.1 / self.lod_factor,
+            block.2 / self.lod_factor,
+        ];
+
+        let mut factor = self.lod_factor;
+        for &direction in Direction::ALL.iter() {
+            let (di, dj, dk) = direction.offset();
+            let on_face = [di, dj, dk]
+                .iter()
+                .zip(cell.iter())
+                .any(|(&d, &c)| (d < 0 && c == 0) || (d > 0 && c == last));
+
+            if on_face {
+                factor = factor.min(self.neighbor_lod_factors[direction.index()]);
+            }
+        }
+
+        factor
+    }
+}
+
+/// The vertices of a finished MeshJob, ready to be given back to its chunk.
+pub struct ChunkMesh {
+    pub vertices: Vec<ChunkMeshVertex>,
+    pub lod_factor: usize,
+    pub revision: u64,
+}
+
+/// A cube of blocks that can be turned into a greedy mesh. A chunk's blocks make a grid of
+/// `CHUNK_BLOCK_WIDTH`, and downsampled copies of them make smaller grids for chunks far from the
+/// camera.
+#[derive(Clone, Debug)]
+pub struct BlockGrid {
+    width: usize,
+
+    /// Blocks stored x-major: the block at (i, j, k) is at `(i * width + j) * width + k`.
+    blocks: Vec<Block>,
+}
+
+impl BlockGrid {
+    /// Copies a chunk's blocks into a grid.
+    pub fn from_blocks(
+        blocks: &[[[Block; CHUNK_BLOCK_WIDTH]; CHUNK_BLOCK_WIDTH]; CHUNK_BLOCK_WIDTH],
+    ) -> Self {
+        let mut grid =
+            Vec::with_capacity(CHUNK_BLOCK_WIDTH * CHUNK_BLOCK_WIDTH * CHUNK_BLOCK_WIDTH);
+        for plane in blocks.iter() {
+            for row in plane.iter() {
+                grid.extend_from_slice(row);
+            }
+        }
+
+        Self {
+            width: CHUNK_BLOCK_WIDTH,
+            blocks: grid,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get(&self, i: usize, j: usize, k: usize) -> &Block {
+        &self.blocks[(i * self.width + j) * self.width + k]
+    }
+
+    /// Returns a grid `factor` times smaller on each side, where every cell stands in for a
+    /// `factor`-wide cube of blocks. `factor` should evenly divide the grid's width. A cell is only
+    /// filled, with the most common non-air block in its cube, if at least half of the cube is
+    /// filled. That keeps coarse surfaces within half a cell of the real terrain, so they don't
+    /// stand out as steps where they meet chunks with more detail.
+    pub fn downsampled(&self, factor: usize) -> Self {
+        if factor <= 1 {
+            return self.clone();
+        }
+
+        let width = self.width / factor;
+        let mut blocks = Vec::with_capacity(width * width * width);
+
+        for ci in 0..width {
+            for cj in 0..width {
+                for ck in 0..width {
+                    blocks.push(self.cell_for_cube(ci, cj, ck, factor));
+                }
+            }
+        }
+
+        Self { width, blocks }
+    }
+
+    /// Returns a copy of the grid with every cell that `keep` turns down replaced with air.
+    fn only<F>(mut self, keep: F) -> Self
+    where
+        F: Fn(usize, usize, usize) -> bool,
+    {
+        let width = self.width;
+        for i in 0..width {
+            for j in 0..width {
+                for k in 0..width {
+                    if !keep(i, j, k) {
+                        self.blocks[(i * width + j) * width + k] = Block::from(BlockType::Air);
+                    }
+                }
+            }
+        }
+
+        self
+    }
+
+    fn cell_for_cube(&self, ci: usize, cj: usize, ck: usize, factor: usize) -> Block {
+        // there are only a handful of block types, so a small list beats a hash map here
+        let mut counts: Vec<(Block, usize)> = Vec::new();
+        let mut filled = 0;
+
+        for i in (ci * factor)..((ci + 1) * factor).min(self.width) {
+            for j in (cj * factor)..((cj + 1) * factor).min(self.width) {
+                for k in (ck * factor)..((ck + 1) * factor).min(self.width) {
+                    let block = self.get(i, j, k);
+                    if block.should_skip_mesh() {
+                        continue;
+                    }
+
+                    filled += 1;
+                    match counts
+                        .iter_mut()
+                        .find(|(b, _)| b.block_type == block.block_type)
+                    {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((*block, 1)),
+                    }
+                }
+            }
+        }
+
+        let air = Block::from(BlockType::Air);
+        if filled * 2 < factor * factor * factor {
+            return air;
+        }
+
+        counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(block, _)| block)
+            .unwrap_or(air)
+    }
+
+    /// Makes the greedy mesh for the grid. `origin` is the world position of the grid's bottom,
+    /// south-east corner, and `lod_factor` is how many blocks wide each cell is.
+    ///
+    /// Every quad gets both of its faces, so the mesh is closed at the edges of the grid.
+    pub fn greedy_mesh(&self, origin: [f32; 3], lod_factor: usize) -> Vec<ChunkMeshVertex> {
+        let mut vertices = Vec::<ChunkMeshVertex>::new();
+
+        vertices.append(&mut self.get_greedy_mesh_along_axis(Axis::X, origin, lod_factor));
+        vertices.append(&mut self.get_greedy_mesh_along_axis(Axis::Y, origin, lod_factor));
+        vertices.append(&mut self.get_greedy_mesh_along_axis(Axis::Z, origin, lod_factor));
+
+        vertices
+    }
+
+    // TODO: potentially separate traversals by Direction instead of Axis to further cull adjacent
+    // or hidden faces
+    fn get_greedy_mesh_along_axis(
+        &self,
+        along_axis: Axis,
+        origin: [f32; 3],
+        lod_factor: usize,
+    ) -> Vec<ChunkMeshVertex> {
+        let mut vertices = Vec::<ChunkMeshVertex>::new();
+
+        // `layer` is basically the layer along whichever axis we're traversing
+        for layer in 0..self.width {
+            let mut visited_mask = vec![vec![false; self.width]; self.width];
+
+            for i in 0..self.width {
+                for j in 0..self.width {
+                    // the block at the starting point that we have to match
+                    let reference_block = self.get_block_along_layer(along_axis, layer, i, j);
+
+                    // decide if we should just skip over this block for meshing
+                    if reference_block.should_skip_mesh() || visited_mask[i][j] {
+                        continue;
+                    }
+
+                    // just mark this spot as visited while we're on it
+                    visited_mask[i][j] = true;
+
+                    // starting_point should not be mut
+                    let starting_point = (i, j);
+
+                    // inclusive ending! ending_point is also included in the final mesh
+                    let mut ending_point = (i, j);
+
+                    // traverse along the "i" axis first, getting the ending_point's `i` (start
+                    // at/skip i + 1 because the block at i is the reference_block, which doesn't
+                    // need to be matched to itself, silly :))
+                    for (k, flag_row) in visited_mask.iter_mut().enumerate().skip(i + 1) {
+                        // if blocks are still the same type, move the ending_point
+                        if self
+                            .get_block_along_layer(along_axis, layer, k, j)
+                            .block_type
+                            == reference_block.block_type
+                        {
+                            // block types match, so we can move the ending_point!
+                            ending_point.0 = k;
+                            flag_row[j] = true;
+                        } else {
+                            // block types don't match, so don't advance ending_point, don't mark
+                            // this position as visited, do not pass go, do not collect $200, just
+                            // break
+                            break;
+                        }
+                    }
+
+                    // now, we have to traverse along both the "i" the "j" axis (up until
+                    // ending_point's "i" component), making sure all blocks still match
+                    // reference_block's type. if we can move along the "j" axis and all block
+                    // types are still the same, we can advance ending_point.
+                    //
+                    // why "j" axis first? for each iteration, we need to traverse along the i axis
+                    // to accurately decide if we can *extend* the mesh in the direction of j. we
+                    // start at j + 1 because it's already been decided that all blocks along j
+                    // match.
+                    for l in (j + 1)..self.width {
+                        // go as far as we can while block types match. if even one of the blocks
+                        // along the i axis here don't match, we have to stop. if all blocks in
+                        // this row match, we can advance ending_point's j component.
+                        //
+                        // remember, ending_point is inclusive, which is why we go up to and equal
+                        // to ending_point.0
+                        let mut should_advance = true;
+                        for (m, flag_row) in visited_mask
+                            .iter_mut()
+                            .enumerate()
+                            .take(ending_point.0 + 1)
+                            .skip(i)
+                        {
+                            if self
+                                .get_block_along_layer(along_axis, layer, m, l)
+                                .block_type
+                                != reference_block.block_type
+                            {
+                                // came across a mismatching block, so signal no advancing and
+                                // leave this loop
+                                should_advance = false;
+                                break;
+                            }
+                            flag_row[l] = true;
+                        }
+
+                        if should_advance {
+                            ending_point.1 = l;
+                        // then we'll move onto the next row
+                        } else {
+                            // if we came across a block that doesn't match in this row, we have to
+                            // reset all visited flags in this row
+                            for row_flags in
+                                visited_mask.iter_mut().take(ending_point.0 + 1).skip(i)
+                            {
+                                row_flags[l] = false;
+                            }
+
+                            // and leave this loop
+                            break;
+                        }
+                    }
+
+                    // now, at this point, we should have a starting_point and ending_point. we can
+                    // use this data to make our mesh:
+
+                    let width = ending_point.0 - starting_point.0 + 1;
+                    let height = ending_point.1 - starting_point.1 + 1;
+
+                    let (direction, other_direction) = match along_axis {
+                        Axis::X => (Direction::East, Direction::West),
+                        Axis::Y => (Direction::Up, Direction::Down),
+                        Axis::Z => (Direction::North, Direction::South),
+                    };
+                    let quad_start = match along_axis {
+                        Axis::X => (layer, starting_point.0, starting_point.1),
+                        Axis::Y => (starting_point.0, layer, starting_point.1),
+                        Axis::Z => (starting_point.0, starting_point.1, layer),
+                    };
+                    if let Ok(texture_layer) = BlockTextureIndex::from_type_and_direction(
+                        reference_block.block_type,
+                        direction,
+                    ) {
+                        vertices.append(&mut Self::get_quad_face_vertices(
+                            quad_start,
+                            width,
+                            height,
+                            texture_layer,
+                            direction,
+                            origin,
+                            lod_factor,
+                        ));
+                    }
+                    if let Ok(other_texture_layer) = BlockTextureIndex::from_type_and_direction(
+                        reference_block.block_type,
+                        other_direction,
+                    ) {
+                        vertices.append(&mut Self::get_quad_face_vertices(
+                            quad_start,
+                            width,
+                            height,
+                            other_texture_layer,
+                            other_direction,
+                            origin,
+                            lod_factor,
+                        ));
+                    }
+                }
+            }
+        }
+
+        vertices
+    }
+
+    fn get_block_along_layer(&self, along_axis: Axis, layer: usize, i: usize, j: usize) -> &Block {
+        match along_axis {
+            Axis::X => self.get(layer, j, i), // becuase "i" is the z axis
+            Axis::Y => self.get(i, layer, j),
+            Axis::Z => self.get(i, j, layer),
+        }
+    }
+
+    fn get_quad_face_vertices(
+        start_grid_pos: (usize, usize, usize),
+        width: usize,
+        height: usize,
+        texture_layer: BlockTextureIndex,
+        face_direction: Direction,
+        origin: [f32; 3],
+        lod_factor: usize,
+    ) -> Vec<ChunkMeshVertex> {
+        // each cell of a downsampled grid covers `lod_factor` blocks in every direction
+        let cell_width = Block::WIDTH * lod_factor as f32;
+
+        // the starting block's exact position within the chunk (in other words, relative to the
+        // chunk, not the world)
+        let block_grid_pos = [
+            start_grid_pos.0 as f32 * cell_width,
+            start_grid_pos.1 as f32 * cell_width,
+            start_grid_pos.2 as f32 * cell_width,
+        ];
+
+        // the bottom, south-east vertex position of the cuboid
+        let base_vertex_pos = [
+            origin[0] + block_grid_pos[0],
+            origin[1] + block_grid_pos[1],
+            origin[2] + block_grid_pos[2],
+        ];
+
+        let (quad_width, quad_height) = (width as f32 * cell_width, height as f32 * cell_width);
+
+        let (lower_left_pos, lower_right_pos, upper_right_pos, upper_left_pos) =
+            match face_direction {
+                Direction::North => {
+                    let z = base_vertex_pos[2] + cell_width;
+                    (
+                        [base_vertex_pos[0], base_vertex_pos[1], z],
+                        [base_vertex_pos[0] + quad_width, base_vertex_pos[1], z],
+                        [
+                            base_vertex_pos[0] + quad_width,
+                            base_vertex_pos[1] + quad_height,
+                            z,
+                        ],
+                        [base_vertex_pos[0], base_vertex_pos[1] + quad_height, z],
+                    )
+                }
+                Direction::South => {
+                    let z = base_vertex_pos[2];
+                    (
+                        [base_vertex_pos[0] + quad_width, base_vertex_pos[1], z],
+                        [base_vertex_pos[0], base_vertex_pos[1], z],
+                        [base_vertex_pos[0], base_vertex_pos[1] + quad_height, z],
+                        [
+                            base_vertex_pos[0] + quad_width,
+                            base_vertex_pos[1] + quad_height,
+                            z,
+                        ],
+                    )
+                }
+                Direction::East => {
+                    let x = base_vertex_pos[0];
+                    (
+                        [x, base_vertex_pos[1], base_vertex_pos[2]],
+                        [x, base_vertex_pos[1], base_vertex_pos[2] + quad_width],
+                        [
+                            x,
+                            base_vertex_pos[1] + quad_height,
+                            base_vertex_pos[2] + quad_width,
+                        ],
+                        [x, base_vertex_pos[1] + quad_height, base_vertex_pos[2]],
+                    )
+                }
+                Direction::West => {
+                    let x = base_vertex_pos[0] + cell_width;
+                    (
+                        [x, base_vertex_pos[1], base_vertex_pos[2] + quad_width],
+                        [x, base_vertex_pos[1], base_vertex_pos[2]],
+                        [x, base_vertex_pos[1] + quad_height, base_vertex_pos[2]],
+                        [
+                            x,
+                            base_vertex_pos[1] + quad_height,
+                            base_vertex_pos[2] + quad_width,
+                        ],
+                    )
+                }
+                Direction::Up => {
+                    let y = base_vertex_pos[1] + cell_width;
+                    (
+                        [base_vertex_pos[0] + quad_width, y, base_vertex_pos[2]],
+                        [base_vertex_pos[0], y, base_vertex_pos[2]],
+                        [base_vertex_pos[0], y, base_vertex_pos[2] + quad_height],
+                        [
+                            base_vertex_pos[0] + quad_width,
+                            y,
+                            base_vertex_pos[2] + quad_height,
+                        ],
+                    )
+                }
+                Direction::Down => {
+                    let y = base_vertex_pos[1];
+                    (
+                        [base_vertex_pos[0], y, base_vertex_pos[2]],
+                        [base_vertex_pos[0] + quad_width, y, base_vertex_pos[2]],
+                        [
+                            base_vertex_pos[0] + quad_width,
+                            y,
+                            base_vertex_pos[2] + quad_height,
+                        ],
+                        [base_vertex_pos[0], y, base_vertex_pos[2] + quad_height],
+                    )
+                }
+            };
+
+        let texture_layer = texture_layer.layer();
+
+        // repeat the texture once per block, not once per cell, so distant chunks look the same as
+        // near ones
+        let (width, height) = (width * lod_factor, height * lod_factor);
+
+        let (lower_left, lower_right, upper_right, upper_left) = (
+            ChunkMeshVertex {
+                position: lower_left_pos,
+                uv_coords: [0.0, height as f32],
+                texture_layer,
+            },
+            ChunkMeshVertex {
+                position: lower_right_pos,
+                uv_coords: [width as f32, height as f32],
+                texture_layer,
+            },
+            ChunkMeshVertex {
+                position: upper_right_pos,
+                uv_coords: [width as f32, 0.0],
+                texture_layer,
+            },
+            ChunkMeshVertex {
+                position: upper_left_pos,
+                uv_coords: [0.0, 0.0],
+                texture_layer,
+            },
+        );
+
+        vec![
+            lower_left,
+            lower_right,
+            upper_left,
+            upper_left,
+            lower_right,
+            upper_right,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_with_floor(height: usize) -> BlockGrid {
+        let mut blocks = [[[Block::from(BlockType::Air); CHUNK_BLOCK_WIDTH]; CHUNK_BLOCK_WIDTH];
+            CHUNK_BLOCK_WIDTH];
+        for plane in blocks.iter_mut() {
+            for (j, row) in plane.iter_mut().enumerate() {
+                if j < height {
+                    for block in row.iter_mut() {
+                        *block = Block::from(BlockType::Stone);
+                    }
+                }
+            }
+        }
+        BlockGrid::from_blocks(&blocks)
+    }
+
+    #[test]
+    fn downsampling_shrinks_the_grid() {
+        let grid = grid_with_floor(4);
+        assert_eq!(grid.downsampled(2).width(), CHUNK_BLOCK_WIDTH / 2);
+        assert_eq!(grid.downsampled(8).width(), CHUNK_BLOCK_WIDTH / 8);
+    }
+
+    #[test]
+    fn cells_are_filled_when_at_least_half_full() {
+        for &factor in [2, 4, 8].iter() {
+            let half = grid_with_floor(factor / 2).downsampled(factor);
+            assert_eq!(half.get(0, 0, 0).block_type, BlockType::Stone);
+            assert_eq!(half.get(0, 1, 0).block_type, BlockType::Air);
+
+            // one block short of half would stick up too far above the real floor, so it's left
+            // out
+            let mut thin = grid_with_floor(factor / 2);
+            thin.blocks[0] = Block::from(BlockType::Air);
+            let thin = thin.downsampled(factor);
+            assert_eq!(thin.get(0, 0, 0).block_type, BlockType::Air);
+            assert_eq!(thin.get(1, 0, 0).block_type, BlockType::Stone);
+        }
+    }
+
+    #[test]
+    fn downsampled_meshes_cover_the_same_space() {
+        // the top of a full-detail floor and an 8x floor are both at the top of the first cell
+        let mesh = grid_with_floor(8).downsampled(8).greedy_mesh([0.0; 3], 8);
+        let top = mesh.iter().map(|v| v.position[1]).fold(f32::MIN, f32::max);
+
+        assert!((top - 8.0 * Block::WIDTH).abs() < 1e-5);
+    }
+
+    #[test]
+    fn borders_match_more_detailed_neighbors() {
+        // a floor five blocks deep, which 8x cells round up to eight
+        let floor = grid_with_floor(5);
+
+        let fine = MeshJob {
+            grid: floor.clone(),
+            origin: [-CHUNK_SIZE, 0.0, 0.0],
+            lod_factor: 1,
+            neighbor_lod_factors: [1; 6],
+            revision: 0,
+        }
+        .run();
+
+        let mut neighbor_lod_factors = [8; 6];
+        neighbor_lod_factors[Direction::East.index()] = 1;
+        let coarse = MeshJob {
+            grid: floor,
+            origin: [0.0; 3],
+            lod_factor: 8,
+            neighbor_lod_factors,
+            revision: 0,
+        }
+        .run();
+
+        // every corner either chunk puts on the plane between them
+        let on_border = |mesh: &ChunkMesh| {
+            let mut corners: Vec<[f32; 3]> = mesh
+                .vertices
+                .iter()
+                .map(|v| v.position)
+                .filter(|p| p[0].abs() < 1e-5)
+                .collect();
+            corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            corners.dedup();
+            corners
+        };
+
+        assert_eq!(on_border(&fine), on_border(&coarse));
+
+        // away from the border, the coarse chunk still rounds the floor up
+        let top = coarse
+            .vertices
+            .iter()
+            .map(|v| v.position[1])
+            .fold(f32::MIN, f32::max);
+        assert!((top - 8.0 * Block::WIDTH).abs() < 1e-5);
+    }
+}
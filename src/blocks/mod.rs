@@ -1,9 +1,15 @@
 pub mod chunk;
+pub mod mesh;
 pub mod render;
 pub mod textures;
+pub mod visibility;
 
 pub use self::chunk::*;
+pub use self::mesh::*;
 pub use self::textures::*;
+pub use self::visibility::*;
+
+use crate::physics::{Fluid, Surface};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Block {
@@ -35,7 +41,9 @@ impl Block {
             BlockType::Grass => 15.0,
             BlockType::Stone => 50.0,
             BlockType::Sand => 5.0,
-            BlockType::Air => 0.0,
+            BlockType::Ice => 8.0,
+            BlockType::Mud => 5.0,
+            BlockType::Air | BlockType::Water => 0.0,
             _ => {
                 eprintln!("BlockType `{:?}` not implemented. Lifespan unknown!", ty);
                 0.0
@@ -47,9 +55,26 @@ impl Block {
         self.block_type == BlockType::Air
     }
 
+    pub fn block_type(&self) -> BlockType {
+        self.block_type
+    }
+
+    /// Returns true if bodies can't pass through the block.
+    pub fn is_solid(&self) -> bool {
+        self.block_type != BlockType::Air && self.fluid().is_none()
+    }
+
+    pub fn fluid(&self) -> Option<Fluid> {
+        self.block_type.definition().fluid
+    }
+
+    pub fn surface(&self) -> Surface {
+        self.block_type.definition().surface
+    }
+
     pub fn is_see_through(&self) -> bool {
         match self.block_type {
-            BlockType::Air | BlockType::Glass => true,
+            BlockType::Air | BlockType::Glass | BlockType::Water => true,
             _ => false,
         }
     }
@@ -74,4 +99,87 @@ pub enum BlockType {
     Furnace,
     Leaves,
     Glass,
+    Ice,
+    Mud,
+    Water,
+}
+
+impl BlockType {
+    /// Returns what every block of this type has in common.
+    pub fn definition(self) -> &'static BlockDefinition {
+        &DEFINITIONS[self as usize]
+    }
+}
+
+/// BlockDefinition is the tuning shared by every block of a type.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockDefinition {
+    /// The type of block this defines.
+    pub block_type: BlockType,
+
+    /// How the block feels to walk on.
+    pub surface: Surface,
+
+    /// What the block is made of if it's a fluid, like water.
+    pub fluid: Option<Fluid>,
+}
+
+impl BlockDefinition {
+    const fn plain(block_type: BlockType) -> Self {
+        Self {
+            block_type,
+            surface: Surface::DEFAULT,
+            fluid: None,
+        }
+    }
+
+    const fn on(block_type: BlockType, friction: f32, speed_factor: f32) -> Self {
+        Self {
+            block_type,
+            surface: Surface {
+                friction,
+                speed_factor,
+            },
+            fluid: None,
+        }
+    }
+}
+
+/// The definition of every BlockType, in the order they're declared.
+const DEFINITIONS: [BlockDefinition; 19] = [
+    BlockDefinition::plain(BlockType::Air),
+    BlockDefinition::on(BlockType::Sand, 1.2, 0.6),
+    BlockDefinition::plain(BlockType::Dirt),
+    BlockDefinition::plain(BlockType::Grass),
+    BlockDefinition::plain(BlockType::Stone),
+    BlockDefinition::plain(BlockType::Cobblestone),
+    BlockDefinition::plain(BlockType::CoalOre),
+    BlockDefinition::plain(BlockType::IronOre),
+    BlockDefinition::plain(BlockType::CopperOre),
+    BlockDefinition::plain(BlockType::GoldOre),
+    BlockDefinition::plain(BlockType::DiamondOre),
+    BlockDefinition::plain(BlockType::Wood),
+    BlockDefinition::plain(BlockType::WoodPlanks),
+    BlockDefinition::plain(BlockType::Furnace),
+    BlockDefinition::on(BlockType::Leaves, 0.8, 0.8),
+    BlockDefinition::plain(BlockType::Glass),
+    BlockDefinition::on(BlockType::Ice, 0.01, 1.0),
+    BlockDefinition::on(BlockType::Mud, 2.0, 0.35),
+    BlockDefinition {
+        block_type: BlockType::Water,
+        surface: Surface::DEFAULT,
+        fluid: Some(Fluid::WATER),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definitions_are_in_declaration_order() {
+        for (i, definition) in DEFINITIONS.iter().enumerate() {
+            assert_eq!(definition.block_type as usize, i);
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::engine::texture::mipmap::with_alpha_cutoff;
+
 /// NOTE: Why are we passing in block_texture_bind_group_layout when we could just make it here? I
 /// think making it more than once causes inconsistencies between bind groups.
 pub fn make_chunk_render_pipeline(
@@ -18,7 +20,7 @@ pub fn make_chunk_render_pipeline(
     // compile texture shaders
     let (vs_module, fs_module) = engine.compile_shader_modules(
         include_str!("../shaders/block.vert"),
-        include_str!("../shaders/block.frag"),
+        &with_alpha_cutoff(include_str!("../shaders/block.frag")),
     )?;
 
     let render_pipeline_layout =
@@ -0,0 +1,140 @@
+use super::CHUNK_BLOCK_WIDTH;
+use crate::world::Direction;
+
+/// ChunkVisibility records which pairs of a chunk's six faces are connected through see-through
+/// blocks. If you can't walk (or look) from one face to another through air or glass, nothing
+/// behind the second face can be seen through the first. The renderer uses this to skip chunks
+/// hidden behind solid terrain, like caves underground.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChunkVisibility {
+    /// One bitmask per face, indexed by `Direction::index`. Bit `n` of `connections[m]` is set if
+    /// face `m` can see face `n`.
+    connections: [u8; 6],
+}
+
+impl ChunkVisibility {
+    /// Every face can see every other face, like a chunk full of air.
+    pub const ALL: Self = Self {
+        connections: [0b11_1111; 6],
+    };
+
+    /// No face can see any other face, like a chunk full of stone.
+    pub const NONE: Self = Self {
+        connections: [0; 6],
+    };
+
+    /// Flood-fills the see-through blocks of a chunk, connecting every pair of faces that each
+    /// filled region touches. `is_see_through` is given array indices (i, j, k).
+    pub fn compute<F>(is_see_through: F) -> Self
+    where
+        F: Fn(usize, usize, usize) -> bool,
+    {
+        const W: usize = CHUNK_BLOCK_WIDTH;
+        let flat = |i: usize, j: usize, k: usize| (i * W + j) * W + k;
+
+        let mut visibility = Self::NONE;
+        let mut visited = vec![false; W * W * W];
+        let mut stack = Vec::new();
+
+        for i in 0..W {
+            for j in 0..W {
+                for k in 0..W {
+                    if visited[flat(i, j, k)] || !is_see_through(i, j, k) {
+                        continue;
+                    }
+
+                    // faces touched by this region, as a bitmask
+                    let mut touched = 0u8;
+
+                    visited[flat(i, j, k)] = true;
+                    stack.push((i, j, k));
+
+                    while let Some((x, y, z)) = stack.pop() {
+                        for &direction in Direction::ALL.iter() {
+                            let (dx, dy, dz) = direction.offset();
+                            let (nx, ny, nz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+
+                            let w = W as i64;
+                            if nx < 0 || ny < 0 || nz < 0 || nx >= w || ny >= w || nz >= w {
+                                // stepping out of the chunk means this region touches that face
+                                touched |= 1 << direction.index();
+                                continue;
+                            }
+
+                            let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+                            if !visited[flat(nx, ny, nz)] && is_see_through(nx, ny, nz) {
+                                visited[flat(nx, ny, nz)] = true;
+                                stack.push((nx, ny, nz));
+                            }
+                        }
+                    }
+
+                    for face in 0..6 {
+                        if touched & (1 << face) != 0 {
+                            visibility.connections[face] |= touched;
+                        }
+                    }
+                }
+            }
+        }
+
+        visibility
+    }
+
+    /// Returns true if something entering the chunk through face `from` can exit through face
+    /// `to`.
+    pub fn connects(&self, from: Direction, to: Direction) -> bool {
+        self.connections[from.index()] & (1 << to.index()) != 0
+    }
+}
+
+impl Default for ChunkVisibility {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = CHUNK_BLOCK_WIDTH;
+
+    #[test]
+    fn empty_chunk_connects_everything() {
+        assert_eq!(
+            ChunkVisibility::compute(|_, _, _| true),
+            ChunkVisibility::ALL
+        );
+    }
+
+    #[test]
+    fn solid_chunk_connects_nothing() {
+        assert_eq!(
+            ChunkVisibility::compute(|_, _, _| false),
+            ChunkVisibility::NONE
+        );
+    }
+
+    #[test]
+    fn solid_floor_separates_up_and_down() {
+        // a solid layer halfway up the chunk
+        let v = ChunkVisibility::compute(|_, j, _| j != W / 2);
+
+        assert!(!v.connects(Direction::Up, Direction::Down));
+        assert!(!v.connects(Direction::Down, Direction::Up));
+        assert!(v.connects(Direction::Up, Direction::North));
+        assert!(v.connects(Direction::East, Direction::West));
+    }
+
+    #[test]
+    fn tunnel_connects_only_its_ends() {
+        // a one-block tunnel running along the x axis through solid rock
+        let v = ChunkVisibility::compute(|_, j, k| j == 4 && k == 4);
+
+        assert!(v.connects(Direction::East, Direction::West));
+        assert!(v.connects(Direction::West, Direction::East));
+        assert!(!v.connects(Direction::East, Direction::Up));
+        assert!(!v.connects(Direction::North, Direction::South));
+    }
+}
@@ -34,6 +34,42 @@ impl Camera {
         &self.matrix
     }
 
+    /// Returns the planes bounding what the camera can currently see.
+    pub fn get_frustum(&self) -> crate::maths::Frustum {
+        crate::maths::Frustum::from_matrix(&self.matrix)
+    }
+
+    pub fn get_position(&self) -> cgmath::Point3<f32> {
+        self.eye
+    }
+
+    /// Returns which way is right on the screen, as a unit vector in the world.
+    pub fn get_right(&self) -> cgmath::Vector3<f32> {
+        use cgmath::InnerSpace;
+
+        let right = (self.target - self.eye).cross(self.up);
+
+        // looking straight along the up axis, any direction across it will do
+        if right.magnitude2() > 0.0 {
+            right.normalize()
+        } else {
+            cgmath::Vector3::unit_x()
+        }
+    }
+
+    /// Returns which way is up on the screen, as a unit vector in the world. Unlike the up axis,
+    /// this tilts forward and back as the camera looks down and up.
+    pub fn get_screen_up(&self) -> cgmath::Vector3<f32> {
+        use cgmath::InnerSpace;
+
+        let forward = self.target - self.eye;
+        if forward.magnitude2() > 0.0 {
+            self.get_right().cross(forward).normalize()
+        } else {
+            self.up
+        }
+    }
+
     pub fn jump_position_to(&mut self, position: (f32, f32, f32)) {
         self.eye = position.into();
         self.update_view_projection_matrix()
@@ -204,10 +240,10 @@ impl CameraController {
             match keycode {
                 VirtualKeyCode::Space => self.is_up_pressed = is_pressed,
                 VirtualKeyCode::LShift => self.is_down_pressed = is_pressed,
-                VirtualKeyCode::W | VirtualKeyCode::Up => self.is_forward_pressed = is_pressed,
-                VirtualKeyCode::A | VirtualKeyCode::Left => self.is_left_pressed = is_pressed,
-                VirtualKeyCode::S | VirtualKeyCode::Down => self.is_backward_pressed = is_pressed,
-                VirtualKeyCode::D | VirtualKeyCode::Right => self.is_right_pressed = is_pressed,
+                VirtualKeyCode::W => self.is_forward_pressed = is_pressed,
+                VirtualKeyCode::A => self.is_left_pressed = is_pressed,
+                VirtualKeyCode::S => self.is_backward_pressed = is_pressed,
+                VirtualKeyCode::D => self.is_right_pressed = is_pressed,
                 _ => (),
             }
         }
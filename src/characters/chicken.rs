@@ -1,84 +1,366 @@
-use crate::characters::{Character, CharacterAction, Direction, FacingDirection};
+use crate::characters::{
+    Character, CharacterAction, Damage, DamageSource, Direction, FacingDirection, Health, Hunger,
+    Stamina,
+};
+use crate::entities::{Attack, EntityId};
 use crate::items;
-use crate::items::{Backpack, Item, ItemStack};
+use crate::items::{Backpack, Food, Item, ItemStack};
 use crate::maths::AABB;
-use crate::physics::PhysicalObject;
+use crate::physics::{BodyHandle, PhysicalObject, PhysicsWorld, SwimState};
+use crate::sprite::{Animation, Billboard, Playback, Sprite, SpriteInstance, SpriteLayers};
 use crate::traits::{Killable, Logicable, Renderable};
-use cgmath::Vector3;
+use cgmath::{InnerSpace, Vector3};
+
+/// How fast the chicken walks and runs, in meters per second.
+const WALK_SPEED: f32 = 2.0;
+const RUN_SPEED: f32 = 5.0;
+
+/// How fast the chicken leaves the ground when it jumps, in meters per second. Enough to hop up
+/// onto a block with a little room to spare.
+const JUMP_SPEED: f32 = 4.0;
+
+/// How quickly the chicken can speed up, slow down, or turn around, in meters per second per
+/// second. It has a lot less control while it's in the air.
+const GROUND_ACCELERATION: f32 = 30.0;
+const AIR_ACCELERATION: f32 = 5.0;
+
+/// The chicken's mass, in kilograms.
+const MASS: f32 = 2.0;
+
+/// Half of the chicken's width, height, and depth, in meters.
+const HALF_SIZE: (f32, f32, f32) = (0.2, 0.25, 0.2);
+
+/// How fast the chicken can go once it's worn itself out, in meters per second.
+const TIRED_SPEED: f32 = 1.0;
+
+/// How much stamina the chicken has, and how much it gets back each second it's on the ground
+/// and not sprinting.
+const STAMINA: f32 = 10.0;
+const STAMINA_RECOVERY: f32 = 2.0;
+
+/// How much stamina sprinting (each second) and jumping use up. Five seconds of sprinting wears
+/// the chicken out.
+const SPRINT_STAMINA: f32 = 2.0;
+const JUMP_STAMINA: f32 = 1.5;
+
+/// How many health points a healthy chicken has, and how many it gets back each second.
+const LIFESPAN: f32 = 10.0;
+const REGENERATION: f32 = 0.1;
+
+/// How long after getting hurt the chicken can't be hurt again, in seconds.
+const INVULNERABILITY: f32 = 0.5;
+
+/// How long the chicken spends dying before it's gone, in seconds.
+const DYING_TIME: f32 = 1.0;
+
+/// How fast the chicken can hit the ground without getting hurt, in meters per second, and how
+/// much each meter per second faster than that hurts. Falls of about three meters are safe.
+const SAFE_LANDING_SPEED: f32 = 8.0;
+const FALL_DAMAGE: f32 = 1.0;
+
+/// How long the chicken can hold its breath under water, in seconds, and how much it hurts each
+/// second after that.
+const BREATH: f32 = 10.0;
+const DROWNING_DAMAGE: f32 = 1.0;
+
+/// How much walking and running (each second) and jumping tire the chicken out, using up its
+/// food. See `Hunger::EXHAUSTION_PER_POINT`.
+const WALK_EXHAUSTION: f32 = 0.1;
+const RUN_EXHAUSTION: f32 = 0.8;
+const JUMP_EXHAUSTION: f32 = 0.2;
+
+/// How much regenerating tires the chicken out each second, so healing costs food too.
+const REGENERATION_EXHAUSTION: f32 = 0.6;
+
+/// How much starving hurts each second.
+const STARVATION_DAMAGE: f32 = 0.5;
+
+/// How wide the chicken's sprite is compared to how tall it is; every frame is 12 by 13 pixels.
+const SPRITE_ASPECT: f32 = 12.0 / 13.0;
 
 /// The main character of this game. we ain't callin it chicky chicky for nothing folks
 #[derive(Debug)]
 pub struct Chicken {
-    physical: PhysicalObject,
     backpack: Backpack,
     action: CharacterAction,
     facing: FacingDirection,
-    health: f32,
-    lifespan: f32,
-    // chicken_sprites: HashMap<CharacterAction, Sprite>,
+    health: Health,
+    hunger: Hunger,
+    stamina: Stamina,
+
+    /// What the chicken is eating and how many seconds are left until it's eaten.
+    eating: Option<(Food, f32)>,
+
+    /// Seconds of breath left under water.
+    breath: f32,
+
+    /// Seconds since the chicken died.
+    dead_for: f32,
+
+    /// What was in the backpack when the chicken died, until `take_spilled` takes it.
+    spilled: Backpack,
+
+    /// Plays the animation for `action`.
+    sprite: Sprite,
+    /// Where the player wants to go, set by `walk` and cleared by `stop`.
+    moving: Option<Direction>,
+    running: bool,
+    squatting: bool,
+
+    /// Set by `jump` until the next `steer` pushes off the ground.
+    jumping: bool,
+
+    /// Whether the chicken's body was standing on the ground and running into a wall after the
+    /// world last stepped, read back from the body by `settle`.
+    on_ground: bool,
+    pushing_wall: bool,
+
+    /// How fast the chicken's body was falling, in meters per second, when it was last steered.
+    falling_speed: f32,
 }
 
 impl Chicken {
     /// Creates and initializes a new Chicken
     pub fn new() -> Self {
-        // let c: Self = Default::default();
-
-        // c.chicken_sprites[CharacterAction::Nothing] =
-        //     Sprite::must_new("assets/photos/chicken/stand.png", 0, 0);
-        // c.chicken_sprites[CharacterAction::Run] =
-        //     Sprite::must_new("assets/photos/chicken/sprint.png", 4, 0.15);
-        // c.chicken_sprites[CharacterAction::Walk] =
-        //     Sprite::must_new("assets/photos/chicken/walk.png", 4, 0.2);
-        // c.chicken_sprites[CharacterAction::Squat] =
-        //     Sprite::must_new("assets/photos/chicken/squat.png", 0, 0);
-        // c.chicken_sprites[CharacterAction::Push] =
-        //     Sprite::must_new("assets/photos/chicken/push.png", 4, 0.75);
-        // c.chicken_sprites[CharacterAction::Fall] =
-        //     Sprite::must_new("assets/photos/chicken/fall.png", 2, 0.1);
-
         Default::default()
     }
+
+    pub fn action(&self) -> CharacterAction {
+        self.action
+    }
+
+    pub fn facing(&self) -> FacingDirection {
+        self.facing
+    }
+
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    pub fn hunger(&self) -> &Hunger {
+        &self.hunger
+    }
+
+    pub fn stamina(&self) -> &Stamina {
+        &self.stamina
+    }
+
+    /// Returns true if the chicken is running along the ground. Tired chickens can't sprint, no
+    /// matter how hard they try.
+    fn is_sprinting(&self) -> bool {
+        self.running
+            && !self.stamina.is_tired()
+            && self.on_ground
+            && self.target_velocity().magnitude2() > 0.0
+    }
+
+    /// Wears the chicken out while it sprints, and lets it catch its breath otherwise.
+    fn exert(&mut self, delta_sec: f32) {
+        if self.is_sprinting() {
+            self.stamina.drain(SPRINT_STAMINA * delta_sec);
+        } else if self.on_ground {
+            self.stamina.recover(delta_sec);
+        }
+    }
+
+    /// Starts eating `food`, which takes `Food::eating_time` seconds. The chicken stands still
+    /// while it eats, and walking, jumping or squatting stops it, putting the food in the
+    /// backpack. Gives the food back if the chicken can't eat right now: it's dead, in the air, or
+    /// already eating.
+    pub fn eat(&mut self, food: Food) -> Result<(), Food> {
+        if self.health.is_dead() || !self.on_ground || self.eating.is_some() {
+            return Err(food);
+        }
+
+        self.eating = Some((food, food.eating_time()));
+        self.update_action();
+        Ok(())
+    }
+
+    /// Stops eating, putting what was being eaten back in the backpack.
+    fn stop_eating(&mut self) {
+        if let Some((food, _)) = self.eating.take() {
+            store(&mut self.backpack, food);
+        }
+    }
+
+    /// Finishes meals, uses up food for whatever the chicken is doing, and heals or starves the
+    /// chicken depending on how much food is left.
+    fn digest(&mut self, delta_sec: f32) {
+        if let Some((food, left)) = self.eating {
+            if left > delta_sec {
+                self.eating = Some((food, left - delta_sec));
+            } else {
+                self.eating = None;
+                self.hunger.eat(food.nutrition());
+            }
+        }
+
+        if self.on_ground && self.target_velocity().magnitude2() > 0.0 {
+            let exhaustion = if self.is_sprinting() {
+                RUN_EXHAUSTION
+            } else {
+                WALK_EXHAUSTION
+            };
+            self.hunger.exhaust(exhaustion * delta_sec);
+        }
+
+        if self.hunger.is_well_fed() && self.health.is_hurt() {
+            self.health.regenerate(delta_sec);
+            self.hunger.exhaust(REGENERATION_EXHAUSTION * delta_sec);
+        }
+
+        if self.hunger.is_starving() {
+            let amount = STARVATION_DAMAGE * delta_sec;
+            self.damage(Damage::new(DamageSource::Starvation, amount));
+        }
+    }
+
+    /// Returns how many seconds the chicken can stay under water before it starts drowning.
+    pub fn breath(&self) -> f32 {
+        self.breath
+    }
+
+    pub fn backpack_mut(&mut self) -> &mut Backpack {
+        &mut self.backpack
+    }
+
+    /// Returns true if the chicken is standing on something to jump off of, and is up to it.
+    fn can_jump(&self) -> bool {
+        self.on_ground && !self.squatting && !self.stamina.is_tired() && !self.health.is_dead()
+    }
+
+    /// Returns the horizontal velocity the chicken is trying to reach.
+    fn target_velocity(&self) -> Vector3<f32> {
+        let direction = match self.moving {
+            Some(_) if self.squatting || self.eating.is_some() => {
+                return Vector3::new(0.0, 0.0, 0.0)
+            }
+            Some(Direction::Left) => Vector3::new(-1.0, 0.0, 0.0),
+            Some(Direction::Right) => Vector3::new(1.0, 0.0, 0.0),
+            Some(Direction::Up) => Vector3::new(0.0, 0.0, -1.0),
+            Some(Direction::Down) => Vector3::new(0.0, 0.0, 1.0),
+            None => return Vector3::new(0.0, 0.0, 0.0),
+        };
+
+        let speed = if self.stamina.is_tired() {
+            TIRED_SPEED
+        } else if self.running {
+            RUN_SPEED
+        } else {
+            WALK_SPEED
+        };
+
+        direction * speed
+    }
+
+    /// Picks the action that matches what the chicken is doing: dying once it's dead, falling
+    /// while it's in the air, eating, being tired, pushing while it walks into a wall, and
+    /// otherwise whatever the player asked for.
+    fn update_action(&mut self) {
+        let action = if self.health.is_dead() {
+            CharacterAction::Dying
+        } else if !self.on_ground {
+            CharacterAction::Fall
+        } else if self.eating.is_some() {
+            CharacterAction::Eat
+        } else if self.stamina.is_tired() {
+            CharacterAction::Tired
+        } else if self.moving.is_some() && self.pushing_wall {
+            CharacterAction::Push
+        } else if self.squatting {
+            CharacterAction::Squat
+        } else if self.moving.is_some() && self.running {
+            CharacterAction::Run
+        } else if self.moving.is_some() {
+            CharacterAction::Walk
+        } else {
+            CharacterAction::Nothing
+        };
+
+        if action != self.action {
+            self.action = action;
+            self.sprite = Self::animation_for(action).sprite();
+        }
+    }
+}
+
+/// Puts one of `food` in the backpack, on top of a stack of the same food if there's room.
+fn store(backpack: &mut Backpack, food: Food) {
+    let stack = backpack.iter_mut().find(|stack| match stack {
+        (Item::Food(f), count) => *f == food && *count < u8::MAX,
+        _ => false,
+    });
+
+    match stack {
+        Some((_, count)) => *count += 1,
+        None => backpack.push((Item::Food(food), 1)),
+    }
 }
 
 impl Default for Chicken {
     fn default() -> Self {
         Self {
-            physical: PhysicalObject::new(
-                2.0,
-                AABB {
-                    center_pos: Vector3::from((0.0, 0.0, 0.0)),
-                    half_size: Vector3::from((0.0, 0.0, 0.0)),
-                },
-            ),
             backpack: Default::default(),
             action: Default::default(),
             facing: Default::default(),
-            health: Default::default(),
-            lifespan: Default::default(),
+            health: Health::new(LIFESPAN, REGENERATION, INVULNERABILITY),
+            hunger: Hunger::new(),
+            stamina: Stamina::new(STAMINA, STAMINA_RECOVERY),
+            eating: None,
+            breath: BREATH,
+            dead_for: 0.0,
+            spilled: Vec::new(),
+
+            sprite: Self::animation_for(CharacterAction::default()).sprite(),
+
+            moving: None,
+            running: false,
+            squatting: false,
+            jumping: false,
+
+            on_ground: false,
+            pushing_wall: false,
+            falling_speed: 0.0,
         }
     }
 }
 
 impl Logicable for Chicken {
-    fn logic(&mut self, _delta_sec: f32) {
-        todo!()
+    /// Heals, starves, tires and animates the chicken. Moving it is left to `steer`, since its
+    /// body belongs to the PhysicsWorld.
+    fn logic(&mut self, delta_sec: f32) {
+        if delta_sec <= 0.0 {
+            return;
+        }
+
+        self.health.logic(delta_sec);
+        if !self.health.is_dead() {
+            self.digest(delta_sec);
+            self.exert(delta_sec);
+        }
+
+        if self.health.is_dead() {
+            self.dead_for += delta_sec;
+        }
+
+        self.update_action();
+        self.sprite.animate(delta_sec);
     }
 }
 
 impl Renderable for Chicken {
-    fn render(&self, _: &wgpu::RenderPass) {
-        todo!()
-    }
+    /// Chickens are drawn with the rest of the characters, so there's nothing to do here.
+    fn render(&self, _: &wgpu::RenderPass) {}
 }
 
 impl Character for Chicken {
-    /// Walks the chicken
+    /// Walks the chicken, or runs it if `sup` is true. It keeps going until `stop` is called.
     fn walk(&mut self, direction: Direction, sup: bool) {
-        if sup {
-            self.action = CharacterAction::Run;
-        } else {
-            self.action = CharacterAction::Walk;
-        }
+        self.moving = Some(direction);
+        self.running = sup;
+        self.squatting = false;
+        self.stop_eating();
 
         match direction {
             Direction::Left => self.facing = FacingDirection::Left,
@@ -87,48 +369,719 @@ impl Character for Chicken {
         }
     }
 
-    /// Jumps the chicken
+    /// Jumps the chicken during the next `steer`, if it's standing on something to jump off of.
     fn jump(&mut self, _sup: bool) {
-        self.physical.apply_force(Vector3::from((0.0, 6.0, 0.0)));
+        if self.can_jump() {
+            self.jumping = true;
+        }
     }
 
-    /// Squats the chicken
+    /// Squats the chicken, which stops it in its tracks.
     fn down(&mut self, _sup: bool) {
-        self.stop();
-        self.action = CharacterAction::Squat;
+        self.squatting = true;
+        self.stop_eating();
+        self.update_action();
     }
 
     /// Stops the chicken's movement
     fn stop(&mut self) {
-        self.action = CharacterAction::Nothing;
+        self.moving = None;
+        self.running = false;
+        self.squatting = false;
+        self.update_action();
+    }
+
+    /// Attacks with whatever's in hand, which hits harder or softer depending on what it is.
+    fn attack(&self, with: Option<&items::Item>, power: f32, who: EntityId) -> Attack {
+        let multiplier = with.map_or(1.0, Item::damage_multiplier);
+        Attack {
+            target: who,
+            damage: Damage::new(DamageSource::Melee, power * multiplier),
+        }
+    }
+
+    /// Returns a body the shape and weight of a chicken, standing (or falling) at `position`, for
+    /// putting in the PhysicsWorld the chicken is steered through.
+    fn body_at(&self, position: Vector3<f32>) -> PhysicalObject {
+        let mut body = PhysicalObject::new(
+            MASS,
+            AABB {
+                center_pos: Vector3::new(0.0, 0.0, 0.0),
+                half_size: Vector3::from(HALF_SIZE),
+            },
+        );
+        body.set_position(position);
+        body
     }
 
-    fn attack<K: Killable>(&self, _with: Option<&items::Item>, _power: f32, _who: K) {}
+    /// Eats the first food in the backpack. Returns false if there's nothing to eat or the
+    /// chicken can't eat right now.
+    fn eat_from_backpack(&mut self) -> bool {
+        let slot = self.backpack.iter().position(|stack| match stack {
+            (Item::Food(_), count) => *count > 0,
+            _ => false,
+        });
+
+        let food = match slot.map(|i| &mut self.backpack[i]) {
+            Some((Item::Food(food), count)) => {
+                *count -= 1;
+                *food
+            }
+            _ => return false,
+        };
+        self.backpack.retain(|(_, count)| *count > 0);
+
+        match self.eat(food) {
+            Ok(()) => true,
+            Err(f) => {
+                store(&mut self.backpack, f);
+                false
+            }
+        }
+    }
+
+    /// Takes what the chicken had in its backpack when it died, for dropping into the world.
+    fn take_spilled(&mut self) -> Backpack {
+        std::mem::take(&mut self.spilled)
+    }
+
+    /// Returns the sprite to draw for the chicken as it is now, standing on the bottom of its
+    /// body's `hitbox` as tall as it is. Returns None if the frame it's on isn't in `layers`.
+    fn sprite_instance(&self, hitbox: &AABB, layers: &SpriteLayers) -> Option<SpriteInstance> {
+        let animation = Self::animation_for(self.action);
+        let layer = layers.layer(&animation, self.sprite.frame())?;
+
+        let height = hitbox.half_size.y * 2.0;
+        let feet = hitbox.center_pos - Vector3::new(0.0, hitbox.half_size.y, 0.0);
+
+        Some(SpriteInstance::new(
+            feet,
+            (height * SPRITE_ASPECT, height),
+            layer,
+            Billboard::AxisLocked,
+            self.facing.is_flipped(),
+        ))
+    }
+
+    /// Pushes the chicken's `body` toward the speed it's trying to go, as hard as it can from
+    /// where it is, and pushes off the ground if it was asked to jump. Call before every step the
+    /// world takes. Dead chickens don't push themselves anywhere.
+    fn steer(&mut self, world: &mut PhysicsWorld, body: BodyHandle) {
+        let jumping = std::mem::take(&mut self.jumping);
+        let (velocity, mass) = match world.body(body) {
+            Some(b) => (b.velocity(), b.mass()),
+            None => return,
+        };
+        self.falling_speed = -velocity.y;
+
+        if self.health.is_dead() {
+            return;
+        }
+
+        // not already on its way up, so jumping twice before the next step doesn't double up
+        if jumping && self.can_jump() && velocity.y <= 0.0 {
+            self.stop_eating();
+            self.hunger.exhaust(JUMP_EXHAUSTION);
+            self.stamina.drain(JUMP_STAMINA);
+
+            if let Some(b) = world.body_mut(body) {
+                let up = JUMP_SPEED - velocity.y;
+                b.apply_impulse(Vector3::new(0.0, up * mass, 0.0));
+            }
+        }
+
+        let most = if self.on_ground {
+            GROUND_ACCELERATION
+        } else {
+            AIR_ACCELERATION
+        };
+        world.steer(body, self.target_velocity(), most);
+        self.update_action();
+    }
+
+    /// Catches up with where the world moved the chicken's `body` over the last `delta_sec`
+    /// seconds, hurting it if it landed too hard or has stayed under water too long.
+    fn settle(&mut self, body: &PhysicalObject, delta_sec: f32) {
+        let was_airborne = !self.on_ground;
+        self.on_ground = body.on_ground();
+        self.pushing_wall = body.pushing_wall();
+
+        if was_airborne && self.on_ground && self.falling_speed > SAFE_LANDING_SPEED {
+            let amount = (self.falling_speed - SAFE_LANDING_SPEED) * FALL_DAMAGE;
+            self.damage(Damage::new(DamageSource::Fall, amount));
+        }
+
+        if body.swim_state() == SwimState::Underwater {
+            self.breath = (self.breath - delta_sec).max(0.0);
+            if self.breath <= 0.0 {
+                let amount = DROWNING_DAMAGE * delta_sec;
+                self.damage(Damage::new(DamageSource::Drowning, amount));
+            }
+        } else {
+            self.breath = BREATH;
+        }
+
+        self.update_action();
+    }
 
     fn render(&self) {}
+
+    fn animation_for(action: CharacterAction) -> Animation {
+        let (name, image, frame_count, seconds_per_frame): (_, &'static [u8], _, _) = match action {
+            CharacterAction::Run => (
+                "chicken/sprint",
+                include_bytes!("../../assets/images/chicken/sprint.png"),
+                4,
+                0.15,
+            ),
+            CharacterAction::Walk => (
+                "chicken/walk",
+                include_bytes!("../../assets/images/chicken/walk.png"),
+                4,
+                0.2,
+            ),
+            CharacterAction::Squat => (
+                "chicken/squat",
+                include_bytes!("../../assets/images/chicken/squat.png"),
+                1,
+                0.0,
+            ),
+            CharacterAction::Push => (
+                "chicken/push",
+                include_bytes!("../../assets/images/chicken/push.png"),
+                4,
+                0.75,
+            ),
+            CharacterAction::Fall => (
+                "chicken/fall",
+                include_bytes!("../../assets/images/chicken/fall.png"),
+                2,
+                0.1,
+            ),
+            CharacterAction::Tired => (
+                "chicken/tired",
+                include_bytes!("../../assets/images/chicken/tired.png"),
+                2,
+                0.5,
+            ),
+            // no pictures for anything else yet
+            _ => (
+                "chicken/stand",
+                include_bytes!("../../assets/images/chicken/stand.png"),
+                1,
+                0.0,
+            ),
+        };
+
+        Animation {
+            name,
+            image,
+            frame_count,
+            seconds_per_frame,
+            playback: Playback::Loop,
+        }
+    }
+
+    fn sprite(&self) -> &Sprite {
+        &self.sprite
+    }
 }
 
 impl Killable for Chicken {
-    /// Hits the chicken with the object and power specified.
-    fn hit(&mut self, _with: Option<Item>, _power: f32) -> &[items::ItemStack] {
+    /// Hits the chicken, harder with a weapon or tool in hand. Returns what it drops if the hit
+    /// kills it.
+    fn hit(&mut self, with: Option<Item>, power: f32) -> &[ItemStack] {
+        let multiplier = with.as_ref().map_or(1.0, Item::damage_multiplier);
+        self.damage(Damage::new(DamageSource::Melee, power * multiplier))
+    }
+
+    /// Hurts the chicken. Returns what it drops if it dies.
+    fn damage(&mut self, damage: Damage) -> &[ItemStack] {
+        if self.health.damage(damage) && self.health.is_dead() {
+            return self.kill();
+        }
+
         &[]
     }
 
-    /// Kills the chicken, dropping its inventory
+    /// Kills the chicken, spilling its backpack
     fn kill(&mut self) -> &[ItemStack] {
-        &self.backpack
+        self.health.kill();
+        self.spilled.append(&mut self.backpack);
+        self.update_action();
+
+        &self.spilled
     }
 
     fn health_left(&self) -> f32 {
-        self.health
+        self.health.points()
     }
 
     fn lifespan(&self) -> f32 {
-        self.lifespan
+        self.health.lifespan()
     }
 
-    /// Returns true if the chicken is alive
+    /// Returns true until the chicken has finished dying
     fn is_alive(&self) -> bool {
-        true
+        !self.health.is_dead() || self.dead_for < DYING_TIME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::BlockIndex;
+    use std::ops::{Deref, DerefMut};
+
+    const STEP: f32 = 1.0 / 60.0;
+
+    /// Solid everywhere below y = 0, with a wall at block column x = 8 (from 4.0 to 4.5).
+    fn field((i, j, _): BlockIndex) -> bool {
+        j < 0 || i == 8
+    }
+
+    /// A chicken with its body in a world of its own.
+    struct Penned {
+        chicken: Chicken,
+        world: PhysicsWorld,
+        body: BodyHandle,
+    }
+
+    impl Penned {
+        fn at(position: Vector3<f32>) -> Self {
+            let mut world = PhysicsWorld::new();
+            let body = world.add_body(Chicken::new().body_at(position));
+            Self {
+                chicken: Chicken::new(),
+                world,
+                body,
+            }
+        }
+
+        fn body(&self) -> &PhysicalObject {
+            self.world.body(self.body).unwrap()
+        }
+    }
+
+    impl Deref for Penned {
+        type Target = Chicken;
+
+        fn deref(&self) -> &Chicken {
+            &self.chicken
+        }
+    }
+
+    impl DerefMut for Penned {
+        fn deref_mut(&mut self) -> &mut Chicken {
+            &mut self.chicken
+        }
+    }
+
+    /// A chicken standing on the ground of the `field`.
+    fn standing_chicken() -> Penned {
+        let mut c = Penned::at(Vector3::new(0.25, 1.0, 0.25));
+        run_for(&mut c, 1.0);
+        assert!(c.body().on_ground());
+        c
+    }
+
+    fn run_for(c: &mut Penned, seconds: f32) {
+        for _ in 0..(seconds / STEP) as usize {
+            tick(c, STEP);
+        }
+    }
+
+    /// Runs one frame, `delta_sec` seconds long, the way the world's entities do.
+    fn tick(c: &mut Penned, delta_sec: f32) {
+        let Penned {
+            chicken,
+            world,
+            body,
+        } = c;
+
+        chicken.logic(delta_sec);
+        world.update_with(delta_sec, &field, |w| chicken.steer(w, *body));
+        chicken.settle(world.body(*body).unwrap(), delta_sec);
+    }
+
+    #[test]
+    fn walks_and_runs() {
+        let mut c = standing_chicken();
+        assert_eq!(c.action(), CharacterAction::Nothing);
+
+        c.walk(Direction::Right, false);
+        run_for(&mut c, 0.5);
+        let walking = c.body().velocity().x;
+        assert!((walking - WALK_SPEED).abs() < 0.2, "{}", walking);
+        assert_eq!(c.action(), CharacterAction::Walk);
+
+        c.walk(Direction::Left, true);
+        run_for(&mut c, 0.5);
+        let running = c.body().velocity().x;
+        assert!((running + RUN_SPEED).abs() < 0.2, "{}", running);
+        assert_eq!(c.action(), CharacterAction::Run);
+        assert_eq!(c.facing(), FacingDirection::Left);
+
+        c.stop();
+        run_for(&mut c, 0.5);
+        assert!(c.body().velocity().x.abs() < 0.01);
+        assert_eq!(c.action(), CharacterAction::Nothing);
+    }
+
+    #[test]
+    fn jumps_only_from_the_ground() {
+        let mut c = standing_chicken();
+
+        c.jump(false);
+        c.jump(false);
+        run_for(&mut c, STEP);
+        // less one step of gravity
+        assert!((c.body().velocity().y - JUMP_SPEED).abs() < 0.2);
+
+        run_for(&mut c, 0.1);
+        assert_eq!(c.action(), CharacterAction::Fall);
+
+        // no jumping again in midair
+        let rising = c.body().velocity().y;
+        c.jump(false);
+        run_for(&mut c, STEP);
+        assert!(c.body().velocity().y < rising);
+
+        // high enough to get up onto a block
+        let mut highest: f32 = 0.0;
+        for _ in 0..60 {
+            run_for(&mut c, STEP);
+            highest = highest.max(c.body().position().y - HALF_SIZE.1);
+        }
+        assert!(highest > 0.5, "{}", highest);
+        assert!(c.body().on_ground());
+    }
+
+    #[test]
+    fn falls_the_same_at_any_frame_rate() {
+        let fall = |frame_rate: f32| {
+            let mut c = Penned::at(Vector3::new(0.25, 10.0, 0.25));
+            for _ in 0..frame_rate as usize {
+                tick(&mut c, 1.0 / frame_rate);
+            }
+            c.body().position().y
+        };
+
+        // a second in, still well above the ground
+        let (slow, fast) = (fall(30.0), fall(240.0));
+        assert!(slow > 4.0, "{}", slow);
+        assert!((slow - fast).abs() < 1e-3, "{} {}", slow, fast);
+    }
+
+    #[test]
+    fn walks_and_stops_the_same_at_any_frame_rate() {
+        let walk = |frame_rate: f32| {
+            let mut c = standing_chicken();
+            let (mut fastest, mut backwards): (f32, f32) = (0.0, 0.0);
+
+            c.walk(Direction::Right, false);
+            for _ in 0..frame_rate as usize {
+                tick(&mut c, 1.0 / frame_rate);
+                fastest = fastest.max(c.body().velocity().x);
+            }
+
+            c.stop();
+            for _ in 0..frame_rate as usize {
+                tick(&mut c, 1.0 / frame_rate);
+                backwards = backwards.min(c.body().velocity().x);
+            }
+
+            (c.body().position().x, fastest, backwards)
+        };
+
+        for &frame_rate in [30.0, 240.0].iter() {
+            let (_, fastest, backwards) = walk(frame_rate);
+
+            // no overshooting the walking speed, or rocking back and forth when stopping
+            assert!(
+                fastest <= WALK_SPEED + 1e-3,
+                "{} at {}",
+                fastest,
+                frame_rate
+            );
+            assert!(backwards >= -1e-3, "{} at {}", backwards, frame_rate);
+        }
+
+        let ((slow, ..), (fast, ..)) = (walk(30.0), walk(240.0));
+        assert!(slow > 2.0, "{}", slow);
+        assert!((slow - fast).abs() < 1e-3, "{} {}", slow, fast);
+    }
+
+    #[test]
+    fn squatting_stops_the_chicken() {
+        let mut c = standing_chicken();
+        c.walk(Direction::Right, false);
+        run_for(&mut c, 0.5);
+
+        c.down(false);
+        assert_eq!(c.action(), CharacterAction::Squat);
+        run_for(&mut c, 0.5);
+        assert!(c.body().velocity().x.abs() < 0.01);
+        assert_eq!(c.action(), CharacterAction::Squat);
+
+        c.jump(false);
+        run_for(&mut c, STEP);
+        assert!(c.body().velocity().y.abs() < 0.01);
+    }
+
+    #[test]
+    fn pushes_against_walls() {
+        let mut c = standing_chicken();
+        c.walk(Direction::Right, false);
+        run_for(&mut c, 3.0);
+
+        assert_eq!(c.action(), CharacterAction::Push);
+        assert!(c.body().position().x + HALF_SIZE.0 <= 4.0);
+    }
+
+    #[test]
+    fn every_action_has_a_sprite_sheet() {
+        for &action in CharacterAction::ALL.iter() {
+            let animation = Chicken::animation_for(action);
+            let sheet = animation.sheet().unwrap();
+
+            assert_eq!(sheet.frame_count(), animation.frame_count as usize);
+            assert_eq!(sheet.frame_dimensions(), (12, 13), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn animations_follow_the_action() {
+        let mut c = standing_chicken();
+        assert_eq!(c.sprite().frame_count(), 1);
+
+        c.walk(Direction::Right, false);
+        run_for(&mut c, STEP);
+        assert_eq!(c.action(), CharacterAction::Walk);
+        assert_eq!(c.sprite().frame_count(), 4);
+
+        // 0.2 seconds a frame
+        run_for(&mut c, 0.5);
+        assert_eq!(c.sprite().frame(), 2);
+
+        // a new action starts its animation over
+        c.walk(Direction::Right, true);
+        run_for(&mut c, STEP);
+        assert_eq!(c.action(), CharacterAction::Run);
+        assert_eq!(c.sprite().frame(), 0);
+    }
+
+    #[test]
+    fn sprites_stand_on_the_ground_facing_the_way_the_chicken_walks() {
+        let layers = SpriteLayers::new(&Chicken::animations());
+        let mut c = standing_chicken();
+
+        let standing = c.sprite_instance(c.body().hitbox(), &layers).unwrap();
+        assert!((standing.position[1] - c.body().hitbox().min().y).abs() < 1e-6);
+        assert!(!standing.is_flipped());
+        assert_eq!(standing.billboard(), Billboard::AxisLocked);
+
+        c.walk(Direction::Left, false);
+        run_for(&mut c, 0.3);
+        let walking = c.sprite_instance(c.body().hitbox(), &layers).unwrap();
+        assert!(walking.is_flipped());
+        assert_eq!(
+            Some(walking.texture_layer),
+            layers.layer(&Chicken::animation_for(CharacterAction::Walk), 1)
+        );
+
+        // nothing to draw without the chicken's sheets
+        assert_eq!(
+            c.sprite_instance(c.body().hitbox(), &SpriteLayers::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn weapons_hit_harder() {
+        use crate::items::{Material, Weapon, WeaponType};
+
+        let mut c = standing_chicken();
+        c.hit(None, 1.0);
+        assert!((c.health_left() - (LIFESPAN - 1.0)).abs() < 0.01);
+
+        // still recovering from the last hit
+        let sword = || Item::Weapon(Weapon::new(WeaponType::Sword, Material::Steel));
+        c.hit(Some(sword()), 1.0);
+        assert!((c.health_left() - (LIFESPAN - 1.0)).abs() < 0.01);
+
+        // a little comes back in the meantime
+        run_for(&mut c, INVULNERABILITY + STEP);
+        c.hit(Some(sword()), 1.0);
+        assert!((c.health_left() - (LIFESPAN - 5.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn long_falls_hurt() {
+        let mut c = Penned::at(Vector3::new(0.25, 10.0, 0.25));
+        run_for(&mut c, 2.0);
+
+        assert!(c.body().on_ground());
+        assert!(c.health_left() < LIFESPAN - 1.0, "{}", c.health_left());
+        assert_eq!(
+            c.health().last_damage().map(|d| d.source),
+            Some(DamageSource::Fall)
+        );
+
+        // standing_chicken falls a short way
+        assert_eq!(standing_chicken().health_left(), LIFESPAN);
+    }
+
+    #[test]
+    fn dying_spills_the_backpack() {
+        let mut c = standing_chicken();
+        c.backpack_mut().push((Item::Other, 3));
+        c.walk(Direction::Right, false);
+
+        let dropped = c.damage(Damage::new(DamageSource::Fire, LIFESPAN * 2.0));
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(c.action(), CharacterAction::Dying);
+        assert!(c.is_alive());
+
+        // no getting up again
+        c.jump(false);
+        run_for(&mut c, DYING_TIME / 2.0);
+        assert_eq!(c.health_left(), 0.0);
+        assert!(c.body().velocity().magnitude() < 0.01);
+        assert_eq!(c.action(), CharacterAction::Dying);
+
+        run_for(&mut c, DYING_TIME);
+        assert!(!c.is_alive());
+        assert_eq!(c.take_spilled().len(), 1);
+        assert!(c.take_spilled().is_empty());
+    }
+
+    /// A standing chicken that's eaten nothing in a long time.
+    fn starving_chicken() -> Penned {
+        let mut c = standing_chicken();
+        c.hunger.exhaust(1000.0);
+        assert!(c.hunger().is_starving());
+        c
+    }
+
+    #[test]
+    fn eating_takes_time() {
+        let mut c = starving_chicken();
+
+        assert_eq!(c.eat(Food::Apple), Ok(()));
+        assert_eq!(c.eat(Food::Lemon), Err(Food::Lemon));
+        assert_eq!(c.action(), CharacterAction::Eat);
+
+        run_for(&mut c, Food::Apple.eating_time() - 0.2);
+        assert_eq!(c.hunger().food(), 0.0);
+
+        run_for(&mut c, 0.3);
+        assert_eq!(c.hunger().food(), Food::Apple.nutrition().food);
+        assert_eq!(c.action(), CharacterAction::Nothing);
+    }
+
+    #[test]
+    fn walking_away_puts_the_food_back() {
+        let mut c = starving_chicken();
+        c.backpack_mut().push((Item::Food(Food::Carrot), 2));
+
+        assert!(c.eat_from_backpack());
+        assert!(matches!(c.backpack[..], [(Item::Food(Food::Carrot), 1)]));
+
+        run_for(&mut c, 0.5);
+        c.walk(Direction::Right, false);
+        assert!(matches!(c.backpack[..], [(Item::Food(Food::Carrot), 2)]));
+        assert_eq!(c.hunger().food(), 0.0);
+
+        // the last one
+        c.stop();
+        c.backpack_mut()[0].1 = 1;
+        assert!(c.eat_from_backpack());
+        assert!(c.backpack.is_empty());
+        assert!(!c.eat_from_backpack());
+    }
+
+    #[test]
+    fn running_makes_the_chicken_hungry() {
+        let mut walker = standing_chicken();
+        let mut runner = standing_chicken();
+        let mut sitter = standing_chicken();
+        walker.walk(Direction::Right, false);
+        runner.walk(Direction::Right, true);
+
+        // not far enough to reach the wall
+        run_for(&mut walker, 0.5);
+        run_for(&mut runner, 0.5);
+        run_for(&mut sitter, 0.5);
+
+        let used = |c: &Penned| c.hunger().food() + c.hunger().saturation();
+        assert!(used(&runner) < used(&walker));
+        assert!(used(&walker) < used(&sitter));
+
+        let before = used(&sitter);
+        sitter.jump(false);
+        run_for(&mut sitter, STEP);
+        assert!(used(&sitter) < before);
+    }
+
+    #[test]
+    fn only_well_fed_chickens_heal() {
+        let mut fed = standing_chicken();
+        fed.damage(Damage::new(DamageSource::Melee, 5.0));
+        run_for(&mut fed, 1.0);
+        assert!(fed.health_left() > LIFESPAN - 5.0);
+
+        let mut starving = starving_chicken();
+        starving.damage(Damage::new(DamageSource::Melee, 5.0));
+        run_for(&mut starving, 1.0);
+        assert!(starving.health_left() < LIFESPAN - 5.0);
+        assert_eq!(
+            starving.health().last_damage().map(|d| d.source),
+            Some(DamageSource::Starvation)
+        );
+    }
+
+    #[test]
+    fn sprinting_wears_the_chicken_out() {
+        let mut c = standing_chicken();
+
+        // away from the wall
+        c.walk(Direction::Left, true);
+        run_for(&mut c, 4.0);
+        assert_eq!(c.action(), CharacterAction::Run);
+
+        run_for(&mut c, 1.5);
+        assert!(c.stamina().is_tired());
+        assert_eq!(c.action(), CharacterAction::Tired);
+        let plodding = c.body().velocity().x;
+        assert!((plodding + TIRED_SPEED).abs() < 0.2, "{}", plodding);
+
+        // too tired to jump
+        c.jump(false);
+        run_for(&mut c, STEP);
+        assert!(c.body().velocity().y.abs() < 0.01);
+
+        c.stop();
+        run_for(
+            &mut c,
+            STAMINA * Stamina::RECOVERED / STAMINA_RECOVERY + 0.1,
+        );
+        assert!(!c.stamina().is_tired());
+        assert_eq!(c.action(), CharacterAction::Nothing);
+
+        // a step's worth comes back too
+        let before = c.stamina().points() + STAMINA_RECOVERY * STEP;
+        c.jump(false);
+        run_for(&mut c, STEP);
+        assert!((c.stamina().points() - (before - JUMP_STAMINA)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn has_a_real_hitbox() {
+        let half = Chicken::new()
+            .body_at(Vector3::new(0.0, 0.0, 0.0))
+            .hitbox()
+            .half_size;
+        assert!(half.x > 0.0 && half.y > 0.0 && half.z > 0.0);
     }
 }
@@ -0,0 +1,216 @@
+/// What hurt something.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DamageSource {
+    /// Hit up close, with or without something in hand.
+    Melee,
+
+    /// Hit by something fired from afar.
+    Projectile,
+
+    /// Landed too hard.
+    Fall,
+    Fire,
+
+    /// Ran out of breath under water.
+    Drowning,
+
+    /// Ran out of food.
+    Starvation,
+}
+
+impl DamageSource {
+    /// Returns true if being hurt this way should keep anything else from hurting for a moment.
+    /// Drowning and starvation hurt a little at a time, so they don't; otherwise they'd keep
+    /// everything else from landing.
+    pub fn grants_invulnerability(self) -> bool {
+        match self {
+            Self::Melee | Self::Projectile | Self::Fall | Self::Fire => true,
+            Self::Drowning | Self::Starvation => false,
+        }
+    }
+}
+
+/// Damage is one helping of hurt.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Damage {
+    pub source: DamageSource,
+
+    /// How many health points it takes away.
+    pub amount: f32,
+}
+
+impl Damage {
+    pub fn new(source: DamageSource, amount: f32) -> Self {
+        Self { source, amount }
+    }
+}
+
+/// Health keeps track of how many health points something has left. It comes back slowly while
+/// `regenerate` is called, but never past the lifespan. After most kinds of damage, there's a
+/// moment where nothing else can hurt, so that (say) one swing doesn't land every frame it's
+/// touching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Health {
+    points: f32,
+    lifespan: f32,
+
+    /// Health points regained per second of `regenerate`.
+    regeneration: f32,
+
+    /// How long nothing can hurt after being hurt, in seconds.
+    invulnerability: f32,
+
+    /// Seconds left until something can hurt again.
+    invulnerable_for: f32,
+    last_damage: Option<Damage>,
+}
+
+impl Health {
+    /// Makes a Health that starts out full.
+    pub fn new(lifespan: f32, regeneration: f32, invulnerability: f32) -> Self {
+        Self {
+            points: lifespan,
+            lifespan,
+            regeneration,
+            invulnerability,
+            invulnerable_for: 0.0,
+            last_damage: None,
+        }
+    }
+
+    pub fn points(&self) -> f32 {
+        self.points
+    }
+
+    /// Returns the most health points there can be.
+    pub fn lifespan(&self) -> f32 {
+        self.lifespan
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.points <= 0.0
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_for > 0.0
+    }
+
+    /// Returns the last damage that hurt, which for the dead is what killed them.
+    pub fn last_damage(&self) -> Option<Damage> {
+        self.last_damage
+    }
+
+    /// Takes away the damage's health points. Returns true if it hurt: the dead, and the
+    /// invulnerable (for damage that respects invulnerability), don't take any damage.
+    pub fn damage(&mut self, damage: Damage) -> bool {
+        let blocked = damage.source.grants_invulnerability() && self.is_invulnerable();
+        if self.is_dead() || blocked || damage.amount.is_nan() || damage.amount <= 0.0 {
+            return false;
+        }
+
+        self.points = (self.points - damage.amount).max(0.0);
+        self.last_damage = Some(damage);
+        if damage.source.grants_invulnerability() {
+            self.invulnerable_for = self.invulnerability;
+        }
+
+        true
+    }
+
+    /// Gives back health points, up to the lifespan. The dead stay dead.
+    pub fn heal(&mut self, amount: f32) {
+        if !self.is_dead() && amount > 0.0 {
+            self.points = (self.points + amount).min(self.lifespan);
+        }
+    }
+
+    /// Takes away every health point, whether or not anything can hurt right now.
+    pub fn kill(&mut self) {
+        self.points = 0.0;
+    }
+
+    /// Returns true if there's anything to regenerate.
+    pub fn is_hurt(&self) -> bool {
+        !self.is_dead() && self.points < self.lifespan
+    }
+
+    /// Gives back health points for `delta_sec` seconds of regenerating. Characters only
+    /// regenerate while they're well fed.
+    pub fn regenerate(&mut self, delta_sec: f32) {
+        self.heal(self.regeneration * delta_sec);
+    }
+
+    /// Counts down invulnerability.
+    pub fn logic(&mut self, delta_sec: f32) {
+        self.invulnerable_for = (self.invulnerable_for - delta_sec).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(amount: f32) -> Damage {
+        Damage::new(DamageSource::Melee, amount)
+    }
+
+    #[test]
+    fn hits_are_followed_by_invulnerability() {
+        let mut health = Health::new(10.0, 0.0, 0.5);
+
+        assert!(health.damage(hit(3.0)));
+        assert_eq!(health.points(), 7.0);
+
+        // too soon
+        assert!(!health.damage(hit(3.0)));
+        assert_eq!(health.points(), 7.0);
+
+        // drowning doesn't wait
+        assert!(health.damage(Damage::new(DamageSource::Drowning, 1.0)));
+        assert_eq!(health.points(), 6.0);
+
+        health.logic(0.6);
+        assert!(!health.is_invulnerable());
+        assert!(health.damage(hit(3.0)));
+        assert_eq!(health.points(), 3.0);
+    }
+
+    #[test]
+    fn regenerates_up_to_the_lifespan() {
+        let mut health = Health::new(10.0, 2.0, 0.0);
+        health.damage(hit(5.0));
+        assert!(health.is_hurt());
+
+        // only when asked
+        health.logic(1.0);
+        assert_eq!(health.points(), 5.0);
+
+        health.regenerate(1.0);
+        assert_eq!(health.points(), 7.0);
+
+        health.regenerate(10.0);
+        assert_eq!(health.points(), 10.0);
+        assert!(!health.is_hurt());
+
+        health.heal(5.0);
+        assert_eq!(health.points(), 10.0);
+    }
+
+    #[test]
+    fn the_dead_stay_dead() {
+        let mut health = Health::new(10.0, 2.0, 0.0);
+
+        assert!(health.damage(Damage::new(DamageSource::Fall, 25.0)));
+        assert!(health.is_dead());
+        assert_eq!(health.points(), 0.0);
+        assert_eq!(
+            health.last_damage().map(|d| d.source),
+            Some(DamageSource::Fall)
+        );
+
+        health.regenerate(10.0);
+        health.heal(5.0);
+        assert!(health.is_dead() && !health.is_hurt());
+        assert!(!health.damage(hit(1.0)));
+    }
+}
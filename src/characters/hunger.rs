@@ -0,0 +1,119 @@
+use crate::items::Nutrition;
+
+/// Hunger is how full a character is. Doing things (running, jumping, healing) uses up
+/// saturation first, then food. With no food left, a character starves; with nearly all of it,
+/// they heal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunger {
+    /// From 0, starving, up to MAX_FOOD.
+    food: f32,
+
+    /// Never more than `food`.
+    saturation: f32,
+}
+
+impl Hunger {
+    pub const MAX_FOOD: f32 = 20.0;
+
+    /// How much food there has to be to heal.
+    pub const WELL_FED: f32 = 18.0;
+
+    /// How much exhaustion uses up one point of saturation or food.
+    pub const EXHAUSTION_PER_POINT: f32 = 4.0;
+
+    /// Makes a full Hunger, with a little saturation.
+    pub fn new() -> Self {
+        Self {
+            food: Self::MAX_FOOD,
+            saturation: 5.0,
+        }
+    }
+
+    pub fn food(&self) -> f32 {
+        self.food
+    }
+
+    pub fn saturation(&self) -> f32 {
+        self.saturation
+    }
+
+    pub fn is_starving(&self) -> bool {
+        self.food <= 0.0
+    }
+
+    /// Returns true if there's enough food to heal.
+    pub fn is_well_fed(&self) -> bool {
+        self.food >= Self::WELL_FED
+    }
+
+    /// Uses up saturation, and then food, for doing something tiring.
+    pub fn exhaust(&mut self, exhaustion: f32) {
+        if exhaustion.is_nan() || exhaustion <= 0.0 {
+            return;
+        }
+
+        let mut points = exhaustion / Self::EXHAUSTION_PER_POINT;
+
+        let from_saturation = points.min(self.saturation);
+        self.saturation -= from_saturation;
+        points -= from_saturation;
+
+        self.food = (self.food - points).max(0.0);
+    }
+
+    /// Fills up on food, as far as it'll go.
+    pub fn eat(&mut self, nutrition: Nutrition) {
+        self.food = (self.food + nutrition.food.max(0.0)).min(Self::MAX_FOOD);
+        self.saturation = (self.saturation + nutrition.saturation.max(0.0)).min(self.food);
+    }
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Food;
+
+    #[test]
+    fn saturation_runs_out_first() {
+        let mut hunger = Hunger::new();
+
+        hunger.exhaust(4.0 * Hunger::EXHAUSTION_PER_POINT);
+        assert_eq!(hunger.saturation(), 1.0);
+        assert_eq!(hunger.food(), Hunger::MAX_FOOD);
+
+        hunger.exhaust(3.0 * Hunger::EXHAUSTION_PER_POINT);
+        assert_eq!(hunger.saturation(), 0.0);
+        assert_eq!(hunger.food(), Hunger::MAX_FOOD - 2.0);
+        assert!(hunger.is_well_fed());
+
+        hunger.exhaust(100.0 * Hunger::EXHAUSTION_PER_POINT);
+        assert_eq!(hunger.food(), 0.0);
+        assert!(hunger.is_starving() && !hunger.is_well_fed());
+    }
+
+    #[test]
+    fn eating_fills_up_to_the_top() {
+        let mut hunger = Hunger::new();
+        hunger.exhaust(1000.0);
+
+        hunger.eat(Food::Apple.nutrition());
+        assert_eq!(hunger.food(), 4.0);
+        assert!((hunger.saturation() - 2.4).abs() < 1e-6);
+
+        // saturation can't outgrow food
+        hunger.eat(Food::Nuts.nutrition());
+        assert_eq!(hunger.food(), 7.0);
+        assert_eq!(hunger.saturation(), 7.0);
+
+        for _ in 0..5 {
+            hunger.eat(Food::Pineapple.nutrition());
+        }
+        assert_eq!(hunger.food(), Hunger::MAX_FOOD);
+    }
+}
@@ -1,12 +1,25 @@
 #![allow(dead_code)]
 
 pub mod chicken;
+pub mod health;
+pub mod hunger;
+pub mod stamina;
 
 pub use chicken::*;
+pub use health::*;
+pub use hunger::*;
+pub use stamina::*;
 
+use crate::entities::{Attack, EntityId};
 use crate::items;
+use crate::items::Backpack;
+use crate::maths::AABB;
+use crate::physics::{BodyHandle, PhysicalObject, PhysicsWorld};
+use crate::sprite::{Animation, Sprite, SpriteInstance, SpriteLayers};
 use crate::traits::*;
+use cgmath::Vector3;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Direction {
     Up,
     Down,
@@ -23,16 +36,65 @@ pub trait Character: Killable + Logicable + Renderable {
     fn jump(&mut self, sup: bool); // Do something when the space bar is pressed
     fn stop(&mut self); // Nothing is happening anymore; stop movement
 
-    /// Initiates an attack by the Controllable. Returns whatever the Controllable might be
-    /// holding, the attack power, and where the Controllable was aiming
-    fn attack<K: Killable>(&self, with: Option<&items::Item>, power: f32, who: K);
+    /// Initiates an attack by the Controllable on `who`, with whatever the Controllable might be
+    /// holding and the attack power. Returns the attack for the world's entities to land, since
+    /// the target is shared with everything else and can't be hurt from here.
+    fn attack(&self, with: Option<&items::Item>, power: f32, who: EntityId) -> Attack;
+
+    /// Returns a body the shape and weight of the character, standing (or falling) at
+    /// `position`, for the physics world to move around.
+    fn body_at(&self, position: Vector3<f32>) -> PhysicalObject;
+
+    /// Pushes the character's `body` the way it's trying to go. Called after `logic`, before every
+    /// step the world takes.
+    fn steer(&mut self, world: &mut PhysicsWorld, body: BodyHandle);
+
+    /// Catches up with where the world moved the character's `body` over the last `delta_sec`
+    /// seconds.
+    fn settle(&mut self, body: &PhysicalObject, delta_sec: f32);
+
+    /// Eats the first food in the backpack. Returns false if there's nothing to eat or the
+    /// character can't eat right now.
+    fn eat_from_backpack(&mut self) -> bool;
+
+    /// Takes what the character had in its backpack when it died, for dropping into the world.
+    fn take_spilled(&mut self) -> Backpack;
 
     /// Renders the character.
     fn render(&self);
+
+    /// Returns the animation the character shows while doing `action`.
+    fn animation_for(action: CharacterAction) -> Animation
+    where
+        Self: Sized;
+
+    /// Returns the character's current animation, for picking which frame to draw.
+    fn sprite(&self) -> &Sprite;
+
+    /// Returns the sprite to draw for the character as it is now, standing on its body's
+    /// `hitbox`. Returns None if the frame it's on isn't in `layers`.
+    fn sprite_instance(&self, hitbox: &AABB, layers: &SpriteLayers) -> Option<SpriteInstance>;
+
+    /// Returns every animation the character might show, each only once, for loading all of
+    /// their sprite sheets up front.
+    fn animations() -> Vec<Animation>
+    where
+        Self: Sized,
+    {
+        let mut animations: Vec<Animation> = Vec::new();
+        for &action in CharacterAction::ALL.iter() {
+            let animation = Self::animation_for(action);
+            if !animations.iter().any(|a| a.name == animation.name) {
+                animations.push(animation);
+            }
+        }
+
+        animations
+    }
 }
 
 /// Specifies what a certain character is doing.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CharacterAction {
     Nothing,
     Walk,
@@ -46,6 +108,27 @@ pub enum CharacterAction {
     Push,
     Sleep,
     Eat,
+
+    /// Worn out from sprinting.
+    Tired,
+}
+
+impl CharacterAction {
+    pub const ALL: [CharacterAction; 13] = [
+        Self::Nothing,
+        Self::Walk,
+        Self::Run,
+        Self::Squat,
+        Self::Climb,
+        Self::Fall,
+        Self::Attack,
+        Self::Hurt,
+        Self::Dying,
+        Self::Push,
+        Self::Sleep,
+        Self::Eat,
+        Self::Tired,
+    ];
 }
 
 impl Default for CharacterAction {
@@ -55,12 +138,20 @@ impl Default for CharacterAction {
 }
 
 /// Right or Left, telling which direction a character (or whatever sprite) is facing
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FacingDirection {
     Right,
     Left,
 }
 
+impl FacingDirection {
+    /// Returns true if sprites need mirroring to face this way. Sprite sheets are drawn facing
+    /// right.
+    pub fn is_flipped(self) -> bool {
+        self == Self::Left
+    }
+}
+
 impl Default for FacingDirection {
     fn default() -> Self {
         Self::Right
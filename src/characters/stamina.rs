@@ -0,0 +1,91 @@
+/// Stamina is how much a character has left in them for sprinting and jumping. It comes back
+/// while they rest. Using all of it up leaves them tired until it's back to
+/// `Stamina::RECOVERED`, no matter how much they'd like to keep going.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamina {
+    points: f32,
+    max: f32,
+
+    /// Points regained per second of `recover`.
+    recovery: f32,
+    tired: bool,
+}
+
+impl Stamina {
+    /// How much of the most stamina there can be has to come back before a tired character isn't
+    /// tired anymore.
+    pub const RECOVERED: f32 = 0.5;
+
+    /// Makes a full Stamina.
+    pub fn new(max: f32, recovery: f32) -> Self {
+        Self {
+            points: max,
+            max,
+            recovery,
+            tired: false,
+        }
+    }
+
+    pub fn points(&self) -> f32 {
+        self.points
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    pub fn is_tired(&self) -> bool {
+        self.tired
+    }
+
+    /// Uses up stamina. Running out makes the character tired.
+    pub fn drain(&mut self, amount: f32) {
+        if amount.is_nan() || amount <= 0.0 {
+            return;
+        }
+
+        self.points = (self.points - amount).max(0.0);
+        if self.points <= 0.0 {
+            self.tired = true;
+        }
+    }
+
+    /// Gives back stamina for `delta_sec` seconds of rest.
+    pub fn recover(&mut self, delta_sec: f32) {
+        if delta_sec <= 0.0 {
+            return;
+        }
+
+        self.points = (self.points + self.recovery * delta_sec).min(self.max);
+        if self.points >= self.max * Self::RECOVERED {
+            self.tired = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_out_is_tiring() {
+        let mut stamina = Stamina::new(10.0, 2.0);
+
+        stamina.drain(9.0);
+        assert!(!stamina.is_tired());
+        stamina.drain(9.0);
+        assert_eq!(stamina.points(), 0.0);
+        assert!(stamina.is_tired());
+
+        // still tired partway back
+        stamina.recover(2.0);
+        assert_eq!(stamina.points(), 4.0);
+        assert!(stamina.is_tired());
+
+        stamina.recover(0.5);
+        assert!(!stamina.is_tired());
+
+        stamina.recover(100.0);
+        assert_eq!(stamina.points(), stamina.max());
+    }
+}
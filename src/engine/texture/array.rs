@@ -0,0 +1,243 @@
+use super::errors::TextureError;
+use super::mipmap;
+
+/// FilterSettings decides how textures are sampled when they're drawn bigger or smaller than they
+/// really are.
+#[derive(Debug, Copy, Clone)]
+pub struct FilterSettings {
+    /// Used when a texel covers more than one pixel (up close).
+    pub mag_filter: wgpu::FilterMode,
+
+    /// Used when a pixel covers more than one texel (far away).
+    pub min_filter: wgpu::FilterMode,
+
+    /// Used between mip levels. Linear hides the line where one mip level switches to the next.
+    pub mipmap_filter: wgpu::FilterMode,
+}
+
+impl Default for FilterSettings {
+    /// Crisp pixels up close, smooth in the distance.
+    fn default() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+/// TextureArray is a stack of same-sized 2D textures, each in its own layer. Layers are picked
+/// with an integer index in shaders, so they never blend into each other the way slices of a 3D
+/// texture can, and each layer repeats on its own.
+pub struct TextureArray {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+
+    layers: u32,
+    label: Option<String>,
+
+    /// The size of a single layer.
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+}
+
+impl TextureArray {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(
+        device: &wgpu::Device,
+        px_dimensions: (u32, u32),
+        layers: u32,
+        filters: FilterSettings,
+        label: Option<&str>,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: px_dimensions.0,
+            height: px_dimensions.1,
+            depth: 1,
+        };
+
+        // every layer gets a full mip chain so that distant blocks don't shimmer
+        let mip_level_count = mipmap::mip_level_count(size.width, size.height);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            array_layer_count: layers,
+            mip_level_count,
+            sample_count: 1,
+
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        // TextureView: offers us a *view* into our texture. the default view would only see the
+        // first layer, so ask for all of them
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Self::FORMAT,
+            dimension: wgpu::TextureViewDimension::D2Array,
+            aspect: wgpu::TextureAspect::All,
+            base_mip_level: 0,
+            level_count: mip_level_count,
+            base_array_layer: 0,
+            array_layer_count: layers,
+        });
+
+        // Sampler: controls how the Texture is *sampled*. Repeat so that one quad can tile a
+        // texture across many blocks.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filters.mag_filter,
+            min_filter: filters.min_filter,
+            mipmap_filter: filters.mipmap_filter,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            compare: wgpu::CompareFunction::Always,
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size,
+            mip_level_count,
+            layers,
+            label: label.map(String::from),
+        }
+    }
+
+    pub fn set_layer_from_bytes(
+        &mut self,
+        device: &wgpu::Device,
+        index: u32,
+        bytes: &[u8],
+    ) -> Result<wgpu::CommandBuffer, TextureError> {
+        self.check_index(index)?;
+
+        let img = image::load_from_memory(bytes).map_err(|e| {
+            TextureError::from_error_with_detail(e, "setting layer from bytes (loading image)")
+        })?;
+
+        self.set_layer_from_image(device, index, img)
+    }
+
+    pub fn set_layer_from_image(
+        &mut self,
+        device: &wgpu::Device,
+        index: u32,
+        img: image::DynamicImage,
+    ) -> Result<wgpu::CommandBuffer, TextureError> {
+        self.check_index(index)?;
+
+        let rgba = img.into_rgba();
+        self.check_dimensions(rgba.dimensions())?;
+
+        self.set_layer_from_mip_chain(device, index, &mipmap::generate_mip_chain(&rgba))
+    }
+
+    /// Uploads a mip chain made ahead of time by `mipmap::generate_mip_chain`. Animations use this
+    /// so that they don't have to make a new chain every time the frame changes.
+    pub fn set_layer_from_mip_chain(
+        &self,
+        device: &wgpu::Device,
+        index: u32,
+        levels: &[image::RgbaImage],
+    ) -> Result<wgpu::CommandBuffer, TextureError> {
+        self.check_index(index)?;
+
+        match levels.first() {
+            Some(level) => self.check_dimensions(level.dimensions())?,
+            None => {
+                return Err(TextureError::from_message(format!(
+                    "tried to set a layer of `{}` from an empty mip chain",
+                    self.label()
+                )))
+            }
+        }
+
+        if levels.len() != self.mip_level_count as usize {
+            return Err(TextureError::from_message(format!(
+                "mip chain has {} levels, but layers of `{}` have {}",
+                levels.len(),
+                self.label(),
+                self.mip_level_count
+            )));
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("texture command encoder"),
+        });
+
+        for (mip_level, level) in levels.iter().enumerate() {
+            let (width, height) = level.dimensions();
+
+            // COPY_SRC: copy from this buffer
+            let buffer = device.create_buffer_with_data(
+                bytemuck::cast_slice(level.as_ref()),
+                wgpu::BufferUsage::COPY_SRC,
+            );
+
+            encoder.copy_buffer_to_texture(
+                wgpu::BufferCopyView {
+                    buffer: &buffer,
+                    offset: 0,
+                    bytes_per_row: 4 * width,
+                    rows_per_image: height,
+                },
+                wgpu::TextureCopyView {
+                    texture: &self.texture,
+                    mip_level: mip_level as u32,
+                    array_layer: index,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth: 1,
+                },
+            );
+        }
+
+        Ok(encoder.finish())
+    }
+
+    fn check_dimensions(&self, dimensions: (u32, u32)) -> Result<(), TextureError> {
+        if dimensions != (self.size.width, self.size.height) {
+            Err(TextureError::from_message(format!(
+                "image is {}x{}, but layers of `{}` are {}x{}",
+                dimensions.0,
+                dimensions.1,
+                self.label(),
+                self.size.width,
+                self.size.height
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn label(&self) -> &str {
+        if let Some(l) = &self.label {
+            l
+        } else {
+            "(no label)"
+        }
+    }
+
+    fn check_index(&self, index: u32) -> Result<(), TextureError> {
+        if index >= self.layers {
+            Err(TextureError::from_message(format!(
+                "tried to set a texture out of range: index {} on texture array `{}`, which has {} layers",
+                index,
+                self.label(),
+                self.layers
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use image::{Rgba, RgbaImage};
+
+/// Alpha below this is treated as fully transparent by the block and sprite shaders, which discard
+/// those fragments. Mip levels are adjusted around this value so cutouts keep their shape.
+pub const ALPHA_CUTOFF: f32 = 0.5;
+
+/// Defines `ALPHA_CUTOFF` in the GLSL source, right after its `#version` line, so the shaders
+/// discard at the same value the mip chain is made for.
+pub fn with_alpha_cutoff(source: &str) -> String {
+    let (version, rest) = source.split_at(source.find('\n').map_or(source.len(), |i| i + 1));
+    format!(
+        "{}#define ALPHA_CUTOFF {:?}\n{}",
+        version, ALPHA_CUTOFF, rest
+    )
+}
+
+/// Returns how many mip levels a full chain for a texture this size has, including the full size
+/// level.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Makes every mip level for the image, starting with a copy of the image itself and halving until
+/// it's one pixel wide and tall.
+///
+/// Colors are averaged weighted by alpha so that transparent pixels don't darken the edges of
+/// cutouts. Then, if the image has any transparency, each level's alpha is scaled so that the same
+/// fraction of pixels pass `ALPHA_CUTOFF` as in the full size image. Without that, cutouts like
+/// leaves get thinner with every level and vanish in the distance.
+pub fn generate_mip_chain(img: &RgbaImage) -> Vec<RgbaImage> {
+    let level_count = mip_level_count(img.width(), img.height());
+    let mut levels = Vec::with_capacity(level_count as usize);
+    levels.push(img.clone());
+
+    let is_cutout = img.pixels().any(|p| p[3] < 255);
+    let target_coverage = alpha_coverage(img, 1.0);
+
+    for _ in 1..level_count {
+        let mut next = downsample(&levels[levels.len() - 1]);
+        if is_cutout {
+            preserve_coverage(&mut next, target_coverage);
+        }
+        levels.push(next);
+    }
+
+    levels
+}
+
+/// Halves the image in each direction (never below one pixel), averaging each 2x2 square with
+/// alpha-weighted colors.
+fn downsample(img: &RgbaImage) -> RgbaImage {
+    let (width, height) = ((img.width() / 2).max(1), (img.height() / 2).max(1));
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let mut color_sum = [0.0f32; 3];
+        let mut plain_color_sum = [0.0f32; 3];
+        let mut alpha_sum = 0.0f32;
+        let mut count = 0.0f32;
+
+        for dy in 0..2 {
+            for dx in 0..2 {
+                // clamp for images with an odd (or one) pixel width or height
+                let sx = (x * 2 + dx).min(img.width() - 1);
+                let sy = (y * 2 + dy).min(img.height() - 1);
+                let p = img.get_pixel(sx, sy);
+
+                let alpha = p[3] as f32 / 255.0;
+                for c in 0..3 {
+                    color_sum[c] += p[c] as f32 * alpha;
+                    plain_color_sum[c] += p[c] as f32;
+                }
+                alpha_sum += alpha;
+                count += 1.0;
+            }
+        }
+
+        let mut out = [0u8; 4];
+        for c in 0..3 {
+            out[c] = if alpha_sum > 0.0 {
+                (color_sum[c] / alpha_sum).round() as u8
+            } else {
+                // fully transparent; keep the colors anyway so they don't bleed black when
+                // filtered
+                (plain_color_sum[c] / count).round() as u8
+            };
+        }
+        out[3] = (alpha_sum / count * 255.0).round() as u8;
+
+        Rgba(out)
+    })
+}
+
+/// Returns the fraction of pixels whose alpha, multiplied by `scale`, passes the cutoff.
+fn alpha_coverage(img: &RgbaImage, scale: f32) -> f32 {
+    let passing = img
+        .pixels()
+        .filter(|p| p[3] as f32 / 255.0 * scale >= ALPHA_CUTOFF)
+        .count();
+
+    passing as f32 / (img.width() * img.height()) as f32
+}
+
+/// Scales the image's alpha so that its coverage is as close as possible to `target`.
+fn preserve_coverage(img: &mut RgbaImage, target: f32) {
+    // binary search for the scale; coverage only grows as the scale does
+    let (mut low, mut high) = (0.0f32, 4.0f32);
+    for _ in 0..16 {
+        let middle = (low + high) / 2.0;
+        if alpha_coverage(img, middle) < target {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    let scale = high;
+    for p in img.pixels_mut() {
+        p[3] = (p[3] as f32 * scale).min(255.0).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_counts() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(16, 16), 5);
+        assert_eq!(mip_level_count(16, 4), 5);
+        assert_eq!(mip_level_count(17, 3), 5);
+    }
+
+    #[test]
+    fn chain_halves_down_to_one_pixel() {
+        let chain = generate_mip_chain(&RgbaImage::new(16, 8));
+        let sizes: Vec<_> = chain.iter().map(|l| l.dimensions()).collect();
+
+        assert_eq!(sizes, vec![(16, 8), (8, 4), (4, 2), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_darken_colors() {
+        // a green leaf pixel next to black, fully transparent pixels
+        let img = RgbaImage::from_fn(2, 2, |x, y| {
+            if x == 0 && y == 0 {
+                Rgba([0, 200, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        let chain = generate_mip_chain(&img);
+        assert_eq!(chain[1].get_pixel(0, 0)[1], 200);
+    }
+
+    #[test]
+    fn sparse_cutouts_do_not_vanish() {
+        // one opaque pixel in every 2x2 square. plain averaging makes every pixel of the next
+        // level a quarter opaque, which would fail the cutoff everywhere
+        let img = RgbaImage::from_fn(16, 16, |x, y| {
+            if x % 2 == 0 && y % 2 == 0 {
+                Rgba([0, 200, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        for level in generate_mip_chain(&img).iter() {
+            assert!(alpha_coverage(level, 1.0) > 0.0);
+        }
+    }
+
+    #[test]
+    fn partial_coverage_is_kept() {
+        // left half opaque, right half clear: half of every level should stay visible
+        let img = RgbaImage::from_fn(16, 16, |x, _| {
+            if x < 8 {
+                Rgba([0, 200, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        for level in generate_mip_chain(&img).iter().take(4) {
+            assert!((alpha_coverage(level, 1.0) - 0.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn opaque_images_stay_opaque() {
+        let img = RgbaImage::from_fn(8, 8, |x, _| Rgba([x as u8 * 30, 0, 0, 255]));
+
+        for level in generate_mip_chain(&img).iter() {
+            assert!(level.pixels().all(|p| p[3] == 255));
+        }
+    }
+
+    #[test]
+    fn the_cutoff_is_defined_after_the_version() {
+        let source = with_alpha_cutoff("#version 450\nvoid main() {}\n");
+        let lines: Vec<&str> = source.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec!["#version 450", "#define ALPHA_CUTOFF 0.5", "void main() {}"]
+        );
+    }
+}
@@ -1,7 +1,8 @@
 mod errors;
 
-pub mod three;
+pub mod array;
+pub mod mipmap;
 pub mod two;
 
-pub use three::*;
+pub use array::*;
 pub use two::*;
@@ -0,0 +1,304 @@
+use super::EntityId;
+use crate::blocks::Block;
+use crate::physics::{BlockIndex, Terrain};
+use crate::world::{Path, PathCache, PathSearch, SearchState, Walker};
+use cgmath::{InnerSpace, Vector3};
+
+/// How close along the ground a mob has to get to a waypoint, in meters, before heading for the
+/// next one. Any further and mobs would cut corners into the walls they're going around.
+const ARRIVAL_DISTANCE: f32 = 0.1;
+
+/// Ai decides where a mob wants to go. Mobs only ever look at other entities through their ids,
+/// so one that's gone just leaves the mob standing around.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Ai {
+    /// Stands still.
+    Idle,
+
+    /// Walks toward another entity at `speed` meters per second, stopping once it's within
+    /// `distance` meters of it.
+    Follow {
+        target: EntityId,
+        speed: f32,
+        distance: f32,
+    },
+
+    /// Runs away from another entity at `speed` meters per second until it's at least `distance`
+    /// meters away.
+    Flee {
+        from: EntityId,
+        speed: f32,
+        distance: f32,
+    },
+
+    /// Like Follow, but finds its way around walls and up steps along a Route instead of walking
+    /// straight at the other entity.
+    Seek {
+        target: EntityId,
+        speed: f32,
+        distance: f32,
+    },
+}
+
+impl Ai {
+    /// Returns the velocity along the ground that a mob at `position` wants to be going.
+    /// `position_of` looks up where other entities are. Seeking mobs only head straight for their
+    /// target like this until they have a path to walk along.
+    pub fn target_velocity<F>(&self, position: Vector3<f32>, position_of: F) -> Vector3<f32>
+    where
+        F: Fn(EntityId) -> Option<Vector3<f32>>,
+    {
+        let still = Vector3::new(0.0, 0.0, 0.0);
+
+        let (other, speed, towards, distance) = match *self {
+            Self::Idle => return still,
+            Self::Follow {
+                target,
+                speed,
+                distance,
+            }
+            | Self::Seek {
+                target,
+                speed,
+                distance,
+            } => (target, speed, true, distance),
+            Self::Flee {
+                from,
+                speed,
+                distance,
+            } => (from, speed, false, distance),
+        };
+
+        let other = match position_of(other) {
+            Some(p) => p,
+            None => return still,
+        };
+
+        // mobs walk, so only how far apart they are along the ground matters
+        let mut offset = other - position;
+        offset.y = 0.0;
+        let apart = offset.magnitude();
+
+        if towards && apart > distance {
+            offset / apart * speed
+        } else if !towards && apart < distance && apart > 0.0 {
+            -offset / apart * speed
+        } else {
+            still
+        }
+    }
+}
+
+/// Steering is which way a mob's Ai decided to go this tick, for pushing its body that way before
+/// every physics step.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Steering {
+    /// The velocity along the ground to speed up or slow down to.
+    pub velocity: Vector3<f32>,
+
+    /// Whether to jump at the first chance this tick.
+    pub jump: bool,
+}
+
+/// Route is a seeking mob's way to its goal: the path it's still searching for, or the one it's
+/// walking along.
+pub struct Route {
+    goal: BlockIndex,
+    search: Option<PathSearch>,
+    path: Option<Path>,
+
+    /// Where the mob heads for along the path, and which of them is next.
+    waypoints: Vec<Vector3<f32>>,
+    next: usize,
+}
+
+impl Route {
+    /// Starts on the way from `start` to `goal`, walking a path from `cache` if there's one
+    /// already, and otherwise searching for one looking at no more than `max_nodes` nodes.
+    pub fn new(
+        walker: Walker,
+        start: BlockIndex,
+        goal: BlockIndex,
+        max_nodes: usize,
+        cache: &mut PathCache,
+    ) -> Self {
+        let mut route = Self {
+            goal,
+            search: None,
+            path: None,
+            waypoints: Vec::new(),
+            next: 0,
+        };
+
+        match cache.get(walker, start, goal) {
+            Some(path) => route.follow(path.clone()),
+            None => route.search = Some(PathSearch::new(walker, start, goal, max_nodes)),
+        }
+        route
+    }
+
+    pub fn goal(&self) -> BlockIndex {
+        self.goal
+    }
+
+    /// Returns true if the route has a path to walk along.
+    pub fn is_found(&self) -> bool {
+        self.path.is_some()
+    }
+
+    fn follow(&mut self, path: Path) {
+        self.waypoints = path.waypoints();
+        self.path = Some(path);
+
+        // already standing on the first one
+        self.next = 1;
+    }
+
+    /// Searches a little further, looking at up to `budget` more nodes, and puts the path in
+    /// `cache` once it's found. Does nothing once the search is done.
+    pub fn search<T: Terrain + ?Sized>(
+        &mut self,
+        terrain: &T,
+        budget: usize,
+        cache: &mut PathCache,
+    ) {
+        let state = match self.search.as_mut() {
+            Some(search) => search.step(terrain, budget),
+            None => return,
+        };
+
+        match state {
+            SearchState::Searching => return,
+            SearchState::Found(path) => {
+                cache.insert(path.clone());
+                self.follow(path);
+            }
+            SearchState::Unreachable => {}
+        }
+        self.search = None;
+    }
+
+    /// Returns true if the search is over and found no way to the goal.
+    pub fn is_unreachable(&self) -> bool {
+        self.search.is_none() && self.path.is_none()
+    }
+
+    /// Returns true if a change to the block could mean the route's path can't be walked anymore.
+    pub fn depends_on(&self, block: BlockIndex) -> bool {
+        self.path
+            .as_ref()
+            .map(|p| p.depends_on(block))
+            .unwrap_or(false)
+    }
+
+    /// Returns the velocity along the ground that takes a mob with its feet at `feet` toward the
+    /// next waypoint, and whether it has to jump to get there: up a step, or across a gap. Moves
+    /// on to the waypoint after once the mob gets close. Returns None if there's no path, or the
+    /// mob has walked all of it.
+    pub fn steer(&mut self, feet: Vector3<f32>, speed: f32) -> Option<(Vector3<f32>, bool)> {
+        let along_ground = |v: Vector3<f32>| Vector3::new(v.x, 0.0, v.z);
+
+        while self.next < self.waypoints.len()
+            && along_ground(self.waypoints[self.next] - feet).magnitude() < ARRIVAL_DISTANCE
+        {
+            self.next += 1;
+        }
+
+        let to = *self.waypoints.get(self.next)?;
+        let leg = to - self.waypoints[self.next - 1];
+        let jump = leg.y > 0.0 || along_ground(leg).magnitude() > Block::WIDTH * 1.5;
+
+        Some((along_ground(to - feet).normalize_to(speed), jump))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_and_flees_along_the_ground() {
+        let target = EntityId(7);
+        let at = |id: EntityId| {
+            if id == target {
+                Some(Vector3::new(4.0, 10.0, 0.0))
+            } else {
+                None
+            }
+        };
+        let here = Vector3::new(0.0, 0.0, 0.0);
+
+        let follow = Ai::Follow {
+            target,
+            speed: 2.0,
+            distance: 1.0,
+        };
+        assert_eq!(
+            follow.target_velocity(here, at),
+            Vector3::new(2.0, 0.0, 0.0)
+        );
+
+        // close enough
+        let near = Vector3::new(3.5, 0.0, 0.0);
+        assert_eq!(
+            follow.target_velocity(near, at),
+            Vector3::new(0.0, 0.0, 0.0)
+        );
+
+        let flee = Ai::Flee {
+            from: target,
+            speed: 3.0,
+            distance: 5.0,
+        };
+        assert_eq!(flee.target_velocity(here, at), Vector3::new(-3.0, 0.0, 0.0));
+
+        // nobody to follow
+        let lost = Ai::Follow {
+            target: EntityId(8),
+            speed: 2.0,
+            distance: 1.0,
+        };
+        assert_eq!(lost.target_velocity(here, at), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn routes_are_searched_bit_by_bit_and_walked_waypoint_by_waypoint() {
+        let step = |(i, j, _): BlockIndex| j < 0 || (i >= 3 && j < 1);
+        let walker = Walker::fitting(Vector3::new(0.2, 0.2, 0.2));
+        let mut cache = PathCache::new();
+
+        let mut route = Route::new(walker, (0, 0, 0), (5, 1, 0), 500, &mut cache);
+        route.search(&step, 1, &mut cache);
+        assert!(!route.is_found());
+        for _ in 0..100 {
+            route.search(&step, 1, &mut cache);
+        }
+        assert!(route.is_found());
+
+        // the next mob along doesn't have to look for it
+        assert_eq!(cache.len(), 1);
+        assert!(Route::new(walker, (0, 0, 0), (5, 1, 0), 500, &mut cache).is_found());
+
+        let at = |node: BlockIndex| walker.feet_at(node);
+        assert_eq!(
+            route.steer(at((0, 0, 0)), 2.0),
+            Some((Vector3::new(2.0, 0.0, 0.0), false))
+        );
+        assert_eq!(
+            route.steer(at((1, 0, 0)), 2.0).map(|(_, jump)| jump),
+            Some(false)
+        );
+
+        // up the step
+        assert_eq!(
+            route.steer(at((2, 0, 0)), 2.0).map(|(_, jump)| jump),
+            Some(true)
+        );
+        assert_eq!(
+            route.steer(at((3, 1, 0)), 2.0).map(|(_, jump)| jump),
+            Some(false)
+        );
+        assert!(route.steer(at((4, 1, 0)), 2.0).is_some());
+        assert_eq!(route.steer(at((5, 1, 0)), 2.0), None);
+    }
+}
@@ -0,0 +1,978 @@
+mod ai;
+
+pub use ai::*;
+
+use crate::characters::{Character, Damage, DamageSource, FacingDirection, Health};
+use crate::items::{Backpack, ItemStack};
+use crate::maths::AABB;
+use crate::physics::{BlockIndex, BodyHandle, ContactEvent, PhysicalObject, PhysicsWorld, Terrain};
+use crate::sprite::{Animation, Billboard, Sprite, SpriteInstance, SpriteLayers};
+use crate::world::{PathCache, Walker};
+use cgmath::Vector3;
+use std::collections::BTreeMap;
+
+/// How quickly mobs can speed up, slow down, or turn around, in meters per second per second.
+const MOB_ACCELERATION: f32 = 20.0;
+
+/// How fast mobs leave the ground when they jump, in meters per second. Enough to get up a step,
+/// or across a one block gap.
+const MOB_JUMP_SPEED: f32 = 4.0;
+
+/// How many nodes each seeking mob's path search looks at each tick, and how many in all before
+/// it gives up.
+const PATH_SEARCH_BUDGET: usize = 64;
+const MAX_PATH_NODES: usize = 2000;
+
+/// Half the width of item drops and projectiles, in meters, and how heavy they are, in kilograms.
+const ITEM_DROP_HALF_SIZE: f32 = 0.1;
+const ITEM_DROP_MASS: f32 = 0.5;
+const PROJECTILE_HALF_SIZE: f32 = 0.05;
+const PROJECTILE_MASS: f32 = 0.05;
+
+/// Identifies an entity. Ids are never reused, so an id held onto after its entity is gone stays
+/// invalid instead of pointing at something else.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId(u64);
+
+/// What an entity is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntityKind {
+    Player,
+    Mob,
+    ItemDrop,
+    Projectile,
+}
+
+/// Projectile is what makes an entity hurt whatever it flies into.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Projectile {
+    /// Whoever fired it, which it flies right through.
+    pub owner: Option<EntityId>,
+    pub damage: f32,
+}
+
+/// Appearance is how an entity without a character of its own is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Appearance {
+    pub animation: Animation,
+    pub sprite: Sprite,
+
+    /// How wide and tall the sprite is, in meters.
+    pub size: (f32, f32),
+    pub billboard: Billboard,
+    pub facing: FacingDirection,
+}
+
+impl Appearance {
+    /// Shows the animation from the start, standing upright.
+    pub fn new(animation: Animation, size: (f32, f32)) -> Self {
+        Self {
+            animation,
+            sprite: animation.sprite(),
+            size,
+            billboard: Billboard::AxisLocked,
+            facing: Default::default(),
+        }
+    }
+}
+
+/// Attack is one entity hurting another, waiting for the Attacks system to land it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Attack {
+    pub target: EntityId,
+    pub damage: Damage,
+}
+
+/// The systems that update entities, in the order they run each tick.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum System {
+    /// Mobs decide where to go, and a little more of their path searches is done.
+    Ai,
+
+    /// Characters think.
+    Characters,
+
+    /// Every body moves, with characters and mobs pushing their bodies around before each step.
+    Physics,
+
+    /// Positions, and characters, catch up with where bodies ended up.
+    Positions,
+
+    /// Projectiles that hit something turn into attacks.
+    Projectiles,
+
+    /// Attacks hurt their targets.
+    Attacks,
+
+    /// Health comes back, and the dead are removed, spilling whatever they carried.
+    Health,
+
+    /// Sprites move on to their next frames.
+    Sprites,
+}
+
+impl System {
+    pub const ORDER: [System; 8] = [
+        Self::Ai,
+        Self::Characters,
+        Self::Physics,
+        Self::Positions,
+        Self::Projectiles,
+        Self::Attacks,
+        Self::Health,
+        Self::Sprites,
+    ];
+}
+
+/// Entities is everything that lives in the world: players, mobs, item drops and projectiles. An
+/// entity is just an id, with each of its components stored by that id, and entities only refer
+/// to each other by id. Characters look after their own health and backpack, so they don't get
+/// those components, but their bodies are in the physics world like everyone else's.
+pub struct Entities {
+    next_id: u64,
+
+    kinds: BTreeMap<EntityId, EntityKind>,
+    positions: BTreeMap<EntityId, Vector3<f32>>,
+    bodies: BTreeMap<EntityId, BodyHandle>,
+    health: BTreeMap<EntityId, Health>,
+    inventories: BTreeMap<EntityId, Backpack>,
+    ai: BTreeMap<EntityId, Ai>,
+    routes: BTreeMap<EntityId, Route>,
+    steering: BTreeMap<EntityId, Steering>,
+    appearances: BTreeMap<EntityId, Appearance>,
+    characters: BTreeMap<EntityId, Box<dyn Character>>,
+    items: BTreeMap<EntityId, ItemStack>,
+    projectiles: BTreeMap<EntityId, Projectile>,
+
+    /// Moves every body.
+    physics: PhysicsWorld,
+
+    /// Attacks waiting for the Attacks system.
+    attacks: Vec<Attack>,
+
+    /// Paths seeking mobs have found, forgotten as the blocks along them change.
+    paths: PathCache,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            kinds: BTreeMap::new(),
+            positions: BTreeMap::new(),
+            bodies: BTreeMap::new(),
+            health: BTreeMap::new(),
+            inventories: BTreeMap::new(),
+            ai: BTreeMap::new(),
+            routes: BTreeMap::new(),
+            steering: BTreeMap::new(),
+            appearances: BTreeMap::new(),
+            characters: BTreeMap::new(),
+            items: BTreeMap::new(),
+            projectiles: BTreeMap::new(),
+            physics: PhysicsWorld::new(),
+            attacks: Vec::new(),
+            paths: PathCache::new(),
+        }
+    }
+
+    fn spawn(&mut self, kind: EntityKind, position: Vector3<f32>) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+
+        self.kinds.insert(id, kind);
+        self.positions.insert(id, position);
+        id
+    }
+
+    fn add_body(&mut self, id: EntityId, body: PhysicalObject) {
+        let handle = self.physics.add_body(body);
+        self.bodies.insert(id, handle);
+    }
+
+    /// Adds a player at `position`, controlled by whoever's playing.
+    pub fn spawn_player<C: Character + 'static>(
+        &mut self,
+        character: C,
+        position: Vector3<f32>,
+    ) -> EntityId {
+        let id = self.spawn(EntityKind::Player, position);
+        self.add_body(id, character.body_at(position));
+        self.characters.insert(id, Box::new(character));
+        id
+    }
+
+    /// Adds a mob that goes wherever its Ai wants.
+    pub fn spawn_mob(
+        &mut self,
+        body: PhysicalObject,
+        health: Health,
+        ai: Ai,
+        appearance: Appearance,
+    ) -> EntityId {
+        let id = self.spawn(EntityKind::Mob, body.position());
+        self.add_body(id, body);
+        self.health.insert(id, health);
+        self.inventories.insert(id, Vec::new());
+        self.ai.insert(id, ai);
+        self.appearances.insert(id, appearance);
+        id
+    }
+
+    /// Drops items into the world at `position`.
+    pub fn spawn_item_drop(&mut self, position: Vector3<f32>, stack: ItemStack) -> EntityId {
+        let id = self.spawn(EntityKind::ItemDrop, position);
+
+        let mut body = PhysicalObject::new(
+            ITEM_DROP_MASS,
+            AABB {
+                center_pos: Vector3::new(0.0, 0.0, 0.0),
+                half_size: Vector3::new(
+                    ITEM_DROP_HALF_SIZE,
+                    ITEM_DROP_HALF_SIZE,
+                    ITEM_DROP_HALF_SIZE,
+                ),
+            },
+        );
+        body.set_position(position);
+
+        self.add_body(id, body);
+        self.items.insert(id, stack);
+        id
+    }
+
+    /// Fires a projectile from `position` at `velocity`, in meters per second. It's gone once it
+    /// hits something that can be hurt, or comes to rest on the ground.
+    pub fn spawn_projectile(
+        &mut self,
+        position: Vector3<f32>,
+        velocity: Vector3<f32>,
+        projectile: Projectile,
+    ) -> EntityId {
+        let id = self.spawn(EntityKind::Projectile, position);
+
+        let mut body = PhysicalObject::new(
+            PROJECTILE_MASS,
+            AABB {
+                center_pos: Vector3::new(0.0, 0.0, 0.0),
+                half_size: Vector3::new(
+                    PROJECTILE_HALF_SIZE,
+                    PROJECTILE_HALF_SIZE,
+                    PROJECTILE_HALF_SIZE,
+                ),
+            },
+        );
+        body.set_position(position);
+        body.apply_impulse(velocity * PROJECTILE_MASS);
+
+        self.add_body(id, body);
+        self.projectiles.insert(id, projectile);
+        id
+    }
+
+    /// Removes the entity and every one of its components. Returns false if it was already gone.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if self.kinds.remove(&id).is_none() {
+            return false;
+        }
+
+        if let Some(handle) = self.bodies.remove(&id) {
+            self.physics.remove_body(handle);
+        }
+        self.positions.remove(&id);
+        self.health.remove(&id);
+        self.inventories.remove(&id);
+        self.ai.remove(&id);
+        self.routes.remove(&id);
+        self.steering.remove(&id);
+        self.appearances.remove(&id);
+        self.characters.remove(&id);
+        self.items.remove(&id);
+        self.projectiles.remove(&id);
+        true
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.kinds.contains_key(&id)
+    }
+
+    /// Iterates over every entity, oldest first.
+    pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.kinds.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    pub fn kind(&self, id: EntityId) -> Option<EntityKind> {
+        self.kinds.get(&id).copied()
+    }
+
+    /// Returns where the entity was as of the last tick.
+    pub fn position(&self, id: EntityId) -> Option<Vector3<f32>> {
+        self.positions.get(&id).copied()
+    }
+
+    pub fn body(&self, id: EntityId) -> Option<&PhysicalObject> {
+        self.bodies.get(&id).and_then(|&h| self.physics.body(h))
+    }
+
+    pub fn body_mut(&mut self, id: EntityId) -> Option<&mut PhysicalObject> {
+        let handle = *self.bodies.get(&id)?;
+        self.physics.body_mut(handle)
+    }
+
+    pub fn health(&self, id: EntityId) -> Option<&Health> {
+        self.health.get(&id)
+    }
+
+    pub fn inventory(&self, id: EntityId) -> Option<&Backpack> {
+        self.inventories.get(&id)
+    }
+
+    pub fn inventory_mut(&mut self, id: EntityId) -> Option<&mut Backpack> {
+        self.inventories.get_mut(&id)
+    }
+
+    pub fn ai(&self, id: EntityId) -> Option<&Ai> {
+        self.ai.get(&id)
+    }
+
+    /// Changes what a mob is up to. Does nothing to entities without an Ai.
+    pub fn set_ai(&mut self, id: EntityId, ai: Ai) {
+        if let Some(a) = self.ai.get_mut(&id) {
+            *a = ai;
+            self.routes.remove(&id);
+        }
+    }
+
+    /// Forgets paths through blocks that have changed, so that mobs walking them look for new
+    /// ones. Mobs that found no way at all look again too, since any change might open one up.
+    pub fn forget_paths(&mut self, changed: &[BlockIndex]) {
+        if changed.is_empty() {
+            return;
+        }
+
+        self.paths.invalidate(changed);
+        self.routes.retain(|_, route| {
+            !route.is_unreachable() && !changed.iter().any(|&b| route.depends_on(b))
+        });
+    }
+
+    pub fn appearance(&self, id: EntityId) -> Option<&Appearance> {
+        self.appearances.get(&id)
+    }
+
+    pub fn character(&self, id: EntityId) -> Option<&dyn Character> {
+        self.characters.get(&id).map(|c| c.as_ref())
+    }
+
+    pub fn character_mut(&mut self, id: EntityId) -> Option<&mut (dyn Character + 'static)> {
+        self.characters.get_mut(&id).map(|c| c.as_mut())
+    }
+
+    /// Returns what an item drop is carrying.
+    pub fn item(&self, id: EntityId) -> Option<&ItemStack> {
+        self.items.get(&id)
+    }
+
+    /// Queues an attack, which lands during the next tick's Attacks system.
+    pub fn attack(&mut self, attack: Attack) {
+        self.attacks.push(attack);
+    }
+
+    /// Runs every system, in `System::ORDER`.
+    pub fn tick<T: Terrain + ?Sized>(&mut self, delta_sec: f32, terrain: &T) {
+        for &system in System::ORDER.iter() {
+            self.run(system, delta_sec, terrain);
+        }
+    }
+
+    fn run<T: Terrain + ?Sized>(&mut self, system: System, delta_sec: f32, terrain: &T) {
+        match system {
+            System::Ai => self.plan_mobs(terrain),
+            System::Characters => {
+                for character in self.characters.values_mut() {
+                    character.logic(delta_sec);
+                }
+            }
+            System::Physics => {
+                let characters = &mut self.characters;
+                let mobs = &mut self.steering;
+                let bodies = &self.bodies;
+                self.physics.update_with(delta_sec, terrain, |world| {
+                    for (id, character) in characters.iter_mut() {
+                        if let Some(&handle) = bodies.get(id) {
+                            character.steer(world, handle);
+                        }
+                    }
+                    for (id, steering) in mobs.iter_mut() {
+                        if let Some(&handle) = bodies.get(id) {
+                            steer_mob(world, handle, steering);
+                        }
+                    }
+                });
+            }
+            System::Positions => {
+                for (&id, &handle) in self.bodies.iter() {
+                    if let Some(body) = self.physics.body(handle) {
+                        self.positions.insert(id, body.position());
+                    }
+                }
+                let physics = &self.physics;
+                for (id, character) in self.characters.iter_mut() {
+                    if let Some(body) = self.bodies.get(id).and_then(|&h| physics.body(h)) {
+                        character.settle(body, delta_sec);
+                    }
+                }
+            }
+            System::Projectiles => self.land_projectiles(),
+            System::Attacks => {
+                for attack in std::mem::take(&mut self.attacks) {
+                    if let Some(character) = self.characters.get_mut(&attack.target) {
+                        character.damage(attack.damage);
+                    } else if let Some(health) = self.health.get_mut(&attack.target) {
+                        health.damage(attack.damage);
+                    }
+                }
+            }
+            System::Health => {
+                for health in self.health.values_mut() {
+                    health.logic(delta_sec);
+                    health.regenerate(delta_sec);
+                }
+                self.remove_dead();
+            }
+            System::Sprites => {
+                for appearance in self.appearances.values_mut() {
+                    appearance.sprite.animate(delta_sec);
+                }
+            }
+        }
+    }
+
+    /// Decides which way each mob steers this tick, toward the velocity its Ai wants.
+    fn plan_mobs<T: Terrain + ?Sized>(&mut self, terrain: &T) {
+        self.steering.clear();
+
+        let mobs: Vec<(EntityId, Ai)> = self.ai.iter().map(|(&id, &ai)| (id, ai)).collect();
+        for (id, ai) in mobs {
+            let handle = match self.bodies.get(&id) {
+                Some(&h) => h,
+                None => continue,
+            };
+            let hitbox = match self.physics.body(handle) {
+                Some(b) => *b.hitbox(),
+                None => continue,
+            };
+
+            let positions = &self.positions;
+            let heading =
+                ai.target_velocity(hitbox.center_pos, |other| positions.get(&other).copied());
+            let (target, jump) = match ai {
+                Ai::Seek { target, speed, .. } => {
+                    self.seek(id, target, speed, &hitbox, heading, terrain)
+                }
+                _ => (heading, false),
+            };
+
+            self.steering.insert(
+                id,
+                Steering {
+                    velocity: target,
+                    jump,
+                },
+            );
+
+            if let Some(appearance) = self.appearances.get_mut(&id) {
+                if target.x < 0.0 {
+                    appearance.facing = FacingDirection::Left;
+                } else if target.x > 0.0 {
+                    appearance.facing = FacingDirection::Right;
+                }
+            }
+        }
+    }
+
+    /// Returns the velocity along the ground that takes a seeking mob, with the hitbox, toward
+    /// `target` along its route, and whether it has to jump to keep going. Searches a little
+    /// further for the route's path first, heading straight along `heading` until it's found.
+    fn seek<T: Terrain + ?Sized>(
+        &mut self,
+        id: EntityId,
+        target: EntityId,
+        speed: f32,
+        hitbox: &AABB,
+        heading: Vector3<f32>,
+        terrain: &T,
+    ) -> (Vector3<f32>, bool) {
+        let target_feet = match self.body(target).map(|b| feet(b.hitbox())) {
+            Some(f) if heading != Vector3::new(0.0, 0.0, 0.0) => f,
+
+            // close enough already, or nobody left to seek
+            _ => {
+                self.routes.remove(&id);
+                return (heading, false);
+            }
+        };
+
+        let walker = Walker::fitting(hitbox.half_size);
+        let goal = walker.node_at(target_feet);
+
+        // the target has moved on, so the old route goes somewhere else
+        if self
+            .routes
+            .get(&id)
+            .map(|r| r.goal() != goal)
+            .unwrap_or(true)
+        {
+            let start = walker.node_at(feet(hitbox));
+            let route = Route::new(walker, start, goal, MAX_PATH_NODES, &mut self.paths);
+            self.routes.insert(id, route);
+        }
+
+        let route = self.routes.get_mut(&id).unwrap();
+        route.search(terrain, PATH_SEARCH_BUDGET, &mut self.paths);
+        route.steer(feet(hitbox), speed).unwrap_or((heading, false))
+    }
+
+    /// Returns true if the entity has health to lose.
+    fn can_be_hurt(&self, id: EntityId) -> bool {
+        self.characters.contains_key(&id) || self.health.contains_key(&id)
+    }
+
+    /// Turns projectiles that hit something into attacks, and removes them along with the ones
+    /// that have come to rest.
+    fn land_projectiles(&mut self) {
+        let owners: BTreeMap<BodyHandle, EntityId> = self
+            .bodies
+            .iter()
+            .map(|(&id, &handle)| (handle, id))
+            .collect();
+
+        let mut hits = Vec::new();
+        for event in self.physics.events() {
+            if let ContactEvent::Bodies { a, b } = *event {
+                for &(projectile, other) in [(a, b), (b, a)].iter() {
+                    if let (Some(&p), Some(&o)) = (owners.get(&projectile), owners.get(&other)) {
+                        hits.push((p, o));
+                    }
+                }
+            }
+        }
+
+        let mut spent = Vec::new();
+        for (&id, &handle) in self.bodies.iter() {
+            let body = match (
+                self.projectiles.contains_key(&id),
+                self.physics.body(handle),
+            ) {
+                (true, Some(b)) => b,
+                _ => continue,
+            };
+
+            if body.on_ground() {
+                spent.push(id);
+            }
+        }
+
+        for (id, target) in hits {
+            let hit = match self.projectiles.get(&id) {
+                Some(p) => p.owner != Some(target) && self.can_be_hurt(target),
+                None => false,
+            };
+
+            // each projectile only hits one thing
+            if hit {
+                let projectile = self.projectiles.remove(&id).unwrap();
+                self.attacks.push(Attack {
+                    target,
+                    damage: Damage::new(DamageSource::Projectile, projectile.damage),
+                });
+                spent.push(id);
+            }
+        }
+
+        for id in spent {
+            self.despawn(id);
+        }
+    }
+
+    /// Removes characters that have finished dying and mobs with no health left, dropping
+    /// whatever they carried where they were.
+    fn remove_dead(&mut self) {
+        let dead: Vec<EntityId> = self
+            .characters
+            .iter()
+            .filter(|(_, c)| !c.is_alive())
+            .map(|(&id, _)| id)
+            .chain(
+                self.health
+                    .iter()
+                    .filter(|(_, h)| h.is_dead())
+                    .map(|(&id, _)| id),
+            )
+            .collect();
+
+        for id in dead {
+            let position = self
+                .position(id)
+                .unwrap_or_else(|| Vector3::new(0.0, 0.0, 0.0));
+
+            let mut spilled = self.inventories.remove(&id).unwrap_or_default();
+            if let Some(character) = self.characters.get_mut(&id) {
+                spilled.append(&mut character.take_spilled());
+            }
+
+            self.despawn(id);
+            for stack in spilled {
+                self.spawn_item_drop(position, stack);
+            }
+        }
+    }
+
+    /// Returns the sprite of every entity that has one and whose frame is in `layers`. Sprites
+    /// stand on the bottom of their entity's body.
+    pub fn sprite_instances(&self, layers: &SpriteLayers) -> Vec<SpriteInstance> {
+        let mut instances: Vec<SpriteInstance> = self
+            .characters
+            .iter()
+            .filter_map(|(&id, c)| c.sprite_instance(self.body(id)?.hitbox(), layers))
+            .collect();
+
+        for (&id, appearance) in self.appearances.iter() {
+            let layer = match layers.layer(&appearance.animation, appearance.sprite.frame()) {
+                Some(l) => l,
+                None => continue,
+            };
+
+            let feet = match self.body(id) {
+                Some(body) => feet(body.hitbox()),
+                None => match self.position(id) {
+                    Some(p) => p,
+                    None => continue,
+                },
+            };
+
+            instances.push(SpriteInstance::new(
+                feet,
+                appearance.size,
+                layer,
+                appearance.billboard,
+                appearance.facing.is_flipped(),
+            ));
+        }
+
+        instances
+    }
+}
+
+/// Returns the bottom middle of a hitbox, where whoever it belongs to is standing.
+fn feet(hitbox: &AABB) -> Vector3<f32> {
+    hitbox.center_pos - Vector3::new(0.0, hitbox.half_size.y, 0.0)
+}
+
+/// Pushes a mob's body the way it's steering, as hard as it can, and jumps it if it wants to and
+/// can. Called before every physics step, the same as `Character::steer`.
+fn steer_mob(world: &mut PhysicsWorld, handle: BodyHandle, steering: &mut Steering) {
+    world.steer(handle, steering.velocity, MOB_ACCELERATION);
+
+    if let Some(body) = world.body_mut(handle) {
+        let (velocity, mass) = (body.velocity(), body.mass());
+
+        // not already on its way up, the same as the chicken
+        if steering.jump && body.on_ground() && velocity.y <= 0.0 {
+            body.apply_impulse(Vector3::new(0.0, (MOB_JUMP_SPEED - velocity.y) * mass, 0.0));
+            steering.jump = false;
+        }
+    }
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::characters::{CharacterAction, Chicken};
+    use crate::items::{Food, Item};
+    use crate::physics::testing::floor;
+    use crate::physics::BlockIndex;
+
+    const STEP: f32 = 1.0 / 60.0;
+
+    fn run_for(entities: &mut Entities, seconds: f32) {
+        for _ in 0..(seconds / STEP) as usize {
+            entities.tick(STEP, &floor);
+        }
+    }
+
+    /// A mob a little bigger than a chicken, standing at `x` along the floor.
+    fn mob(entities: &mut Entities, x: f32, ai: Ai) -> EntityId {
+        let mut body = PhysicalObject::new(
+            5.0,
+            AABB {
+                center_pos: Vector3::new(0.0, 0.0, 0.0),
+                half_size: Vector3::new(0.25, 0.25, 0.25),
+            },
+        );
+        body.set_position(Vector3::new(x, 0.25, 0.25));
+
+        let appearance =
+            Appearance::new(Chicken::animation_for(CharacterAction::Nothing), (0.5, 0.5));
+        entities.spawn_mob(body, Health::new(10.0, 0.0, 0.0), ai, appearance)
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut entities = Entities::new();
+        let first = entities.spawn_item_drop(Vector3::new(0.0, 1.0, 0.0), (Item::Other, 1));
+
+        assert!(entities.despawn(first));
+        assert!(!entities.despawn(first));
+        assert!(entities.body(first).is_none() && entities.item(first).is_none());
+
+        let second = entities.spawn_item_drop(Vector3::new(0.0, 1.0, 0.0), (Item::Other, 1));
+        assert_ne!(first, second);
+        assert!(!entities.contains(first));
+        assert_eq!(entities.ids().collect::<Vec<_>>(), vec![second]);
+    }
+
+    #[test]
+    fn mobs_follow_other_entities_by_id() {
+        let mut entities = Entities::new();
+        let player = entities.spawn_player(Chicken::new(), Vector3::new(5.0, 0.5, 0.25));
+        let follower = mob(
+            &mut entities,
+            0.25,
+            Ai::Follow {
+                target: player,
+                speed: 2.0,
+                distance: 1.0,
+            },
+        );
+
+        run_for(&mut entities, 3.0);
+        let x = entities.position(follower).unwrap().x;
+        assert!(x > 3.5 && x < 4.5, "{}", x);
+        assert_eq!(
+            entities.appearance(follower).map(|a| a.facing),
+            Some(FacingDirection::Right)
+        );
+
+        // with the player gone, there's nobody to follow
+        entities.despawn(player);
+        run_for(&mut entities, 1.0);
+        assert!(entities.body(follower).unwrap().velocity().x.abs() < 0.01);
+    }
+
+    #[test]
+    fn mobs_walk_and_stop_the_same_at_any_frame_rate() {
+        let follow = |frame_rate: f32| {
+            let mut entities = Entities::new();
+            let player = entities.spawn_player(Chicken::new(), Vector3::new(8.0, 0.5, 0.25));
+            let follower = mob(
+                &mut entities,
+                0.25,
+                Ai::Follow {
+                    target: player,
+                    speed: 2.0,
+                    distance: 1.0,
+                },
+            );
+            let (mut fastest, mut backwards): (f32, f32) = (0.0, 0.0);
+
+            for _ in 0..frame_rate as usize {
+                entities.tick(1.0 / frame_rate, &floor);
+                fastest = fastest.max(entities.body(follower).unwrap().velocity().x);
+            }
+
+            entities.despawn(player);
+            for _ in 0..frame_rate as usize {
+                entities.tick(1.0 / frame_rate, &floor);
+                backwards = backwards.min(entities.body(follower).unwrap().velocity().x);
+            }
+
+            // no overshooting its speed, or rocking back and forth when it stops
+            assert!(fastest <= 2.0 + 1e-3, "{} at {}", fastest, frame_rate);
+            assert!(backwards >= -1e-3, "{} at {}", backwards, frame_rate);
+            entities.position(follower).unwrap().x
+        };
+
+        let (slow, fast) = (follow(30.0), follow(240.0));
+        assert!(slow > 2.0, "{}", slow);
+        assert!((slow - fast).abs() < 1e-3, "{} {}", slow, fast);
+    }
+
+    #[test]
+    fn seeking_mobs_find_their_way_around_walls() {
+        // the wall is in the way from z = 0 to 1.5
+        let walled = |(i, j, k): BlockIndex| j < 0 || (i == 6 && k < 3);
+        let mut entities = Entities::new();
+        let player = entities.spawn_player(Chicken::new(), Vector3::new(5.25, 0.25, 0.25));
+
+        // chicken sized, with a little room to spare on the way around
+        let seeker = entities.spawn_mob(
+            Chicken::new().body_at(Vector3::new(0.25, 0.25, 0.25)),
+            Health::new(10.0, 0.0, 0.0),
+            Ai::Seek {
+                target: player,
+                speed: 2.0,
+                distance: 1.0,
+            },
+            Appearance::new(Chicken::animation_for(CharacterAction::Nothing), (0.4, 0.5)),
+        );
+
+        for _ in 0..(8.0 / STEP) as usize {
+            entities.tick(STEP, &walled);
+        }
+        let apart = entities.position(seeker).unwrap() - entities.position(player).unwrap();
+        assert!(apart.x.abs() < 1.2 && apart.z.abs() < 1.2, "{:?}", apart);
+        assert_eq!(entities.paths.len(), 1);
+
+        // somewhere else entirely, then right in the way around
+        entities.forget_paths(&[(20, 0, 20)]);
+        assert_eq!(entities.paths.len(), 1);
+        entities.forget_paths(&[(6, 0, 3)]);
+        assert!(entities.paths.is_empty());
+        assert!(entities.routes.is_empty());
+    }
+
+    #[test]
+    fn mobs_with_no_way_through_look_again_when_blocks_change() {
+        // walls three blocks high every four blocks, penning everyone into their own square
+        let penned = |(i, j, k): BlockIndex| {
+            j < 0 || (j < 3 && (i.rem_euclid(4) == 2 || k.rem_euclid(4) == 2))
+        };
+        let mut entities = Entities::new();
+        let player = entities.spawn_player(Chicken::new(), Vector3::new(5.25, 0.25, 0.25));
+        let seeker = entities.spawn_mob(
+            Chicken::new().body_at(Vector3::new(0.25, 0.25, 0.25)),
+            Health::new(10.0, 0.0, 0.0),
+            Ai::Seek {
+                target: player,
+                speed: 2.0,
+                distance: 1.0,
+            },
+            Appearance::new(Chicken::animation_for(CharacterAction::Nothing), (0.4, 0.5)),
+        );
+
+        for _ in 0..(1.0 / STEP) as usize {
+            entities.tick(STEP, &penned);
+        }
+        assert!(entities.routes[&seeker].is_unreachable());
+
+        // nowhere near the pen, but it could have been a gate opening
+        entities.forget_paths(&[(20, 0, 20)]);
+        assert!(entities.routes.is_empty());
+    }
+
+    #[test]
+    fn projectiles_hurt_what_they_hit() {
+        let mut entities = Entities::new();
+        let target = mob(&mut entities, 3.0, Ai::Idle);
+        let player = entities.spawn_player(Chicken::new(), Vector3::new(0.25, 0.25, 0.25));
+
+        let arrow = entities.spawn_projectile(
+            Vector3::new(0.25, 0.25, 0.25),
+            Vector3::new(15.0, 1.0, 0.0),
+            Projectile {
+                owner: Some(player),
+                damage: 4.0,
+            },
+        );
+        run_for(&mut entities, 1.0);
+
+        assert!(!entities.contains(arrow));
+        assert_eq!(entities.health(target).unwrap().points(), 6.0);
+        assert_eq!(
+            entities.character(player).unwrap().health_left(),
+            entities.character(player).unwrap().lifespan()
+        );
+
+        // one that misses comes to rest on the floor
+        let stray = entities.spawn_projectile(
+            Vector3::new(0.25, 2.0, 0.25),
+            Vector3::new(0.0, 0.0, -5.0),
+            Projectile {
+                owner: None,
+                damage: 4.0,
+            },
+        );
+        run_for(&mut entities, 2.0);
+        assert!(!entities.contains(stray));
+    }
+
+    #[test]
+    fn projectiles_hit_characters_they_run_into() {
+        let mut entities = Entities::new();
+        let player = entities.spawn_player(Chicken::new(), Vector3::new(3.0, 0.25, 0.25));
+        run_for(&mut entities, 0.1);
+
+        let pebble = entities.spawn_projectile(
+            Vector3::new(0.25, 0.25, 0.25),
+            Vector3::new(15.0, 1.0, 0.0),
+            Projectile {
+                owner: None,
+                damage: 2.0,
+            },
+        );
+        run_for(&mut entities, 1.0);
+
+        assert!(!entities.contains(pebble));
+        let chicken = entities.character(player).unwrap();
+        assert!(chicken.health_left() < chicken.lifespan());
+    }
+
+    #[test]
+    fn the_dead_drop_what_they_carried() {
+        let mut entities = Entities::new();
+        let player = entities.spawn_player(Chicken::new(), Vector3::new(3.0, 0.25, 0.25));
+        let victim = mob(&mut entities, 0.25, Ai::Idle);
+        entities
+            .inventory_mut(victim)
+            .unwrap()
+            .push((Item::Food(Food::Nuts), 2));
+        run_for(&mut entities, 0.1);
+
+        let attack = entities
+            .character(player)
+            .unwrap()
+            .attack(None, 20.0, victim);
+        entities.attack(attack);
+        run_for(&mut entities, 0.5);
+
+        assert!(!entities.contains(victim));
+        let drops: Vec<EntityId> = entities
+            .ids()
+            .filter(|&id| entities.kind(id) == Some(EntityKind::ItemDrop))
+            .collect();
+        assert_eq!(drops.len(), 1);
+        assert!(matches!(
+            entities.item(drops[0]),
+            Some((Item::Food(Food::Nuts), 2))
+        ));
+        assert!(entities.position(drops[0]).unwrap().x < 1.0);
+    }
+
+    #[test]
+    fn sprites_come_from_characters_and_appearances() {
+        let mut entities = Entities::new();
+        entities.spawn_player(Chicken::new(), Vector3::new(3.0, 0.25, 0.25));
+        mob(&mut entities, 0.25, Ai::Idle);
+
+        let layers = SpriteLayers::new(&Chicken::animations());
+        assert_eq!(entities.sprite_instances(&layers).len(), 2);
+        assert!(entities
+            .sprite_instances(&SpriteLayers::default())
+            .is_empty());
+    }
+}
@@ -1,20 +1,110 @@
 #![allow(unused_variables)]
 
-use crate::blocks::Chunk;
+use crate::characters::{Chicken, Direction};
+use crate::entities::{Entities, EntityId};
+use crate::items::{Food, Item};
+use crate::sprite::{SpriteBatch, SpriteTextures};
+use crate::world::{CullingStats, World};
+use cgmath::Vector3;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
 
 pub(crate) struct Game {
-    // world: Option<World>,
-    tmp_chunk: Chunk,
+    world: World,
+
+    /// Everything living in the world, the chicken included.
+    entities: Entities,
+
+    /// The chicken being played. Its entity is gone once it has died.
+    player: EntityId,
+    keys: ChickenKeys,
+
+    /// Every character's sprite, drawn during RenderPhase::Characters.
+    sprites: SpriteBatch,
+}
+
+/// Which of the keys steering the chicken are held down.
+#[derive(Default)]
+struct ChickenKeys {
+    left: bool,
+    right: bool,
+    run: bool,
+    squat: bool,
 }
 
 impl Game {
     /// Decided to pass in bind groups and pipelines so that this file doesn't become too crowded.
     pub fn new(device: &wgpu::Device) -> Self {
         println!("creating new game");
-        let tmp_chunk = Chunk::generate(0, 0, 0, &device);
+        let world = World::new(0, device);
+
+        // drops onto the ground from just above it, with a snack for the road
+        let mut chicken = Chicken::new();
+        chicken.backpack_mut().push((Item::Food(Food::Apple), 3));
+
+        let mut entities = Entities::new();
+        let player = entities.spawn_player(chicken, Vector3::new(0.25, 10.0, 0.25));
+
         Self {
-            // world: None,
-            tmp_chunk,
+            world,
+            entities,
+            player,
+            keys: Default::default(),
+            sprites: SpriteBatch::new(),
+        }
+    }
+
+    /// Steers the chicken from the keyboard: left and right arrows walk, right shift runs, the up
+    /// arrow jumps, the down arrow squats, and E eats. WASD still flies the camera.
+    pub fn input(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let pressed = *state == ElementState::Pressed;
+            match keycode {
+                VirtualKeyCode::Left => self.keys.left = pressed,
+                VirtualKeyCode::Right => self.keys.right = pressed,
+                VirtualKeyCode::RShift => self.keys.run = pressed,
+                VirtualKeyCode::Down => self.keys.squat = pressed,
+                VirtualKeyCode::Up if pressed => {
+                    if let Some(chicken) = self.entities.character_mut(self.player) {
+                        chicken.jump(false);
+                    }
+                }
+                VirtualKeyCode::E if pressed => {
+                    if let Some(chicken) = self.entities.character_mut(self.player) {
+                        chicken.eat_from_backpack();
+                    }
+                    return;
+                }
+                _ => return,
+            }
+
+            self.steer_chicken();
+        }
+    }
+
+    fn steer_chicken(&mut self) {
+        let keys = &self.keys;
+        let chicken = match self.entities.character_mut(self.player) {
+            Some(c) => c,
+            None => return,
+        };
+
+        if keys.squat {
+            chicken.down(false);
+        } else if keys.left && !keys.right {
+            chicken.walk(Direction::Left, keys.run);
+        } else if keys.right && !keys.left {
+            chicken.walk(Direction::Right, keys.run);
+        } else {
+            chicken.stop();
         }
     }
 
@@ -22,10 +112,16 @@ impl Game {
         phase: RenderPhase,
         payload: &'a mut crate::RenderPayload,
     ) -> wgpu::RenderPass<'a> {
+        // only the first phase starts over; the rest draw on top of what's already there
+        let load_op = match phase {
+            RenderPhase::World => wgpu::LoadOp::Clear,
+            _ => wgpu::LoadOp::Load,
+        };
+
         let color_attachments = &[wgpu::RenderPassColorAttachmentDescriptor {
             attachment: &payload.frame,
             resolve_target: None,
-            load_op: wgpu::LoadOp::Clear,
+            load_op,
             store_op: wgpu::StoreOp::Store,
             clear_color: wgpu::Color {
                 r: 0.1,
@@ -37,12 +133,13 @@ impl Game {
 
         // determine depth attachment. only ignore depth if the phase is the Interface phase.
         let depth_stencil_attachment = match phase {
+            RenderPhase::Interface => None,
             _ => Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
                 attachment: payload.depth_texture,
-                depth_load_op: wgpu::LoadOp::Clear,
+                depth_load_op: load_op,
                 depth_store_op: wgpu::StoreOp::Store,
                 clear_depth: 1.0,
-                stencil_load_op: wgpu::LoadOp::Clear,
+                stencil_load_op: load_op,
                 stencil_store_op: wgpu::StoreOp::Store,
                 clear_stencil: 0,
             }),
@@ -55,12 +152,15 @@ impl Game {
                 depth_stencil_attachment,
             });
 
-        #[allow(clippy::single_match)]
         match phase {
             RenderPhase::World => {
                 pass.set_pipeline(payload.block_render_pipeline);
                 pass.set_bind_group(0, payload.block_texture_bind_group, &[]);
             }
+            RenderPhase::Characters => {
+                pass.set_pipeline(payload.sprite_render_pipeline);
+                pass.set_bind_group(0, payload.sprite_texture_bind_group, &[]);
+            }
             _ => {}
         }
         pass.set_bind_group(1, payload.uniform_bind_group, &[]);
@@ -68,14 +168,42 @@ impl Game {
         pass
     }
 
-    pub fn logic(&mut self, device: &wgpu::Device, queue: &mut wgpu::Queue) {
-        self.tmp_chunk.logic(device);
+    pub fn logic(
+        &mut self,
+        delta_sec: f32,
+        device: &wgpu::Device,
+        queue: &mut wgpu::Queue,
+        camera: &crate::camera::Camera,
+        sprite_textures: &SpriteTextures,
+    ) {
+        self.entities.tick(delta_sec, &self.world);
+        self.world.logic(device, camera);
+        self.entities
+            .forget_paths(&self.world.take_changed_blocks());
+
+        self.sprites.clear();
+        for sprite in self.entities.sprite_instances(sprite_textures.layers()) {
+            self.sprites.push(sprite);
+        }
+        self.sprites.upload(device);
     }
 
     pub fn render(&self, payload: &mut crate::RenderPayload) {
-        let mut world_render_pass = Self::start_render_pass(RenderPhase::World, payload);
+        let camera = payload.camera;
+
+        {
+            let mut world_render_pass = Self::start_render_pass(RenderPhase::World, payload);
+            self.world.render(&mut world_render_pass, camera);
+        }
+
+        // after the world, so that terrain in front of characters hides them
+        let mut character_render_pass = Self::start_render_pass(RenderPhase::Characters, payload);
+        self.sprites.render(&mut character_render_pass);
+    }
 
-        self.tmp_chunk.render(&mut world_render_pass);
+    /// Returns how many chunks were drawn and culled during the last frame.
+    pub fn culling_stats(&self) -> CullingStats {
+        self.world.culling_stats()
     }
 }
 
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum Food {
     Apple,
@@ -10,3 +10,40 @@ pub enum Food {
     Raspberry,
     Nuts,
 }
+
+impl Food {
+    /// How filling the food is.
+    pub fn nutrition(self) -> Nutrition {
+        let (food, saturation) = match self {
+            Self::Apple => (4.0, 2.4),
+            Self::Blueberry => (1.0, 0.4),
+            Self::Carrot => (3.0, 3.6),
+            Self::Lemon => (2.0, 0.4),
+            Self::Orange => (4.0, 2.4),
+            Self::Pineapple => (8.0, 6.0),
+            Self::Raspberry => (1.0, 0.4),
+            Self::Nuts => (3.0, 4.8),
+        };
+
+        Nutrition { food, saturation }
+    }
+
+    /// How long it takes to eat, in seconds. Berries go down in one bite.
+    pub fn eating_time(self) -> f32 {
+        match self {
+            Self::Blueberry | Self::Raspberry => 0.5,
+            Self::Pineapple => 2.5,
+            _ => 1.5,
+        }
+    }
+}
+
+/// Nutrition is how much food fills a character up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Nutrition {
+    /// How many points of hunger it takes away.
+    pub food: f32,
+
+    /// How long it keeps the character full: saturation is used up before food is.
+    pub saturation: f32,
+}
@@ -1,6 +1,6 @@
 /// Materials apply usually only to tools or weapons. Material describes what something is made out
 /// of.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum Material {
     Wood,
@@ -11,3 +11,20 @@ pub enum Material {
     Gold,
     Diamond,
 }
+
+impl Material {
+    /// How much harder tools and weapons made of this hit, compared to wooden ones.
+    pub fn hardness(self) -> f32 {
+        match self {
+            Self::Wood => 1.0,
+            Self::Stone => 1.25,
+            Self::Bronze => 1.5,
+            Self::Brass => 1.5,
+
+            // gold is soft
+            Self::Gold => 1.25,
+            Self::Steel => 2.0,
+            Self::Diamond => 2.5,
+        }
+    }
+}
@@ -3,9 +3,9 @@ mod material;
 mod resource;
 
 use crate::blocks::Block;
-use food::Food;
-use material::Material;
-use resource::Resource;
+pub use food::{Food, Nutrition};
+pub use material::Material;
+pub use resource::Resource;
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -18,6 +18,18 @@ pub enum Item {
     Other,
 }
 
+impl Item {
+    /// How much harder a hit is with this in hand than with nothing at all. Weapons hit hardest,
+    /// tools less so, and anything else is no better than a bare wing.
+    pub fn damage_multiplier(&self) -> f32 {
+        match self {
+            Self::Weapon(w) => w.damage_multiplier(),
+            Self::Tool(t) => t.damage_multiplier(),
+            _ => 1.0,
+        }
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Tool {
@@ -27,7 +39,28 @@ pub struct Tool {
     lifespan: u32,
 }
 
-#[derive(Debug)]
+impl Tool {
+    pub fn new(tool_type: ToolType, material: Material) -> Self {
+        Self {
+            tool_type,
+            material,
+            health: 100,
+            lifespan: 100,
+        }
+    }
+
+    pub fn damage_multiplier(&self) -> f32 {
+        let base = match self.tool_type {
+            ToolType::Axe => 1.5,
+            ToolType::Pick => 1.25,
+            ToolType::Shovel | ToolType::Hoe => 1.0,
+        };
+
+        base * self.material.hardness()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum ToolType {
     Shovel,
@@ -44,7 +77,30 @@ pub struct Weapon {
     lifespan: f32,
 }
 
-#[derive(Debug)]
+impl Weapon {
+    pub fn new(tool_type: WeaponType, material: Material) -> Self {
+        Self {
+            tool_type,
+            material,
+            health: 100.0,
+            lifespan: 100.0,
+        }
+    }
+
+    pub fn damage_multiplier(&self) -> f32 {
+        let base = match self.tool_type {
+            WeaponType::Sword => 2.0,
+
+            // arrows hurt when they're shot, not swung
+            WeaponType::Arrow => 1.0,
+            WeaponType::Bow => 0.5,
+        };
+
+        base * self.material.hardness()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum WeaponType {
     Sword,
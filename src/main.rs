@@ -9,6 +9,7 @@ mod blocks;
 mod camera;
 mod characters;
 mod engine;
+mod entities;
 mod game;
 mod items;
 mod maths;
@@ -49,8 +50,8 @@ fn main() {
                         visibility: wgpu::ShaderStage::FRAGMENT,
                         ty: wgpu::BindingType::SampledTexture {
                             multisampled: false,
-                            dimension: wgpu::TextureViewDimension::D3,
-                            component_type: wgpu::TextureComponentType::Uint,
+                            dimension: wgpu::TextureViewDimension::D2Array,
+                            component_type: wgpu::TextureComponentType::Float,
                         },
                     },
                     wgpu::BindGroupLayoutEntry {
@@ -63,18 +64,41 @@ fn main() {
             });
 
     let texture_dimensions = (16, 16);
+    let texture_filters = engine::FilterSettings::default();
+
+    // a resource pack can be given as the first argument: `chicky-chicky-rs path/to/pack.zip`
+    let block_textures = {
+        use textures::{BlockTextures, ResourcePack};
+
+        let made = match std::env::args_os().nth(1) {
+            Some(path) => {
+                let pack = match ResourcePack::open(&path) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        eprintln!("couldn't open resource pack {}", e);
+                        std::process::exit(1);
+                    }
+                };
+
+                BlockTextures::from_resource_pack(
+                    engine.get_device(),
+                    &pack,
+                    texture_filters,
+                    &block_texture_bind_group_layout,
+                )
+            }
+            None => BlockTextures::default_textures(
+                engine.get_device(),
+                texture_dimensions,
+                texture_filters,
+                &block_texture_bind_group_layout,
+            ),
+        };
 
-    let default_textures = {
-        use textures::BlockTextures;
-
-        let (textures, cmds) = match BlockTextures::default_textures(
-            engine.get_device(),
-            texture_dimensions,
-            &block_texture_bind_group_layout,
-        ) {
+        let (textures, cmds) = match made {
             Ok(tc) => tc,
             Err(e) => {
-                eprintln!("couldn't make default textures: {}", e);
+                eprintln!("couldn't make block textures: {}", e);
                 std::process::exit(1);
             }
         };
@@ -138,6 +162,39 @@ fn main() {
         }
     };
 
+    // character sprites and their pipeline, which samples a texture array just like blocks do
+    let sprite_textures = {
+        use characters::{Character, Chicken};
+
+        let (textures, cmds) = match sprite::SpriteTextures::new(
+            engine.get_device(),
+            &Chicken::animations(),
+            &block_texture_bind_group_layout,
+        ) {
+            Ok(tc) => tc,
+            Err(e) => {
+                eprintln!("couldn't make sprite textures: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        engine.get_queue().submit(&cmds);
+
+        textures
+    };
+
+    let sprite_render_pipeline = match sprite::make_sprite_render_pipeline(
+        &mut engine,
+        &block_texture_bind_group_layout,
+        &uniform_bind_group_layout,
+    ) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     let camera = camera::Camera::default();
     let camera_controller = camera::CameraController::new(5.0, 1.0);
 
@@ -153,7 +210,9 @@ fn main() {
         block_render_pipeline,
         camera,
         camera_controller,
-        block_textures: default_textures,
+        block_textures,
+        sprite_textures,
+        sprite_render_pipeline,
     };
 
     engine.set_runner(runner);
@@ -172,6 +231,9 @@ struct MainRunner {
 
     block_textures: textures::BlockTextures,
     block_render_pipeline: wgpu::RenderPipeline,
+
+    sprite_textures: sprite::SpriteTextures,
+    sprite_render_pipeline: wgpu::RenderPipeline,
 }
 
 impl engine::Runner for MainRunner {
@@ -189,6 +251,10 @@ impl engine::Runner for MainRunner {
             *control_flow = ControlFlow::Exit;
         } else {
             self.camera_controller.input(event);
+
+            match &mut self.state {
+                GameState::Game(g) => g.input(event),
+            }
         }
     }
 
@@ -204,8 +270,15 @@ impl engine::Runner for MainRunner {
         self.uniforms
             .update(device, &self.camera, &mut self.uniform_buffer, queue);
 
+        match self.block_textures.update(device, delta_sec) {
+            Ok(cmds) => queue.submit(&cmds),
+            Err(e) => eprintln!("couldn't animate block textures: {}", e),
+        }
+
         match &mut self.state {
-            GameState::Game(g) => g.logic(device, queue),
+            GameState::Game(g) => {
+                g.logic(delta_sec, device, queue, &self.camera, &self.sprite_textures)
+            }
         }
 
         true
@@ -224,9 +297,12 @@ impl engine::Runner for MainRunner {
             encoder,
             frame,
             depth_texture,
+            camera: &self.camera,
             block_render_pipeline: &self.block_render_pipeline,
             uniform_bind_group: &self.uniform_bind_group,
             block_texture_bind_group: &self.block_textures.get_bind_group(),
+            sprite_render_pipeline: &self.sprite_render_pipeline,
+            sprite_texture_bind_group: self.sprite_textures.get_bind_group(),
         };
 
         #[allow(clippy::single_match)]
@@ -247,7 +323,10 @@ struct RenderPayload<'a> {
     encoder: &'a mut wgpu::CommandEncoder,
     frame: &'a wgpu::TextureView,
     depth_texture: &'a wgpu::TextureView,
+    camera: &'a camera::Camera,
     block_render_pipeline: &'a wgpu::RenderPipeline,
     block_texture_bind_group: &'a wgpu::BindGroup,
     uniform_bind_group: &'a wgpu::BindGroup,
+    sprite_render_pipeline: &'a wgpu::RenderPipeline,
+    sprite_texture_bind_group: &'a wgpu::BindGroup,
 }
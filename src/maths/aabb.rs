@@ -4,13 +4,31 @@ use cgmath::Vector3;
 
 /// AABB is an Axis-Aligned Bounding Box. it is used to check
 /// for collisions in collision detection.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct AABB {
     pub center_pos: Vector3<f32>,
     pub half_size: Vector3<f32>,
 }
 
 impl AABB {
+    /// Makes an AABB from its lowest and highest corners.
+    pub fn from_min_max(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self {
+            center_pos: (min + max) / 2.0,
+            half_size: (max - min) / 2.0,
+        }
+    }
+
+    /// Returns the corner with the lowest x, y, and z.
+    pub fn min(&self) -> Vector3<f32> {
+        self.center_pos - self.half_size
+    }
+
+    /// Returns the corner with the highest x, y, and z.
+    pub fn max(&self) -> Vector3<f32> {
+        self.center_pos + self.half_size
+    }
+
     // Returns true if the AABC is touching the
     // other AABC
     pub fn collides_with(&self, other: &AABB) -> bool {
@@ -36,4 +54,471 @@ impl AABB {
 
         x_collision && y_collision && z_collision
     }
+
+    /// Returns the box where the two AABBs overlap, or None if they don't. Boxes that only touch
+    /// don't overlap, the same as in `collides_with`.
+    pub fn intersection(&self, other: &AABB) -> Option<AABB> {
+        if !self.collides_with(other) {
+            return None;
+        }
+
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+
+        Some(Self::from_min_max(
+            Vector3::new(
+                min.x.max(other_min.x),
+                min.y.max(other_min.y),
+                min.z.max(other_min.z),
+            ),
+            Vector3::new(
+                max.x.min(other_max.x),
+                max.y.min(other_max.y),
+                max.z.min(other_max.z),
+            ),
+        ))
+    }
+
+    /// Returns the smallest AABB holding both AABBs.
+    pub fn union(&self, other: &AABB) -> AABB {
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+
+        Self::from_min_max(
+            Vector3::new(
+                min.x.min(other_min.x),
+                min.y.min(other_min.y),
+                min.z.min(other_min.z),
+            ),
+            Vector3::new(
+                max.x.max(other_max.x),
+                max.y.max(other_max.y),
+                max.z.max(other_max.z),
+            ),
+        )
+    }
+
+    /// Returns true if the point is inside of the AABB or on its surface.
+    pub fn contains_point(&self, point: Vector3<f32>) -> bool {
+        let (min, max) = (self.min(), self.max());
+
+        (0..3).all(|axis| point[axis] >= min[axis] && point[axis] <= max[axis])
+    }
+
+    /// Returns true if `other` is completely inside of the AABB. Sharing faces still counts.
+    pub fn contains(&self, other: &AABB) -> bool {
+        self.contains_point(other.min()) && self.contains_point(other.max())
+    }
+
+    /// Returns how deep the AABBs overlap along the axis they overlap least on, or 0 if they don't
+    /// overlap.
+    pub fn penetration_depth(&self, other: &AABB) -> f32 {
+        self.minimum_translation(other)
+            .map(|mtv| mtv.x.abs() + mtv.y.abs() + mtv.z.abs())
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the minimum translation vector: the shortest move that takes this AABB out of
+    /// `other`. It's always along a single axis. Returns None if they don't overlap.
+    pub fn minimum_translation(&self, other: &AABB) -> Option<Vector3<f32>> {
+        let mut least: Option<(usize, f32)> = None;
+
+        for axis in 0..3 {
+            let delta = self.center_pos[axis] - other.center_pos[axis];
+            let overlap = self.half_size[axis] + other.half_size[axis] - delta.abs();
+            if overlap <= 0.0 {
+                return None;
+            }
+
+            if least
+                .map(|(_, depth)| overlap < depth.abs())
+                .unwrap_or(true)
+            {
+                // push away from `other`'s center. boxes with the same center go positive
+                let direction = if delta < 0.0 { -1.0 } else { 1.0 };
+                least = Some((axis, overlap * direction));
+            }
+        }
+
+        least.map(|(axis, depth)| {
+            let mut mtv = Vector3::new(0.0, 0.0, 0.0);
+            mtv[axis] = depth;
+            mtv
+        })
+    }
+
+    /// Intersects a ray with the AABB using the slab method. Returns the distances along the ray,
+    /// in multiples of `direction`, where it enters and exits the box. The entry distance is
+    /// negative if the ray starts inside. Returns None if the ray misses, or if the box is
+    /// completely behind it. Rays grazing an edge or face hit.
+    pub fn ray_intersection(
+        &self,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+    ) -> Option<(f32, f32)> {
+        let (min, max) = (self.min(), self.max());
+        let mut entry = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                // parallel to this slab; either always inside it or never
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let near = (min[axis] - origin[axis]) / direction[axis];
+            let far = (max[axis] - origin[axis]) / direction[axis];
+            entry = entry.max(near.min(far));
+            exit = exit.min(near.max(far));
+        }
+
+        if entry > exit || exit < 0.0 {
+            None
+        } else {
+            Some((entry, exit))
+        }
+    }
+
+    /// Returns the AABB stretched to cover everywhere it passes through while moving by
+    /// `displacement` (a velocity times a time step, for example).
+    pub fn expanded_by(&self, displacement: Vector3<f32>) -> AABB {
+        self.union(&AABB {
+            center_pos: self.center_pos + displacement,
+            half_size: self.half_size,
+        })
+    }
+
+    /// Returns the point in or on the AABB that's closest to `point`. Points inside are returned
+    /// as they are.
+    pub fn closest_point(&self, point: Vector3<f32>) -> Vector3<f32> {
+        let (min, max) = (self.min(), self.max());
+
+        Vector3::new(
+            point.x.max(min.x).min(max.x),
+            point.y.max(min.y).min(max.y),
+            point.z.max(min.z).min(max.z),
+        )
+    }
+
+    /// Iterates over the cells of a grid with cells `cell_size` wide (like the block grid, with
+    /// `Block::WIDTH`) that the AABB overlaps. Cells it only touches the edge of aren't included.
+    pub fn cells(&self, cell_size: f32) -> impl Iterator<Item = (i64, i64, i64)> {
+        let (min, max) = (self.min(), self.max());
+
+        // the last cell is the one holding the max corner, unless the corner is right on its edge
+        let first = |v: f32| (v / cell_size).floor() as i64;
+        let last = |v: f32| (v / cell_size).ceil() as i64 - 1;
+
+        let (i0, j0, k0) = (first(min.x), first(min.y), first(min.z));
+        let (i1, j1, k1) = (last(max.x), last(max.y), last(max.z));
+
+        (i0..=i1).flat_map(move |i| (j0..=j1).flat_map(move |j| (k0..=k1).map(move |k| (i, j, k))))
+    }
+
+    /// Sweeps the AABB along `displacement` and returns where it first touches `other`, which
+    /// stays put. Returns None if it never does during the move, or if the boxes overlap to begin
+    /// with (use `collides_with` for that). Boxes that only slide along each other's faces don't
+    /// hit.
+    pub fn sweep(&self, displacement: Vector3<f32>, other: &AABB) -> Option<SweepHit> {
+        // grow `other` by our size, then sweep our center through it like a ray
+        let half_size_sum = self.half_size + other.half_size;
+
+        let mut entry_time = f32::NEG_INFINITY;
+        let mut exit_time = f32::INFINITY;
+        let mut entry_axis = 0;
+
+        for axis in 0..3 {
+            let start = self.center_pos[axis];
+            let min = other.center_pos[axis] - half_size_sum[axis];
+            let max = other.center_pos[axis] + half_size_sum[axis];
+            let d = displacement[axis];
+
+            if d == 0.0 {
+                // not moving on this axis, so we have to already be between the faces
+                if start <= min || start >= max {
+                    return None;
+                }
+                continue;
+            }
+
+            let (near, far) = if d > 0.0 {
+                ((min - start) / d, (max - start) / d)
+            } else {
+                ((max - start) / d, (min - start) / d)
+            };
+
+            if near > entry_time {
+                entry_time = near;
+                entry_axis = axis;
+            }
+            exit_time = exit_time.min(far);
+        }
+
+        if entry_time > exit_time || !(0.0..=1.0).contains(&entry_time) {
+            return None;
+        }
+
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+        normal[entry_axis] = -displacement[entry_axis].signum();
+
+        Some(SweepHit {
+            time: entry_time,
+            normal,
+        })
+    }
+}
+
+/// Where a swept AABB first touches another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SweepHit {
+    /// How far along the displacement the hit happens, from 0 (right away) to 1 (at the very end).
+    pub time: f32,
+
+    /// Points out of the face that was hit, back toward the moving box.
+    pub normal: Vector3<f32>,
+}
+
+#[cfg(test)]
+impl AABB {
+    /// Returns a cube centered on the point, reaching `half` out from its center along each axis.
+    pub fn cube(x: f32, y: f32, z: f32, half: f32) -> Self {
+        Self {
+            center_pos: Vector3::new(x, y, z),
+            half_size: Vector3::new(half, half, half),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_and_max_corners() {
+        let b = AABB::from_min_max(Vector3::new(-1.0, 0.0, 2.0), Vector3::new(1.0, 4.0, 3.0));
+
+        assert_eq!(b.center_pos, Vector3::new(0.0, 2.0, 2.5));
+        assert_eq!(b.half_size, Vector3::new(1.0, 2.0, 0.5));
+        assert_eq!(b.min(), Vector3::new(-1.0, 0.0, 2.0));
+        assert_eq!(b.max(), Vector3::new(1.0, 4.0, 3.0));
+    }
+
+    #[test]
+    fn intersections() {
+        let a = AABB::cube(0.0, 0.0, 0.0, 1.0);
+        let b = AABB::cube(1.5, 0.5, 0.0, 1.0);
+
+        let overlap = a.intersection(&b).unwrap();
+        assert_eq!(overlap.min(), Vector3::new(0.5, -0.5, -1.0));
+        assert_eq!(overlap.max(), Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(a.intersection(&b), b.intersection(&a));
+
+        // touching faces isn't overlapping
+        assert!(a.intersection(&AABB::cube(2.0, 0.0, 0.0, 1.0)).is_none());
+        assert!(a.intersection(&AABB::cube(5.0, 0.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn unions() {
+        let a = AABB::cube(0.0, 0.0, 0.0, 1.0);
+        let b = AABB::cube(3.0, 0.0, 0.0, 0.5);
+
+        let both = a.union(&b);
+        assert_eq!(both.min(), Vector3::new(-1.0, -1.0, -1.0));
+        assert_eq!(both.max(), Vector3::new(3.5, 1.0, 1.0));
+        assert!(both.contains(&a) && both.contains(&b));
+
+        assert_eq!(a.union(&a), a);
+    }
+
+    #[test]
+    fn containment_includes_the_surface() {
+        let a = AABB::cube(0.0, 0.0, 0.0, 1.0);
+
+        assert!(a.contains_point(Vector3::new(0.5, -0.5, 0.0)));
+        assert!(a.contains_point(Vector3::new(1.0, 1.0, 1.0)));
+        assert!(!a.contains_point(Vector3::new(1.0001, 0.0, 0.0)));
+
+        assert!(a.contains(&a));
+        assert!(a.contains(&AABB::cube(0.5, 0.5, 0.5, 0.5)));
+        assert!(!a.contains(&AABB::cube(0.6, 0.0, 0.0, 0.5)));
+        assert!(!AABB::cube(0.0, 0.0, 0.0, 0.5).contains(&a));
+    }
+
+    #[test]
+    fn minimum_translation_pushes_out_along_the_shallowest_axis() {
+        let a = AABB::cube(0.0, 0.0, 0.0, 1.0);
+
+        // overlapping by 0.5 on x and 1.7 on y
+        let b = AABB::cube(1.5, 0.3, 0.0, 1.0);
+        assert_eq!(b.minimum_translation(&a), Some(Vector3::new(0.5, 0.0, 0.0)));
+        assert_eq!(
+            a.minimum_translation(&b),
+            Some(Vector3::new(-0.5, 0.0, 0.0))
+        );
+        assert!((a.penetration_depth(&b) - 0.5).abs() < 1e-6);
+
+        // moving by the MTV leaves them touching but not colliding
+        let mtv = b.minimum_translation(&a).unwrap();
+        let moved = AABB {
+            center_pos: b.center_pos + mtv,
+            half_size: b.half_size,
+        };
+        assert!(!moved.collides_with(&a));
+
+        // touching and apart have nothing to fix
+        assert_eq!(a.minimum_translation(&AABB::cube(2.0, 0.0, 0.0, 1.0)), None);
+        assert_eq!(a.penetration_depth(&AABB::cube(2.0, 0.0, 0.0, 1.0)), 0.0);
+        assert_eq!(a.minimum_translation(&AABB::cube(9.0, 0.0, 0.0, 1.0)), None);
+    }
+
+    #[test]
+    fn rays_enter_and_exit() {
+        let a = AABB::cube(5.0, 0.0, 0.0, 1.0);
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(a.ray_intersection(origin, x), Some((4.0, 6.0)));
+
+        // longer direction vectors give shorter distances
+        assert_eq!(a.ray_intersection(origin, x * 2.0), Some((2.0, 3.0)));
+
+        // starting inside enters behind the origin
+        assert_eq!(
+            a.ray_intersection(Vector3::new(5.0, 0.0, 0.0), x),
+            Some((-1.0, 1.0))
+        );
+
+        // pointing away, or passing beside it
+        assert_eq!(a.ray_intersection(origin, -x), None);
+        assert_eq!(a.ray_intersection(Vector3::new(0.0, 2.0, 0.0), x), None);
+
+        // diagonal
+        let (entry, exit) = a
+            .ray_intersection(Vector3::new(0.0, -5.0, 0.0), Vector3::new(1.0, 1.0, 0.0))
+            .unwrap();
+        assert_eq!((entry, exit), (4.0, 6.0));
+    }
+
+    #[test]
+    fn rays_grazing_an_edge_hit() {
+        let a = AABB::cube(5.0, 0.0, 0.0, 1.0);
+
+        // along the top face
+        assert_eq!(
+            a.ray_intersection(Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+            Some((4.0, 6.0))
+        );
+
+        // through a single edge
+        let (entry, exit) = a
+            .ray_intersection(Vector3::new(0.0, -3.0, 0.0), Vector3::new(1.0, 1.0, 0.0))
+            .unwrap();
+        assert_eq!(entry, exit);
+    }
+
+    #[test]
+    fn expanding_by_a_velocity() {
+        let a = AABB::cube(0.0, 0.0, 0.0, 0.5);
+
+        let swept = a.expanded_by(Vector3::new(2.0, -1.0, 0.0));
+        assert_eq!(swept.min(), Vector3::new(-0.5, -1.5, -0.5));
+        assert_eq!(swept.max(), Vector3::new(2.5, 0.5, 0.5));
+
+        assert_eq!(a.expanded_by(Vector3::new(0.0, 0.0, 0.0)), a);
+    }
+
+    #[test]
+    fn closest_points() {
+        let a = AABB::cube(0.0, 0.0, 0.0, 1.0);
+
+        assert_eq!(
+            a.closest_point(Vector3::new(5.0, 0.5, -3.0)),
+            Vector3::new(1.0, 0.5, -1.0)
+        );
+        assert_eq!(
+            a.closest_point(Vector3::new(0.2, 0.3, 0.4)),
+            Vector3::new(0.2, 0.3, 0.4)
+        );
+        assert_eq!(
+            a.closest_point(Vector3::new(1.0, 1.0, 1.0)),
+            Vector3::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn overlapped_cells() {
+        // a block-sized box sitting exactly in one cell only overlaps that cell
+        let block = AABB::from_min_max(Vector3::new(0.5, 0.0, -0.5), Vector3::new(1.0, 0.5, 0.0));
+        assert_eq!(block.cells(0.5).collect::<Vec<_>>(), vec![(1, 0, -1)]);
+
+        // a little bigger spills into the neighbors
+        let cells: Vec<_> = AABB::cube(0.5, 0.0, 0.0, 0.3).cells(0.5).collect();
+        assert_eq!(cells.len(), 2 * 2 * 2);
+        assert!(cells.contains(&(0, -1, -1)) && cells.contains(&(1, 0, 0)));
+
+        // a flat box on a cell edge overlaps nothing
+        let flat = AABB::from_min_max(Vector3::new(0.0, 0.5, 0.0), Vector3::new(1.0, 0.5, 1.0));
+        assert_eq!(flat.cells(0.5).count(), 0);
+    }
+
+    #[test]
+    fn sweep_finds_the_time_of_impact() {
+        let moving = AABB::cube(0.0, 0.0, 0.0, 0.5);
+        let wall = AABB::cube(5.0, 0.0, 0.0, 0.5);
+
+        let hit = moving
+            .sweep(Vector3::new(8.0, 0.0, 0.0), &wall)
+            .expect("should hit the wall");
+        assert!((hit.time - 0.5).abs() < 1e-6);
+        assert_eq!(hit.normal, Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn sweep_hits_thin_walls_at_any_speed() {
+        let moving = AABB::cube(0.0, 0.0, 0.0, 0.25);
+        let wall = AABB::cube(10.0, 0.0, 0.0, 0.01);
+
+        for &speed in [11.0, 1e3, 1e6, 1e9].iter() {
+            let hit = moving.sweep(Vector3::new(speed, 0.0, 0.0), &wall);
+            assert!(hit.is_some(), "went through at {}", speed);
+        }
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let moving = AABB::cube(0.0, 0.0, 0.0, 0.5);
+        let wall = AABB::cube(5.0, 0.0, 0.0, 0.5);
+
+        // too short, moving away, and passing beside it
+        assert!(moving.sweep(Vector3::new(3.0, 0.0, 0.0), &wall).is_none());
+        assert!(moving.sweep(Vector3::new(-8.0, 0.0, 0.0), &wall).is_none());
+        assert!(moving
+            .sweep(Vector3::new(8.0, 0.0, 0.0), &AABB::cube(5.0, 2.0, 0.0, 0.5))
+            .is_none());
+    }
+
+    #[test]
+    fn sliding_along_a_face_is_not_a_hit() {
+        let moving = AABB::cube(0.0, 1.0, 0.0, 0.5);
+        let floor = AABB::cube(0.0, 0.0, 0.0, 0.5);
+
+        assert!(moving.sweep(Vector3::new(3.0, 0.0, 0.0), &floor).is_none());
+
+        // but moving into it is
+        let hit = moving.sweep(Vector3::new(1.0, -1.0, 0.0), &floor).unwrap();
+        assert_eq!(hit.time, 0.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn overlapping_boxes_are_not_swept() {
+        let moving = AABB::cube(0.0, 0.0, 0.0, 0.5);
+        assert!(moving
+            .sweep(Vector3::new(1.0, 0.0, 0.0), &AABB::cube(0.5, 0.0, 0.0, 0.5))
+            .is_none());
+    }
 }
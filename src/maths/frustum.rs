@@ -0,0 +1,162 @@
+#![allow(dead_code)]
+
+use super::AABB;
+use cgmath::{Matrix4, Vector3, Vector4};
+
+/// A plane in the form `normal · p + distance = 0`. Points for which the left-hand side is
+/// positive are on the "inside" of the plane.
+#[derive(Debug, Copy, Clone)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Makes a plane out of the coefficients `(a, b, c, d)` of `ax + by + cz + d = 0`, normalizing
+    /// them so that `signed_distance` returns real distances.
+    fn from_coefficients(v: Vector4<f32>) -> Self {
+        let normal = Vector3::new(v.x, v.y, v.z);
+        let length = (normal.x * normal.x + normal.y * normal.y + normal.z * normal.z).sqrt();
+
+        if length == 0.0 {
+            // degenerate plane; everything is considered inside of it
+            return Self {
+                normal,
+                distance: 0.0,
+            };
+        }
+
+        Self {
+            normal: normal / length,
+            distance: v.w / length,
+        }
+    }
+
+    /// Returns the signed distance from the plane to the point. Positive means the point is inside.
+    pub fn signed_distance(&self, point: Vector3<f32>) -> f32 {
+        self.normal.x * point.x + self.normal.y * point.y + self.normal.z * point.z + self.distance
+    }
+}
+
+/// The six planes bounding everything a camera can see. Used to skip drawing things that are off
+/// screen.
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, and far, in that order. All normals point inward.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from a view-projection matrix (the Gribb/Hartmann method).
+    ///
+    /// cgmath's projection matrices map depth to -1..1 like OpenGL does, so the near plane is
+    /// `row 3 + row 2`. Culling against that plane is never stricter than culling against the 0..1
+    /// depth range wgpu clips to, so nothing visible gets culled.
+    pub fn from_matrix(m: &Matrix4<f32>) -> Self {
+        // cgmath matrices are column-major; m[column][row]
+        let row = |r: usize| Vector4::new(m[0][r], m[1][r], m[2][r], m[3][r]);
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        Self {
+            planes: [
+                Plane::from_coefficients(r3 + r0),
+                Plane::from_coefficients(r3 - r0),
+                Plane::from_coefficients(r3 + r1),
+                Plane::from_coefficients(r3 - r1),
+                Plane::from_coefficients(r3 + r2),
+                Plane::from_coefficients(r3 - r2),
+            ],
+        }
+    }
+
+    /// Returns true if any part of the AABB might be inside of the frustum. This is conservative:
+    /// boxes near the corners of the frustum can be reported as visible even if they aren't, but a
+    /// visible box is never reported as hidden.
+    pub fn intersects_aabb(&self, aabb: &AABB) -> bool {
+        self.planes.iter().all(|plane| {
+            // the box's "radius" projected onto the plane normal
+            let radius = plane.normal.x.abs() * aabb.half_size.x
+                + plane.normal.y.abs() * aabb.half_size.y
+                + plane.normal.z.abs() * aabb.half_size.z;
+
+            plane.signed_distance(aabb.center_pos) >= -radius
+        })
+    }
+
+    /// Returns true if the point is inside of the frustum.
+    pub fn contains_point(&self, point: Vector3<f32>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Point3};
+
+    /// A camera at the origin looking down -z, just like `Camera` would build it.
+    fn looking_down_negative_z() -> Frustum {
+        let view = Matrix4::look_at(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vector3::unit_y(),
+        );
+        let proj = cgmath::perspective(Deg(90.0), 1.0, 0.1, 100.0);
+
+        Frustum::from_matrix(&(proj * view))
+    }
+
+    #[test]
+    fn planes_are_normalized() {
+        for plane in looking_down_negative_z().planes.iter() {
+            let n = plane.normal;
+            let length = (n.x * n.x + n.y * n.y + n.z * n.z).sqrt();
+            assert!((length - 1.0).abs() < 1e-5, "length was {}", length);
+        }
+    }
+
+    #[test]
+    fn points() {
+        let frustum = looking_down_negative_z();
+
+        assert!(frustum.contains_point(Vector3::new(0.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vector3::new(0.0, 0.0, 10.0)));
+
+        // beyond the far plane
+        assert!(!frustum.contains_point(Vector3::new(0.0, 0.0, -150.0)));
+
+        // with a 90 degree fov, x = -z is right on the edge
+        assert!(frustum.contains_point(Vector3::new(9.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vector3::new(11.0, 0.0, -10.0)));
+    }
+
+    #[test]
+    fn box_in_front_is_visible() {
+        assert!(looking_down_negative_z().intersects_aabb(&AABB::cube(0.0, 0.0, -20.0, 1.0)));
+    }
+
+    #[test]
+    fn box_behind_is_culled() {
+        assert!(!looking_down_negative_z().intersects_aabb(&AABB::cube(0.0, 0.0, 20.0, 1.0)));
+    }
+
+    #[test]
+    fn box_to_the_side_is_culled() {
+        let frustum = looking_down_negative_z();
+        assert!(!frustum.intersects_aabb(&AABB::cube(30.0, 0.0, -10.0, 1.0)));
+        assert!(!frustum.intersects_aabb(&AABB::cube(0.0, -30.0, -10.0, 1.0)));
+    }
+
+    #[test]
+    fn box_straddling_an_edge_is_visible() {
+        // center is outside of the right plane, but the box pokes into the frustum
+        assert!(looking_down_negative_z().intersects_aabb(&AABB::cube(12.0, 0.0, -10.0, 3.0)));
+    }
+
+    #[test]
+    fn box_containing_the_camera_is_visible() {
+        assert!(looking_down_negative_z().intersects_aabb(&AABB::cube(0.0, 0.0, 0.0, 8.0)));
+    }
+}
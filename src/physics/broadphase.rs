@@ -0,0 +1,300 @@
+use crate::maths::AABB;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// How wide each cell of the grid is by default, in meters. About the size of a few chickens, so
+/// most bodies only ever sit in one to eight cells.
+pub const DEFAULT_CELL_SIZE: f32 = 2.0;
+
+type Cell = (i32, i32, i32);
+
+/// Broadphase is a uniform grid (a spatial hash) over AABBs. Checking every body against every
+/// other body takes O(n²) time; the grid only pairs up bodies that share a cell, so it stays fast
+/// with thousands of bodies as long as they aren't all piled into the same spot.
+///
+/// Keys are whatever identifies a box, like a BodyHandle. Boxes are moved with `update`, which
+/// only touches the grid when a box crosses into different cells.
+pub struct Broadphase<K> {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<K>>,
+    entries: HashMap<K, Entry>,
+}
+
+struct Entry {
+    aabb: AABB,
+
+    /// The first and last cells the box covers, inclusive.
+    cells: (Cell, Cell),
+}
+
+impl<K: Copy + Eq + Ord + Hash> Broadphase<K> {
+    pub fn new() -> Self {
+        Self::with_cell_size(DEFAULT_CELL_SIZE)
+    }
+
+    pub fn with_cell_size(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a box, or moves it if the key is already in the grid.
+    pub fn insert(&mut self, key: K, aabb: AABB) {
+        if self.entries.contains_key(&key) {
+            self.update(key, aabb);
+            return;
+        }
+
+        let cells = self.cells_covering(&aabb);
+        self.add_to_cells(key, cells);
+        self.entries.insert(key, Entry { aabb, cells });
+    }
+
+    /// Moves a box that's already in the grid. Does nothing if it isn't.
+    pub fn update(&mut self, key: K, aabb: AABB) {
+        let cells = self.cells_covering(&aabb);
+
+        let old_cells = match self.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.aabb = aabb;
+                if entry.cells == cells {
+                    // still in the same cells; nothing else to do
+                    return;
+                }
+                std::mem::replace(&mut entry.cells, cells)
+            }
+            None => return,
+        };
+
+        self.remove_from_cells(key, old_cells);
+        self.add_to_cells(key, cells);
+    }
+
+    pub fn remove(&mut self, key: K) {
+        if let Some(entry) = self.entries.remove(&key) {
+            self.remove_from_cells(key, entry.cells);
+        }
+    }
+
+    /// Returns every pair of boxes that overlap, each pair once with the lower key first, sorted.
+    pub fn pairs(&self) -> Vec<(K, K)> {
+        let mut pairs = Vec::new();
+
+        for keys in self.cells.values() {
+            for (n, &a) in keys.iter().enumerate() {
+                for &b in keys[n + 1..].iter() {
+                    if self.entries[&a].aabb.collides_with(&self.entries[&b].aabb) {
+                        pairs.push((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+
+        // boxes sharing more than one cell were paired more than once
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    /// Returns the keys of every box that overlaps `aabb`, sorted.
+    pub fn query(&self, aabb: &AABB) -> Vec<K> {
+        let (first, last) = self.cells_covering(aabb);
+        let mut found = Vec::new();
+
+        for i in first.0..=last.0 {
+            for j in first.1..=last.1 {
+                for k in first.2..=last.2 {
+                    if let Some(keys) = self.cells.get(&(i, j, k)) {
+                        found.extend(
+                            keys.iter()
+                                .filter(|key| self.entries[key].aabb.collides_with(aabb)),
+                        );
+                    }
+                }
+            }
+        }
+
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    fn cells_covering(&self, aabb: &AABB) -> (Cell, Cell) {
+        let min = aabb.center_pos - aabb.half_size;
+        let max = aabb.center_pos + aabb.half_size;
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+
+        (
+            (cell(min.x), cell(min.y), cell(min.z)),
+            (cell(max.x), cell(max.y), cell(max.z)),
+        )
+    }
+
+    fn add_to_cells(&mut self, key: K, (first, last): (Cell, Cell)) {
+        for i in first.0..=last.0 {
+            for j in first.1..=last.1 {
+                for k in first.2..=last.2 {
+                    self.cells.entry((i, j, k)).or_default().push(key);
+                }
+            }
+        }
+    }
+
+    fn remove_from_cells(&mut self, key: K, (first, last): (Cell, Cell)) {
+        for i in first.0..=last.0 {
+            for j in first.1..=last.1 {
+                for k in first.2..=last.2 {
+                    if let Some(keys) = self.cells.get_mut(&(i, j, k)) {
+                        keys.retain(|&other| other != key);
+                        if keys.is_empty() {
+                            self.cells.remove(&(i, j, k));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<K: Copy + Eq + Ord + Hash> Default for Broadphase<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn brute_force_pairs(boxes: &[AABB]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (a, first) in boxes.iter().enumerate() {
+            for (b, second) in boxes.iter().enumerate().skip(a + 1) {
+                if first.collides_with(second) {
+                    pairs.push((a, b));
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn finds_the_same_pairs_as_checking_everything() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let boxes: Vec<AABB> = (0..300)
+            .map(|_| {
+                AABB::cube(
+                    rng.gen::<f32>() * 30.0,
+                    rng.gen::<f32>() * 30.0,
+                    rng.gen::<f32>() * 30.0,
+                    0.2 + rng.gen::<f32>() * 1.5,
+                )
+            })
+            .collect();
+
+        let mut grid = Broadphase::new();
+        for (i, &b) in boxes.iter().enumerate() {
+            grid.insert(i, b);
+        }
+
+        assert_eq!(grid.pairs(), brute_force_pairs(&boxes));
+    }
+
+    #[test]
+    fn moving_boxes_updates_pairs() {
+        let mut grid = Broadphase::new();
+        grid.insert(1, AABB::cube(0.0, 0.0, 0.0, 0.5));
+        grid.insert(2, AABB::cube(10.0, 0.0, 0.0, 0.5));
+        assert!(grid.pairs().is_empty());
+
+        grid.update(2, AABB::cube(0.5, 0.0, 0.0, 0.5));
+        assert_eq!(grid.pairs(), vec![(1, 2)]);
+
+        // moving within the same cells still updates the box
+        grid.update(2, AABB::cube(0.9, 0.0, 0.0, 0.01));
+        assert!(grid.pairs().is_empty());
+
+        grid.remove(1);
+        assert!(grid.pairs().is_empty());
+        assert_eq!(grid.len(), 1);
+    }
+
+    #[test]
+    fn removed_boxes_leave_no_empty_cells() {
+        let mut grid = Broadphase::new();
+        grid.insert(1, AABB::cube(0.0, 0.0, 0.0, 5.0));
+        grid.update(1, AABB::cube(20.0, 0.0, 0.0, 5.0));
+        grid.remove(1);
+
+        assert!(grid.cells.is_empty());
+    }
+
+    #[test]
+    fn queries_boxes_in_a_region() {
+        let mut grid = Broadphase::new();
+        grid.insert(1, AABB::cube(0.0, 0.0, 0.0, 0.5));
+        grid.insert(2, AABB::cube(3.0, 0.0, 0.0, 0.5));
+        grid.insert(3, AABB::cube(-40.0, 0.0, 0.0, 0.5));
+
+        assert_eq!(grid.query(&AABB::cube(1.5, 0.0, 0.0, 1.2)), vec![1, 2]);
+        assert!(grid.query(&AABB::cube(20.0, 0.0, 0.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn work_grows_with_bodies_not_pairs_of_bodies() {
+        for &count in [1_000, 4_000, 16_000].iter() {
+            let mut rng = StdRng::seed_from_u64(count as u64);
+
+            // spread out about as densely as a busy chicken farm: one body per 8 cubic meters
+            let side = (count as f32 * 8.0).cbrt();
+            let mut boxes: Vec<AABB> = (0..count)
+                .map(|_| {
+                    AABB::cube(
+                        rng.gen::<f32>() * side,
+                        rng.gen::<f32>() * side,
+                        rng.gen::<f32>() * side,
+                        0.3,
+                    )
+                })
+                .collect();
+
+            let mut grid = Broadphase::new();
+            for (i, &b) in boxes.iter().enumerate() {
+                grid.insert(i, b);
+            }
+
+            // everyone shuffles around a bit, moving some of them into new cells
+            for (i, b) in boxes.iter_mut().enumerate() {
+                b.center_pos.x += (rng.gen::<f32>() - 0.5) * 0.2;
+                b.center_pos.z += (rng.gen::<f32>() - 0.5) * 0.2;
+                grid.update(i, *b);
+            }
+
+            // `pairs` only checks boxes that share a cell against each other, which stays at a few
+            // checks per body however many there are. checking everything would be
+            // count * (count - 1) / 2 checks, which is 8 million at 4,000 bodies
+            let checks: usize = grid
+                .cells
+                .values()
+                .map(|keys| keys.len() * (keys.len() - 1) / 2)
+                .sum();
+            assert!(checks < count * 4, "{} checks for {} bodies", checks, count);
+
+            // no box this small covers more than 8 cells
+            assert!(grid.cells.len() <= count * 8);
+            assert!(grid.pairs().len() <= checks);
+        }
+    }
+}
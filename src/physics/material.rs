@@ -0,0 +1,53 @@
+/// How much a body slows itself down just by moving, as a fraction of its speed lost per second.
+/// Without any, bodies would slide forever once they got going.
+pub const DEFAULT_DRAG: f32 = 0.1;
+
+/// How much the air slows a body down, growing with the square of its speed (per meter). About
+/// right for a chicken, which falls no faster than about 30 m/s.
+pub const DEFAULT_AIR_RESISTANCE: f32 = 0.01;
+
+/// Surface is how a block feels to walk on. Each type of block has its own in its
+/// `BlockDefinition`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Surface {
+    /// How strongly the surface grips bodies sliding along it, combined with the body's own
+    /// friction. Stone is about 1; ice is nearly 0.
+    pub friction: f32,
+
+    /// Scales how hard bodies can push themselves along the surface. 1 is normal ground, less
+    /// than 1 is slow going like sand or mud.
+    pub speed_factor: f32,
+}
+
+impl Surface {
+    /// Plain, solid ground.
+    pub const DEFAULT: Surface = Surface {
+        friction: 1.0,
+        speed_factor: 1.0,
+    };
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Fluid is what fluid blocks, like water, are made of. Bodies in fluid float or sink depending
+/// on how dense they are, and are slowed down a lot more than they are in air.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fluid {
+    /// In kilograms per cubic meter. Bodies less dense than this float.
+    pub density: f32,
+
+    /// Linear drag on bodies completely under the fluid, like `PhysicalObject::drag`. Bodies only
+    /// partly under get a share of it.
+    pub drag: f32,
+}
+
+impl Fluid {
+    pub const WATER: Fluid = Fluid {
+        density: 1000.0,
+        drag: 3.0,
+    };
+}
@@ -0,0 +1,1079 @@
+mod broadphase;
+mod material;
+mod query;
+mod replay;
+mod terrain;
+#[cfg(test)]
+pub(crate) mod testing;
+mod world;
+
+pub use broadphase::*;
+pub use material::*;
+pub use query::*;
+pub use replay::*;
+pub use terrain::*;
+pub use world::*;
+
+use crate::maths;
+use cgmath::{InnerSpace, Vector3};
+use std::cmp::Ordering;
+
+/// The gravity acceleration constant (m/s/s)
+const GRAVITY: f32 = -9.81;
+
+/// Impacts slower than this (in m/s) never bounce, so that resting bodies settle instead of
+/// jittering on the ground.
+const BOUNCE_THRESHOLD: f32 = 1.0;
+
+/// Bodies with at least this much of themselves in fluid count as underwater. A little less than
+/// 1 so that bobbing at the surface doesn't flicker in and out of it.
+const UNDERWATER_FRACTION: f32 = 0.95;
+
+/// PhysicalObject is an object with physics, position,
+/// velocity, and mass
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalObject {
+    /// if true, the PhysicalObject will not move
+    frozen: bool,
+
+    velocity: Vector3<f32>,
+    acceleration: Vector3<f32>,
+
+    /// In kilograms
+    mass: f32,
+
+    /// How much speed is kept when bouncing off of something, from 0 (no bounce) to 1 (perfectly
+    /// elastic).
+    restitution: f32,
+
+    /// How strongly the PhysicalObject grips things it slides along. 0 is ice.
+    friction: f32,
+
+    /// Linear drag: the fraction of its speed the PhysicalObject loses every second.
+    drag: f32,
+
+    /// Quadratic drag from the air, which matters more the faster the PhysicalObject goes.
+    air_resistance: f32,
+
+    /// The Surface of the blocks under the PhysicalObject, if it was on the ground after its last
+    /// step.
+    ground: Option<Surface>,
+
+    /// How much of the PhysicalObject was in fluid after its last step.
+    submersion: Option<Submersion>,
+
+    /// The collision layers the PhysicalObject is on, for scene queries.
+    layers: Layers,
+
+    on_ground: bool,
+    pushing_wall: bool,
+    at_ceiling: bool,
+
+    was_on_ground: bool,
+    was_pushing_wall: bool,
+    was_at_ceiling: bool,
+
+    hitbox: maths::AABB, // hitbox for collision calculation, but not kill calculation
+}
+
+impl PhysicalObject {
+    pub fn new(mass: f32, physics_box: maths::AABB) -> Self {
+        Self {
+            frozen: false,
+
+            velocity: Vector3::from((0.0, 0.0, 0.0)),
+            acceleration: Vector3::from((0.0, 0.0, 0.0)),
+
+            mass,
+
+            restitution: 0.0,
+            friction: 0.5,
+
+            drag: DEFAULT_DRAG,
+            air_resistance: DEFAULT_AIR_RESISTANCE,
+            ground: None,
+            submersion: None,
+            layers: Layers::DEFAULT,
+
+            on_ground: false,
+            pushing_wall: false,
+            at_ceiling: false,
+
+            was_on_ground: false,
+            was_pushing_wall: false,
+            was_at_ceiling: false,
+
+            hitbox: physics_box,
+        }
+    }
+
+    /// Returns the PhysicalObject's position
+    pub fn position(&self) -> Vector3<f32> {
+        self.hitbox.center_pos
+    }
+
+    /// In meters per second.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    pub fn hitbox(&self) -> &maths::AABB {
+        &self.hitbox
+    }
+
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    pub fn restitution(&self) -> f32 {
+        self.restitution
+    }
+
+    /// Sets how bouncy the PhysicalObject is, from 0 to 1.
+    pub fn set_restitution(&mut self, restitution: f32) {
+        self.restitution = restitution.clamp(0.0, 1.0);
+    }
+
+    pub fn friction(&self) -> f32 {
+        self.friction
+    }
+
+    pub fn set_friction(&mut self, friction: f32) {
+        self.friction = friction.max(0.0);
+    }
+
+    pub fn drag(&self) -> f32 {
+        self.drag
+    }
+
+    pub fn set_drag(&mut self, drag: f32) {
+        self.drag = drag.max(0.0);
+    }
+
+    pub fn air_resistance(&self) -> f32 {
+        self.air_resistance
+    }
+
+    pub fn set_air_resistance(&mut self, air_resistance: f32) {
+        self.air_resistance = air_resistance.max(0.0);
+    }
+
+    /// Returns the Surface the PhysicalObject is standing on, or None if it isn't on the ground.
+    pub fn ground_surface(&self) -> Option<Surface> {
+        self.ground
+    }
+
+    pub fn layers(&self) -> Layers {
+        self.layers
+    }
+
+    /// Puts the PhysicalObject on `layers`, so only scene queries looking for those find it.
+    pub fn set_layers(&mut self, layers: Layers) {
+        self.layers = layers;
+    }
+
+    /// Returns how much of the PhysicalObject is in fluid, from 0 (dry) to 1 (completely under).
+    pub fn submerged_fraction(&self) -> f32 {
+        self.submersion.map(|s| s.fraction).unwrap_or(0.0)
+    }
+
+    pub fn swim_state(&self) -> SwimState {
+        match self.submersion {
+            None => SwimState::Dry,
+            Some(s) if s.fraction >= UNDERWATER_FRACTION => SwimState::Underwater,
+            Some(_) if self.on_ground => SwimState::Wading,
+            Some(_) => SwimState::Swimming,
+        }
+    }
+
+    /// Returns one over the mass, or zero for PhysicalObjects that can't be pushed around: frozen
+    /// ones, and ones without a sensible (positive) mass.
+    fn inverse_mass(&self) -> f32 {
+        if self.frozen || self.mass.is_nan() || self.mass <= 0.0 {
+            0.0
+        } else {
+            1.0 / self.mass
+        }
+    }
+
+    /// Returns the speed along an axis after running into something solid at `speed`. Slow
+    /// impacts just stop.
+    fn bounce(&self, speed: f32) -> f32 {
+        if speed.abs() > BOUNCE_THRESHOLD {
+            -speed * self.restitution
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Frozen PhysicalObjects don't move, and other PhysicalObjects can't move through them.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    fn add_position(&mut self, v2: Vector3<f32>) {
+        self.hitbox.center_pos += v2;
+    }
+
+    /// Modifies the position of the PhysicalObject.
+    pub fn set_position(&mut self, pos: Vector3<f32>) {
+        self.hitbox.center_pos = pos;
+    }
+
+    /// Calculates physics on the PhysicalObject, moving it through the terrain without passing
+    /// through solid blocks.
+    pub fn physics<T: Terrain + ?Sized>(&mut self, delta: f32, terrain: &T) {
+        self.physics_among(delta, terrain, &[]);
+    }
+
+    /// Like `physics`, but also stops at `obstacles`, like the hitboxes of frozen objects. Motion
+    /// is swept rather than jumped, so nothing is skipped over no matter how fast the
+    /// PhysicalObject is going. Returns everything it ran into.
+    fn physics_among<T: Terrain + ?Sized>(
+        &mut self,
+        delta: f32,
+        terrain: &T,
+        obstacles: &[maths::AABB],
+    ) -> StepContacts {
+        let mut contacts = StepContacts::default();
+
+        // no physics if p is frozen
+        if self.frozen {
+            return contacts;
+        }
+
+        self.begin_contacts();
+
+        self.velocity += self.driving_acceleration() * delta;
+        self.slow_down(delta);
+        self.move_among(self.velocity * delta, terrain, obstacles, &mut contacts);
+
+        self.ground = if self.on_ground {
+            Some(surface_under(&self.hitbox, terrain))
+        } else {
+            None
+        };
+        self.submersion = submersion(&self.hitbox, terrain);
+
+        // reset acceleration
+        self.acceleration.x = 0.0;
+        self.acceleration.y = 0.0;
+        self.acceleration.z = 0.0;
+
+        contacts
+    }
+
+    /// Returns the acceleration for this step: applied forces, weakened on slow ground like sand,
+    /// plus gravity and buoyancy. Gravity applies even on the ground; the ground pushing back is
+    /// what keeps `on_ground` set from one step to the next.
+    fn driving_acceleration(&self) -> Vector3<f32> {
+        let speed_factor = self.ground.map(|s| s.speed_factor).unwrap_or(1.0);
+
+        Vector3::new(
+            self.acceleration.x * speed_factor,
+            self.acceleration.y + GRAVITY + self.buoyancy(),
+            self.acceleration.z * speed_factor,
+        )
+    }
+
+    /// Returns the upward acceleration from the fluid the PhysicalObject is in: the weight of the
+    /// fluid it pushes out of the way (Archimedes), divided by its mass.
+    fn buoyancy(&self) -> f32 {
+        let s = match self.submersion {
+            Some(s) => s,
+            None => return 0.0,
+        };
+
+        let half = self.hitbox.half_size;
+        let displaced = s.fraction * 8.0 * half.x * half.y * half.z;
+        s.fluid.density * displaced * -GRAVITY * self.inverse_mass()
+    }
+
+    /// Applies drag, air resistance, and friction with the ground over `delta` seconds. None of
+    /// them can ever reverse the PhysicalObject's motion, only stop it.
+    fn slow_down(&mut self, delta: f32) {
+        // solved implicitly so that big steps or big speeds can't overshoot past zero
+        let fluid_drag = self
+            .submersion
+            .map(|s| s.fluid.drag * s.fraction)
+            .unwrap_or(0.0);
+        let resistance = self.drag + fluid_drag + self.air_resistance * self.velocity.magnitude();
+        if resistance.is_finite() {
+            self.velocity /= 1.0 + resistance * delta;
+        }
+
+        let surface = match self.ground {
+            Some(s) => s,
+            None => return,
+        };
+
+        // sliding friction takes off a fixed amount of speed per second, set by how hard gravity
+        // presses the PhysicalObject into the ground
+        let sliding = Vector3::new(self.velocity.x, 0.0, self.velocity.z);
+        let sliding_speed = sliding.magnitude();
+        if sliding_speed < 1e-6 || !sliding_speed.is_finite() {
+            return;
+        }
+
+        let friction = (self.friction * surface.friction).sqrt();
+        let slowed = (sliding_speed - friction * -GRAVITY * delta).max(0.0);
+        let kept = slowed / sliding_speed;
+        self.velocity.x *= kept;
+        self.velocity.z *= kept;
+    }
+
+    /// Returns a box around everywhere the PhysicalObject could go during its next step of
+    /// `delta` seconds, for finding what it might run into.
+    fn swept_bounds(&self, delta: f32) -> maths::AABB {
+        let displacement = (self.velocity + self.driving_acceleration() * delta) * delta;
+
+        let mut bounds = self.hitbox.expanded_by(displacement);
+        bounds.half_size += Vector3::new(SKIN, SKIN, SKIN);
+        bounds
+    }
+
+    /// Copies the contact flags into the `was_*` flags and clears them for a new step.
+    fn begin_contacts(&mut self) {
+        self.was_on_ground = self.on_ground;
+        self.was_pushing_wall = self.pushing_wall;
+        self.was_at_ceiling = self.at_ceiling;
+
+        self.on_ground = false;
+        self.pushing_wall = false;
+        self.at_ceiling = false;
+    }
+
+    /// Sets the contact flag for running into something along `axis`, returning which kind of
+    /// contact it was.
+    fn touch(&mut self, axis: usize, moving_positive: bool) -> SurfaceContact {
+        match axis {
+            1 if moving_positive => {
+                self.at_ceiling = true;
+                SurfaceContact::Ceiling
+            }
+            1 => {
+                self.on_ground = true;
+                SurfaceContact::Ground
+            }
+            _ => {
+                self.pushing_wall = true;
+                SurfaceContact::Wall
+            }
+        }
+    }
+
+    /// Moves the PhysicalObject by `displacement`, resolving the earliest impact with an obstacle
+    /// first: it moves up to the impact, bounces (or stops) along the obstacle's normal, and slides
+    /// the rest of the way along it. Terrain is checked along every part of the move.
+    fn move_among<T: Terrain + ?Sized>(
+        &mut self,
+        displacement: Vector3<f32>,
+        terrain: &T,
+        obstacles: &[maths::AABB],
+        contacts: &mut StepContacts,
+    ) {
+        let mut remaining = displacement;
+
+        // each impact removes motion along one axis, so three is enough to stop completely
+        for _ in 0..3 {
+            let earliest = obstacles
+                .iter()
+                .enumerate()
+                .filter_map(|(i, o)| self.hitbox.sweep(remaining, o).map(|hit| (i, hit)))
+                .min_by(|(_, a), (_, b)| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+
+            let (obstacle, hit) = match earliest {
+                Some(h) => h,
+                None => break,
+            };
+
+            // stop just short of the obstacle so we don't end up touching (or inside) it
+            let travel = remaining * hit.time + hit.normal * SKIN;
+            self.move_through_terrain(travel, terrain, contacts);
+
+            let axis = if hit.normal.x != 0.0 {
+                0
+            } else if hit.normal.y != 0.0 {
+                1
+            } else {
+                2
+            };
+
+            self.touch(axis, hit.normal[axis] < 0.0);
+            self.velocity[axis] = self.bounce(self.velocity[axis]);
+            contacts.obstacles.push(obstacle);
+
+            remaining *= 1.0 - hit.time;
+            remaining[axis] = 0.0;
+        }
+
+        self.move_through_terrain(remaining, terrain, contacts);
+    }
+
+    /// Moves the PhysicalObject by `displacement` one axis at a time (y, then x, then z), stopping
+    /// at solid blocks. Velocity into a block bounces or stops and the contact flags are set.
+    fn move_through_terrain<T: Terrain + ?Sized>(
+        &mut self,
+        displacement: Vector3<f32>,
+        terrain: &T,
+        contacts: &mut StepContacts,
+    ) {
+        // y first, so that bodies land on the ground before sliding along it
+        for &axis in [1, 0, 2].iter() {
+            let wanted = displacement[axis];
+            let moved = sweep_axis(&self.hitbox, axis, wanted, terrain);
+            self.hitbox.center_pos[axis] += moved;
+
+            // sweep_axis only comes up short when something is in the way
+            if moved == wanted {
+                continue;
+            }
+
+            self.velocity[axis] = self.bounce(self.velocity[axis]);
+            let contact = self.touch(axis, wanted > 0.0);
+            if !contacts.terrain.contains(&contact) {
+                contacts.terrain.push(contact);
+            }
+        }
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    pub fn pushing_wall(&self) -> bool {
+        self.pushing_wall
+    }
+
+    pub fn at_ceiling(&self) -> bool {
+        self.at_ceiling
+    }
+
+    /// Returns true if the PhysicalObject just landed this step.
+    pub fn just_landed(&self) -> bool {
+        self.on_ground && !self.was_on_ground
+    }
+
+    /// Applies a force, in newtons, to the PhysicalObject. This is the only way to move a
+    /// PhysicalObject in the game; velocity and acceleration are not publicly accessible.
+    pub fn apply_force(&mut self, newtons: Vector3<f32>) {
+        self.acceleration.x += newtons.x / self.mass;
+        self.acceleration.y += newtons.y / self.mass;
+        self.acceleration.z += newtons.z / self.mass;
+    }
+
+    /// Applies an impulse, in newton-seconds, changing the PhysicalObject's velocity right away
+    /// instead of over the next step like a force does. Good for sudden pushes, like jumping.
+    pub fn apply_impulse(&mut self, newton_seconds: Vector3<f32>) {
+        self.velocity += newton_seconds * self.inverse_mass();
+    }
+
+    /// Immediately stops the motion of the PhysicalObject. Velocity and acceleration are set to
+    /// zero.
+    fn stop_motion(&mut self) {
+        self.velocity.x = 0.0;
+        self.velocity.y = 0.0;
+        self.velocity.z = 0.0;
+        self.acceleration.x = 0.0;
+        self.acceleration.y = 0.0;
+        self.acceleration.z = 0.0;
+    }
+
+    /// Returns whether or not the PhysicalObjects collide.
+    fn collides_with(&self, other: &PhysicalObject) -> bool {
+        self.hitbox.collides_with(&other.hitbox)
+    }
+
+    /// FixCollision fixes a collision between two PhysicalObjects: they're pushed apart along the
+    /// axis they overlap least on (heavier ones move less, frozen ones don't move at all), then
+    /// their momentum is resolved with `apply_momentum`. Returns true if they were colliding.
+    fn fix_collision(&mut self, other: &mut PhysicalObject) -> bool {
+        // how far `other` has to move to get out of us, along a single axis
+        let mtv = match other.hitbox.minimum_translation(&self.hitbox) {
+            Some(v) => v,
+            None => return false,
+        };
+        let axis = (0..3).find(|&a| mtv[a] != 0.0).unwrap_or(0);
+        let depth = mtv[axis];
+
+        let (inverse_self, inverse_other) = (self.inverse_mass(), other.inverse_mass());
+        let inverse_sum = inverse_self + inverse_other;
+        if inverse_sum == 0.0 {
+            // neither can move
+            return true;
+        }
+
+        self.hitbox.center_pos[axis] -= depth * inverse_self / inverse_sum;
+        other.hitbox.center_pos[axis] += depth * inverse_other / inverse_sum;
+
+        // points from self to other
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+        normal[axis] = depth.signum();
+        apply_momentum(self, other, normal);
+
+        true
+    }
+}
+
+/// How a PhysicalObject is doing in fluid, for deciding whether it should walk, paddle, or dive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SwimState {
+    /// Not in any fluid.
+    Dry,
+
+    /// Partly in fluid, but standing on something.
+    Wading,
+
+    /// Partly in fluid and not standing on anything, like a body floating at the surface.
+    Swimming,
+
+    /// Completely under.
+    Underwater,
+}
+
+/// What a PhysicalObject ran into during one step.
+#[derive(Debug, Default)]
+struct StepContacts {
+    /// Indices into the obstacles it was given.
+    obstacles: Vec<usize>,
+
+    /// Each kind of contact it made with solid blocks, at most once.
+    terrain: Vec<SurfaceContact>,
+}
+
+/// Resolves a collision between two PhysicalObjects with impulses. `normal` is the unit direction
+/// from `p1` toward `p2` at the contact.
+///
+/// Along the normal, the bodies get equal and opposite impulses that stop them closing in on each
+/// other, plus a bounce set by the bouncier of the two restitutions. Across the normal, friction
+/// (the geometric mean of the two coefficients) slows their sliding, but never by more than the
+/// normal impulse allows (Coulomb's law), and never so much that they start sliding backwards.
+///
+/// Frozen PhysicalObjects act as though they have infinite mass: they're never moved, and whatever
+/// hits them bounces off. Momentum is always conserved between two unfrozen bodies, and nothing
+/// here divides by anything that can be zero, so velocities never go NaN.
+pub fn apply_momentum(p1: &mut PhysicalObject, p2: &mut PhysicalObject, normal: Vector3<f32>) {
+    let (inverse1, inverse2) = (p1.inverse_mass(), p2.inverse_mass());
+    let inverse_sum = inverse1 + inverse2;
+    if inverse_sum == 0.0 {
+        return;
+    }
+
+    let relative = p2.velocity - p1.velocity;
+    let normal_speed = relative.dot(normal);
+    if normal_speed >= 0.0 {
+        // already moving apart
+        return;
+    }
+
+    let restitution = if normal_speed.abs() > BOUNCE_THRESHOLD {
+        p1.restitution.max(p2.restitution)
+    } else {
+        0.0
+    };
+
+    // impulses are applied as changes in speed shared out by inverse mass, rather than divided
+    // by the (possibly tiny) sum of inverse masses, so huge masses can't overflow into infinity
+    let (share1, share2) = (inverse1 / inverse_sum, inverse2 / inverse_sum);
+
+    let normal_change = -(1.0 + restitution) * normal_speed;
+    p1.velocity -= normal * (normal_change * share1);
+    p2.velocity += normal * (normal_change * share2);
+
+    // friction, from the sliding left over after the normal impulse
+    let after_bounce = p2.velocity - p1.velocity;
+    let sliding = after_bounce - normal * after_bounce.dot(normal);
+    let sliding_speed = sliding.magnitude();
+    if sliding_speed.is_nan() || sliding_speed < 1e-6 || sliding_speed.is_infinite() {
+        return;
+    }
+
+    let tangent = sliding / sliding_speed;
+    let friction = (p1.friction * p2.friction).sqrt();
+
+    // enough to stop the sliding completely, but no more than friction allows
+    let friction_change = sliding_speed.min(friction * normal_change);
+    p1.velocity += tangent * (friction_change * share1);
+    p2.velocity -= tangent * (friction_change * share2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{body_at, floor};
+    use super::*;
+    use crate::blocks::{Block, BlockType};
+
+    #[test]
+    fn falling_bodies_land_on_blocks() {
+        let mut body = body_at(0.1, 3.0, 0.1);
+
+        for _ in 0..120 {
+            body.physics(1.0 / 60.0, &floor);
+        }
+
+        assert!(body.on_ground());
+        assert!((body.position().y - 0.3).abs() < 0.001);
+        assert_eq!(body.velocity.y, 0.0);
+    }
+
+    #[test]
+    fn landing_updates_the_previous_flags() {
+        let mut body = body_at(0.1, 0.35, 0.1);
+
+        let mut steps = 0;
+        while !body.on_ground() {
+            body.physics(1.0 / 60.0, &floor);
+            steps += 1;
+            assert!(steps < 60, "never landed");
+        }
+        assert!(body.just_landed());
+
+        // still resting on the ground the step after
+        body.physics(1.0 / 60.0, &floor);
+        assert!(body.on_ground() && body.was_on_ground);
+        assert!(!body.just_landed());
+    }
+
+    #[test]
+    fn walls_and_ceilings_stop_bodies() {
+        // a wall at block column x = 4 and a ceiling at block row y = 6
+        let terrain = |(i, j, _): BlockIndex| j < 0 || i == 4 || j == 6;
+        let mut body = body_at(0.1, 0.3 + SKIN, 0.1);
+
+        body.velocity = Vector3::new(20.0, 0.0, 0.0);
+        body.physics(1.0, &terrain);
+        assert!(body.pushing_wall());
+        assert!(body.on_ground());
+        assert_eq!(body.velocity.x, 0.0);
+        assert!(body.position().x + 0.2 <= 2.0);
+
+        body.velocity = Vector3::new(0.0, 50.0, 0.0);
+        body.physics(1.0, &terrain);
+        assert!(body.at_ceiling());
+        assert!(!body.pushing_wall() && body.was_pushing_wall);
+        assert!(body.position().y + 0.3 <= 3.0);
+    }
+
+    /// A flat floor below y = 0 made entirely of one Surface.
+    struct Floor(Surface);
+
+    impl Terrain for Floor {
+        fn is_solid(&self, (_, j, _): BlockIndex) -> bool {
+            j < 0
+        }
+
+        fn surface(&self, _: BlockIndex) -> Surface {
+            self.0
+        }
+    }
+
+    /// Slides a body resting on `floor` at 5 m/s and returns how far it got before stopping.
+    fn slide(floor: &Floor) -> f32 {
+        let mut body = body_at(0.1, 0.3 + SKIN, 0.1);
+        body.physics(1.0 / 60.0, floor);
+        assert_eq!(body.ground_surface(), Some(floor.0));
+
+        body.velocity.x = 5.0;
+        for _ in 0..600 {
+            body.physics(1.0 / 60.0, floor);
+        }
+        assert!(body.velocity.x.abs() < 0.01);
+        assert!(body.velocity.x >= 0.0, "friction reversed the slide");
+
+        body.position().x - 0.1
+    }
+
+    #[test]
+    fn sliding_bodies_come_to_a_stop() {
+        let stone = slide(&Floor(BlockType::Stone.definition().surface));
+        let ice = slide(&Floor(BlockType::Ice.definition().surface));
+        let mud = slide(&Floor(BlockType::Mud.definition().surface));
+
+        assert!(ice > stone * 5.0, "ice {} stone {}", ice, stone);
+        assert!(mud < stone, "mud {} stone {}", mud, stone);
+    }
+
+    #[test]
+    fn slow_ground_weakens_pushing() {
+        let push = |floor: &Floor| {
+            let mut body = body_at(0.1, 0.3 + SKIN, 0.1);
+            body.set_friction(0.0);
+            body.physics(1.0 / 60.0, floor);
+
+            for _ in 0..30 {
+                body.apply_force(Vector3::new(20.0, 0.0, 0.0));
+                body.physics(1.0 / 60.0, floor);
+            }
+            body.velocity.x
+        };
+
+        let stone = push(&Floor(BlockType::Stone.definition().surface));
+        let sand = push(&Floor(BlockType::Sand.definition().surface));
+        assert!(
+            (sand / stone - 0.6).abs() < 0.01,
+            "sand {} stone {}",
+            sand,
+            stone
+        );
+    }
+
+    #[test]
+    fn air_resistance_limits_falling_speed() {
+        let nothing = |_: BlockIndex| false;
+        let mut body = body_at(0.0, 0.0, 0.0);
+
+        for _ in 0..60 * 30 {
+            body.physics(1.0 / 60.0, &nothing);
+        }
+        let terminal = -body.velocity.y;
+        assert!(terminal > 20.0 && terminal < 35.0, "{}", terminal);
+
+        // and without any drag, nothing holds it back
+        let mut vacuum = body_at(0.0, 0.0, 0.0);
+        vacuum.set_drag(0.0);
+        vacuum.set_air_resistance(0.0);
+        for _ in 0..60 * 30 {
+            vacuum.physics(1.0 / 60.0, &nothing);
+        }
+        assert!(-vacuum.velocity.y > 250.0);
+    }
+
+    #[test]
+    fn drag_slows_bodies_in_the_air() {
+        let nothing = |_: BlockIndex| false;
+        let mut body = body_at(0.0, 0.0, 0.0);
+        body.velocity.x = 1e6;
+
+        body.physics(1.0 / 60.0, &nothing);
+        assert!(body.velocity.x > 0.0 && body.velocity.x < 1e6);
+
+        for _ in 0..600 {
+            body.physics(1.0 / 60.0, &nothing);
+        }
+        assert!(
+            body.velocity.x > 0.0 && body.velocity.x < 3.0,
+            "{}",
+            body.velocity.x
+        );
+    }
+
+    /// A pool of water from y = 0 up to y = 4, with a floor below it.
+    struct Pool;
+
+    impl Terrain for Pool {
+        fn is_solid(&self, (_, j, _): BlockIndex) -> bool {
+            j < 0
+        }
+
+        fn fluid(&self, (_, j, _): BlockIndex) -> Option<Fluid> {
+            if (0..8).contains(&j) {
+                Some(Fluid::WATER)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// A floor below y = 0 with half a meter of water on it.
+    struct Puddle;
+
+    impl Terrain for Puddle {
+        fn is_solid(&self, (_, j, _): BlockIndex) -> bool {
+            j < 0
+        }
+
+        fn fluid(&self, (_, j, _): BlockIndex) -> Option<Fluid> {
+            if j == 0 {
+                Some(Fluid::WATER)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Drops a body with the given density into the Pool and lets it settle.
+    fn drop_in_pool(density: f32) -> PhysicalObject {
+        let mut body = body_at(0.1, 5.0, 0.1);
+        body.mass = density * 0.4 * 0.6 * 0.4;
+
+        for _ in 0..60 * 20 {
+            body.physics(1.0 / 60.0, &Pool);
+        }
+        body
+    }
+
+    #[test]
+    fn light_bodies_float() {
+        let body = drop_in_pool(500.0);
+
+        // floating half under, the same as its density compared to water's
+        assert!(
+            (body.submerged_fraction() - 0.5).abs() < 0.02,
+            "{}",
+            body.submerged_fraction()
+        );
+        assert!(body.velocity.y.abs() < 0.01);
+        assert_eq!(body.swim_state(), SwimState::Swimming);
+    }
+
+    #[test]
+    fn dense_bodies_sink() {
+        let body = drop_in_pool(2000.0);
+
+        assert!(body.on_ground());
+        assert_eq!(body.submerged_fraction(), 1.0);
+        assert_eq!(body.swim_state(), SwimState::Underwater);
+    }
+
+    #[test]
+    fn swim_states() {
+        let mut body = body_at(0.1, 0.3 + SKIN, 0.1);
+        body.physics(1.0 / 60.0, &floor);
+        assert_eq!(body.swim_state(), SwimState::Dry);
+
+        // standing in water up to the knees
+        body.physics(1.0 / 60.0, &Puddle);
+        assert_eq!(body.swim_state(), SwimState::Wading);
+
+        // fluid slows bodies down a lot more than air
+        let mut diver = body_at(0.1, 2.0, 0.1);
+        diver.velocity.x = 5.0;
+        diver.physics(1.0 / 60.0, &Pool);
+        diver.velocity.x = 5.0;
+        diver.physics(1.0 / 60.0, &Pool);
+        assert_eq!(diver.swim_state(), SwimState::Underwater);
+        assert!(diver.velocity.x < 5.0 * 0.96, "{}", diver.velocity.x);
+    }
+
+    /// Speeds from a slow walk up to far more than any body should ever reach, in meters per
+    /// second.
+    const SPEEDS: [f32; 7] = [1.0, 10.0, 60.0, 100.0, 1e3, 1e4, 1e6];
+
+    /// Total momentum of the bodies.
+    fn momentum(bodies: &[&PhysicalObject]) -> Vector3<f32> {
+        bodies.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, b| {
+            sum + b.velocity * b.mass
+        })
+    }
+
+    fn moving_body(mass: f32, velocity: Vector3<f32>) -> PhysicalObject {
+        let mut body = body_at(0.0, 0.0, 0.0);
+        body.mass = mass;
+        body.velocity = velocity;
+        body
+    }
+
+    #[test]
+    fn momentum_is_conserved() {
+        let masses = [0.1, 1.0, 2.0, 75.0, 1e4];
+        let restitutions = [0.0, 0.3, 1.0];
+
+        for &m1 in masses.iter() {
+            for &m2 in masses.iter() {
+                for &e in restitutions.iter() {
+                    let mut a = moving_body(m1, Vector3::new(3.0, -1.0, 0.5));
+                    let mut b = moving_body(m2, Vector3::new(-4.0, 2.0, 0.0));
+                    a.set_restitution(e);
+
+                    let before = momentum(&[&a, &b]);
+                    apply_momentum(&mut a, &mut b, Vector3::new(1.0, 0.0, 0.0));
+                    let after = momentum(&[&a, &b]);
+
+                    let error = (after - before).magnitude() / before.magnitude();
+                    assert!(
+                        error < 1e-4,
+                        "m1 {} m2 {} e {}: {:?} -> {:?}",
+                        m1,
+                        m2,
+                        e,
+                        before,
+                        after
+                    );
+
+                    // and they're no longer closing in on each other
+                    assert!(b.velocity.x >= a.velocity.x - 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn elastic_collisions_swap_equal_velocities() {
+        let mut a = moving_body(2.0, Vector3::new(5.0, 0.0, 0.0));
+        let mut b = moving_body(2.0, Vector3::new(-1.0, 0.0, 0.0));
+        a.set_restitution(1.0);
+
+        apply_momentum(&mut a, &mut b, Vector3::new(1.0, 0.0, 0.0));
+
+        assert!((a.velocity.x - -1.0).abs() < 1e-5);
+        assert!((b.velocity.x - 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn frozen_bodies_do_not_move_but_bounce_others() {
+        let mut wall = moving_body(5.0, Vector3::new(0.0, 0.0, 0.0));
+        wall.set_frozen(true);
+        let mut ball = moving_body(1.0, Vector3::new(10.0, 0.0, 0.0));
+        ball.set_restitution(0.5);
+
+        apply_momentum(&mut ball, &mut wall, Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(wall.velocity, Vector3::new(0.0, 0.0, 0.0));
+        assert!((ball.velocity.x - -5.0).abs() < 1e-5);
+
+        // two frozen bodies do nothing at all
+        let mut other_wall = moving_body(5.0, Vector3::new(1.0, 0.0, 0.0));
+        other_wall.set_frozen(true);
+        apply_momentum(&mut wall, &mut other_wall, Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(other_wall.velocity, Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn friction_slows_sliding_without_reversing_it() {
+        let mut floor_body = moving_body(5.0, Vector3::new(0.0, 0.0, 0.0));
+        floor_body.set_frozen(true);
+
+        // a hard landing while sliding: friction stops the sliding completely
+        let mut landing = moving_body(1.0, Vector3::new(0.5, -10.0, 0.0));
+        apply_momentum(&mut landing, &mut floor_body, Vector3::new(0.0, -1.0, 0.0));
+        assert!(landing.velocity.x.abs() < 1e-5);
+
+        // a soft landing while sliding fast: friction only takes some of it
+        let mut skidding = moving_body(1.0, Vector3::new(10.0, -0.5, 0.0));
+        apply_momentum(&mut skidding, &mut floor_body, Vector3::new(0.0, -1.0, 0.0));
+        assert!(skidding.velocity.x > 0.0 && skidding.velocity.x < 10.0);
+
+        // and none at all on ice
+        let mut sliding = moving_body(1.0, Vector3::new(10.0, -0.5, 0.0));
+        sliding.set_friction(0.0);
+        apply_momentum(&mut sliding, &mut floor_body, Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(sliding.velocity.x, 10.0);
+    }
+
+    #[test]
+    fn momentum_never_goes_nan() {
+        let values = [0.0, -0.0, 1e-30, -3.0, 7.5, 1e20, -1e20];
+        let masses = [1e-20, 0.0, -1.0, 1.0, 1e20];
+
+        for &m1 in masses.iter() {
+            for &m2 in masses.iter() {
+                for &v in values.iter() {
+                    for &w in values.iter() {
+                        let mut a = moving_body(m1, Vector3::new(v, w, v));
+                        let mut b = moving_body(m2, Vector3::new(w, v, -w));
+                        a.set_restitution(0.7);
+
+                        for normal in [
+                            Vector3::new(1.0, 0.0, 0.0),
+                            Vector3::new(0.0, -1.0, 0.0),
+                            Vector3::new(0.0, 0.0, 1.0),
+                        ]
+                        .iter()
+                        {
+                            apply_momentum(&mut a, &mut b, *normal);
+                            assert!(
+                                !a.velocity.x.is_nan()
+                                    && !a.velocity.y.is_nan()
+                                    && !a.velocity.z.is_nan()
+                                    && !b.velocity.x.is_nan()
+                                    && !b.velocity.y.is_nan()
+                                    && !b.velocity.z.is_nan(),
+                                "m1 {} m2 {} v {} w {}",
+                                m1,
+                                m2,
+                                v,
+                                w
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fixing_collisions_separates_bodies() {
+        let mut a = moving_body(1.0, Vector3::new(2.0, 0.0, 0.0));
+        let mut b = moving_body(3.0, Vector3::new(0.0, 0.0, 0.0));
+        b.set_position(Vector3::new(0.3, 0.0, 0.0));
+
+        assert!(a.fix_collision(&mut b));
+        assert!(!a.hitbox.collides_with(&b.hitbox));
+
+        // the lighter body moved three times as far
+        assert!((a.position().x - -0.075).abs() < 1e-5);
+        assert!((b.position().x - 0.325).abs() < 1e-5);
+        assert!(!a.fix_collision(&mut b));
+    }
+
+    #[test]
+    fn nothing_tunnels_through_a_one_block_terrain_wall() {
+        // a one-block wall at block column x = 4, from 2.0 to 2.5
+        let wall = |(i, _, _): BlockIndex| i == 4;
+
+        for &speed in SPEEDS.iter() {
+            let mut body = body_at(0.0, 0.0, 0.0);
+            body.velocity = Vector3::new(speed, 0.0, 0.0);
+
+            for _ in 0..10 {
+                body.physics(1.0 / 60.0, &wall);
+                assert!(body.position().x + 0.2 <= 2.0, "went through at {}", speed);
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_tunnels_through_a_one_block_obstacle() {
+        let wall = maths::AABB {
+            center_pos: Vector3::new(2.25, 0.0, 0.0),
+            half_size: Vector3::new(Block::WIDTH / 2.0, 10.0, 10.0),
+        };
+        let nothing = |_: BlockIndex| false;
+
+        for &speed in SPEEDS.iter() {
+            for &direction in [1.0, -1.0].iter() {
+                let mut body = body_at(2.25 - direction * 3.0, 0.0, 0.0);
+                body.velocity = Vector3::new(speed * direction, 0.0, 0.0);
+
+                for _ in 0..10 {
+                    body.physics_among(1.0 / 60.0, &nothing, &[wall]);
+                }
+
+                let x = body.position().x;
+                let stayed_on_its_side = if direction > 0.0 {
+                    x + 0.2 <= 2.0
+                } else {
+                    x - 0.2 >= 2.5
+                };
+                assert!(stayed_on_its_side, "went through at {}", speed);
+                assert!(speed < 60.0 || body.velocity.x == 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn bodies_slide_along_obstacles_they_hit() {
+        // a wide box to land on, with no terrain at all
+        let platform = maths::AABB {
+            center_pos: Vector3::new(0.0, -0.5, 0.0),
+            half_size: Vector3::new(50.0, 0.5, 50.0),
+        };
+        let nothing = |_: BlockIndex| false;
+
+        let mut body = body_at(0.0, 1.0, 0.0);
+        body.set_air_resistance(0.0);
+        body.set_drag(0.0);
+        body.velocity = Vector3::new(3.0, -30.0, 0.0);
+        body.physics_among(0.1, &nothing, &[platform]);
+
+        assert!(body.on_ground());
+        assert!(body.position().y - 0.3 >= 0.0);
+        assert!((body.position().x - 0.3).abs() < 0.01);
+    }
+}
+
+// vim: foldmethod=syntax
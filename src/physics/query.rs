@@ -0,0 +1,327 @@
+use super::{block_bounds, BlockIndex, BodyHandle, Terrain};
+use crate::blocks::Block;
+use crate::maths::AABB;
+use cgmath::Vector3;
+use std::cmp::Ordering;
+use std::ops::BitOr;
+
+/// Layers is a set of up to 32 collision layers. Every body is on some layers, and queries only
+/// find bodies on the layers they ask for, so that (say) an arrow can look for chickens without
+/// hitting dropped items.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Layers(u32);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const ALL: Layers = Layers(u32::MAX);
+
+    /// The layer bodies are on unless they're put on others.
+    pub const DEFAULT: Layers = Layers::layer(0);
+
+    /// Returns a set holding just layer `n`, from 0 to 31.
+    pub const fn layer(n: u32) -> Layers {
+        Layers(1 << n)
+    }
+
+    /// Returns true if every layer in `other` is in this set too.
+    pub fn contains(self, other: Layers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if the sets have any layer in common.
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// QueryFilter decides what a scene query can find.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QueryFilter {
+    /// Bodies are only found if they're on at least one of these layers.
+    pub layers: Layers,
+
+    /// Whether solid blocks are found.
+    pub terrain: bool,
+
+    /// A body to skip, like the one doing the looking.
+    pub ignore: Option<BodyHandle>,
+}
+
+impl QueryFilter {
+    /// Finds everything: solid blocks and bodies on any layer.
+    pub const ALL: QueryFilter = QueryFilter {
+        layers: Layers::ALL,
+        terrain: true,
+        ignore: None,
+    };
+
+    /// Finds bodies on `layers`, but not blocks.
+    pub fn bodies(layers: Layers) -> Self {
+        Self {
+            layers,
+            terrain: false,
+            ignore: None,
+        }
+    }
+
+    /// Returns the same filter, but skipping `body`.
+    pub fn ignoring(self, body: BodyHandle) -> Self {
+        Self {
+            ignore: Some(body),
+            ..self
+        }
+    }
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// What a scene query found.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HitTarget {
+    Block(BlockIndex),
+    Body(BodyHandle),
+}
+
+/// Where a raycast or shape cast first hit something.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QueryHit {
+    pub target: HitTarget,
+
+    /// How far along the cast the hit is, in meters.
+    pub distance: f32,
+
+    /// Points out of the face that was hit, back toward where the cast came from. Casts that
+    /// start inside of something hit it right away, with the normal pointing straight back.
+    pub normal: Vector3<f32>,
+}
+
+/// Returns the closer of the two hits. Ties go to `a`.
+pub(super) fn closest(a: Option<QueryHit>, b: Option<QueryHit>) -> Option<QueryHit> {
+    match (a, b) {
+        (Some(x), Some(y)) if y.distance < x.distance => Some(y),
+        (None, y) => y,
+        (x, _) => x,
+    }
+}
+
+/// Casts a ray through the terrain, returning the first solid block it hits within
+/// `max_distance`. `direction` has to be a unit vector. Blocks are stepped through one at a time
+/// along the ray, so long rays are cheap.
+pub fn raycast_terrain<T: Terrain + ?Sized>(
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+    max_distance: f32,
+    terrain: &T,
+) -> Option<QueryHit> {
+    let cell = |v: f32| (v / Block::WIDTH).floor() as i64;
+    let mut block = [cell(origin.x), cell(origin.y), cell(origin.z)];
+
+    if terrain.is_solid((block[0], block[1], block[2])) {
+        return Some(QueryHit {
+            target: HitTarget::Block((block[0], block[1], block[2])),
+            distance: 0.0,
+            normal: -direction,
+        });
+    }
+
+    // how far along the ray the next block boundary is on each axis, and how far apart the
+    // boundaries are
+    let mut step = [0; 3];
+    let mut next = [f32::INFINITY; 3];
+    let mut spacing = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            next[axis] = ((block[axis] + 1) as f32 * Block::WIDTH - origin[axis]) / direction[axis];
+            spacing[axis] = Block::WIDTH / direction[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            next[axis] = (block[axis] as f32 * Block::WIDTH - origin[axis]) / direction[axis];
+            spacing[axis] = -Block::WIDTH / direction[axis];
+        }
+    }
+
+    loop {
+        let axis = (0..3)
+            .min_by(|&a, &b| next[a].partial_cmp(&next[b]).unwrap_or(Ordering::Equal))
+            .unwrap_or(0);
+
+        let distance = next[axis];
+        if !distance.is_finite() || distance > max_distance {
+            // also stops rays that go nowhere, which never cross a boundary
+            return None;
+        }
+
+        block[axis] += step[axis];
+        next[axis] += spacing[axis];
+
+        let index = (block[0], block[1], block[2]);
+        if terrain.is_solid(index) {
+            let mut normal = Vector3::new(0.0, 0.0, 0.0);
+            normal[axis] = -step[axis] as f32;
+
+            return Some(QueryHit {
+                target: HitTarget::Block(index),
+                distance,
+                normal,
+            });
+        }
+    }
+}
+
+/// Sweeps `aabb` through the terrain, returning the first solid block it hits within
+/// `max_distance`. `direction` has to be a unit vector. Blocks the box overlaps to begin with are
+/// ignored; use `overlap_terrain` to find those. Every block near the path is checked, so this
+/// gets slower the more space the sweep covers.
+pub fn shape_cast_terrain<T: Terrain + ?Sized>(
+    aabb: &AABB,
+    direction: Vector3<f32>,
+    max_distance: f32,
+    terrain: &T,
+) -> Option<QueryHit> {
+    let displacement = direction * max_distance;
+
+    aabb.expanded_by(displacement)
+        .cells(Block::WIDTH)
+        .filter(|&block| terrain.is_solid(block))
+        .filter_map(|block| {
+            aabb.sweep(displacement, &block_bounds(block))
+                .map(|hit| QueryHit {
+                    target: HitTarget::Block(block),
+                    distance: hit.time * max_distance,
+                    normal: hit.normal,
+                })
+        })
+        .fold(None, |best, hit| closest(best, Some(hit)))
+}
+
+/// Returns every solid block that `aabb` overlaps. Touching a block's face doesn't count.
+pub fn overlap_terrain<T: Terrain + ?Sized>(aabb: &AABB, terrain: &T) -> Vec<BlockIndex> {
+    aabb.cells(Block::WIDTH)
+        .filter(|&block| terrain.is_solid(block))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Solid everywhere below y = 0, plus a one-block wall at block column x = 4 (from 2.0 to
+    /// 2.5).
+    fn walled((i, j, _): BlockIndex) -> bool {
+        j < 0 || i == 4
+    }
+
+    #[test]
+    fn layers() {
+        let arrows = Layers::layer(3);
+        let both = Layers::DEFAULT | arrows;
+
+        assert!(both.contains(arrows) && both.intersects(Layers::DEFAULT));
+        assert!(!arrows.intersects(Layers::DEFAULT));
+        assert!(Layers::ALL.contains(both));
+        assert!(!Layers::NONE.intersects(Layers::ALL));
+    }
+
+    #[test]
+    fn rays_hit_the_first_solid_block() {
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        let hit = raycast_terrain(Vector3::new(0.1, 0.2, 0.1), x, 10.0, &walled).unwrap();
+
+        assert_eq!(hit.target, HitTarget::Block((4, 0, 0)));
+        assert!((hit.distance - 1.9).abs() < 1e-5, "{}", hit.distance);
+        assert_eq!(hit.normal, Vector3::new(-1.0, 0.0, 0.0));
+
+        // too short to reach it
+        assert_eq!(
+            raycast_terrain(Vector3::new(0.1, 0.2, 0.1), x, 1.5, &walled),
+            None
+        );
+
+        // straight down onto the floor
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        let floor_hit = raycast_terrain(Vector3::new(0.1, 3.0, 0.1), down, 10.0, &walled).unwrap();
+        assert_eq!(floor_hit.target, HitTarget::Block((0, -1, 0)));
+        assert!((floor_hit.distance - 3.0).abs() < 1e-5);
+        assert_eq!(floor_hit.normal, Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn diagonal_rays_step_through_every_block() {
+        // a single block in the way of a diagonal ray
+        let lone = |b: BlockIndex| b == (3, 3, 0);
+        let diagonal = Vector3::new(1.0, 1.0, 0.0) / 2f32.sqrt();
+
+        let hit = raycast_terrain(Vector3::new(0.1, 0.2, 0.1), diagonal, 10.0, &lone).unwrap();
+        assert_eq!(hit.target, HitTarget::Block((3, 3, 0)));
+
+        // the entry point is on the face of the block
+        let point = Vector3::new(0.1, 0.2, 0.1) + diagonal * hit.distance;
+        assert!(block_bounds((3, 3, 0)).contains_point(point), "{:?}", point);
+    }
+
+    #[test]
+    fn rays_starting_in_blocks_hit_right_away() {
+        let hit = raycast_terrain(
+            Vector3::new(0.1, -0.2, 0.1),
+            Vector3::new(0.0, 1.0, 0.0),
+            10.0,
+            &walled,
+        )
+        .unwrap();
+
+        assert_eq!(hit.target, HitTarget::Block((0, -1, 0)));
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn shape_casts_stop_at_blocks() {
+        let x = Vector3::new(1.0, 0.0, 0.0);
+
+        // resting on the floor, sliding along it into the wall
+        let resting = AABB::cube(0.25, 0.25, 0.25, 0.25);
+        let hit = shape_cast_terrain(&resting, x, 10.0, &walled).unwrap();
+        assert_eq!(hit.target, HitTarget::Block((4, 0, 0)));
+        assert!((hit.distance - 1.5).abs() < 1e-5, "{}", hit.distance);
+        assert_eq!(hit.normal, Vector3::new(-1.0, 0.0, 0.0));
+
+        // falling onto the floor
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        let falling =
+            shape_cast_terrain(&AABB::cube(0.25, 2.0, 0.25, 0.25), down, 5.0, &walled).unwrap();
+        assert!((falling.distance - 1.75).abs() < 1e-5);
+        assert_eq!(falling.normal, Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(shape_cast_terrain(&resting, -x, 10.0, &walled), None);
+    }
+
+    #[test]
+    fn overlapping_blocks() {
+        // sinking a little into the floor, and touching the wall
+        let b = AABB::from_min_max(Vector3::new(1.5, -0.1, 0.0), Vector3::new(2.0, 0.4, 0.4));
+
+        let mut blocks = overlap_terrain(&b, &walled);
+        blocks.sort();
+        assert_eq!(blocks, vec![(3, -1, 0)]);
+
+        assert!(overlap_terrain(&AABB::cube(1.0, 1.0, 1.0, 0.5), &walled).is_empty());
+    }
+}
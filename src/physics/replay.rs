@@ -0,0 +1,69 @@
+use super::{BodyHandle, PhysicalObject, SurfaceContact};
+use cgmath::Vector3;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// PhysicsSnapshot is a copy of everything in a PhysicsWorld that affects how it steps: every
+/// body (with all of its fields), the time left over from the last update, and which contacts
+/// were already touching. Restoring one with `PhysicsWorld::restore` puts the world back exactly
+/// as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicsSnapshot {
+    pub(super) bodies: BTreeMap<BodyHandle, PhysicalObject>,
+    pub(super) next_handle: u64,
+    pub(super) timestep: f32,
+    pub(super) accumulator: f32,
+    pub(super) touching: BTreeSet<(BodyHandle, BodyHandle)>,
+    pub(super) touching_terrain: BTreeSet<(BodyHandle, SurfaceContact)>,
+}
+
+impl PhysicsSnapshot {
+    pub fn body(&self, handle: BodyHandle) -> Option<&PhysicalObject> {
+        self.bodies.get(&handle)
+    }
+
+    /// Iterates over every body, in handle order.
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &PhysicalObject)> {
+        self.bodies.iter().map(|(&h, b)| (h, b))
+    }
+}
+
+/// Recording is what a PhysicsWorld did while it was recording: a snapshot of how it started, and
+/// the forces applied through `PhysicsWorld::apply_force` before each step. Stepping is
+/// deterministic, so replaying it with `PhysicsWorld::replay` over the same terrain ends up in
+/// exactly the same state, down to the bit.
+///
+/// Only forces are recorded. Anything changed through `body_mut`, and bodies added or removed
+/// while recording, won't be replayed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub(super) start: PhysicsSnapshot,
+
+    /// The forces applied before each step, in the order they were applied.
+    pub(super) ticks: Vec<Vec<(BodyHandle, Vector3<f32>)>>,
+}
+
+impl Recording {
+    /// Returns the state the world was in when recording started.
+    pub fn start(&self) -> &PhysicsSnapshot {
+        &self.start
+    }
+
+    /// Returns how many steps were recorded.
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    /// Returns the forces applied before step `tick`, in the order they were applied.
+    pub fn forces(&self, tick: usize) -> &[(BodyHandle, Vector3<f32>)] {
+        self.ticks.get(tick).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Cuts the recording off after `ticks` steps, for narrowing down which step goes wrong.
+    pub fn truncate(&mut self, ticks: usize) {
+        self.ticks.truncate(ticks);
+    }
+}
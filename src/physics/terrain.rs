@@ -0,0 +1,340 @@
+use super::{Fluid, Surface};
+use crate::blocks::Block;
+use crate::maths::AABB;
+use cgmath::Vector3;
+
+/// The position of a block on the world's block grid. Block (i, j, k) fills the cube from
+/// `(i, j, k) * Block::WIDTH` to `(i + 1, j + 1, k + 1) * Block::WIDTH`.
+pub type BlockIndex = (i64, i64, i64);
+
+/// Boxes are kept this far apart from blocks they touch, so that rounding never leaves a box
+/// overlapping a block it's resting against.
+pub const SKIN: f32 = 0.0001;
+
+/// Terrain is anything made of blocks that bodies can collide with, like the World. Closures work
+/// too, which is handy for tests.
+pub trait Terrain {
+    /// Returns true if bodies can't pass through the block.
+    fn is_solid(&self, block: BlockIndex) -> bool;
+
+    /// Returns what the block is like to walk on. Only asked about solid blocks.
+    fn surface(&self, _block: BlockIndex) -> Surface {
+        Surface::DEFAULT
+    }
+
+    /// Returns the Fluid filling the block, or None if it isn't a fluid block.
+    fn fluid(&self, _block: BlockIndex) -> Option<Fluid> {
+        None
+    }
+}
+
+impl<F> Terrain for F
+where
+    F: Fn(BlockIndex) -> bool,
+{
+    fn is_solid(&self, block: BlockIndex) -> bool {
+        self(block)
+    }
+}
+
+/// Returns the box a block fills.
+pub fn block_bounds((i, j, k): BlockIndex) -> AABB {
+    let min = Vector3::new(i as f32, j as f32, k as f32) * Block::WIDTH;
+    AABB::from_min_max(min, min + Vector3::new(1.0, 1.0, 1.0) * Block::WIDTH)
+}
+
+/// Returns the range of block grid positions that overlap `min..max` along one axis. Touching a
+/// block's edge doesn't count as overlapping it.
+fn blocks_overlapping(min: f32, max: f32) -> std::ops::RangeInclusive<i64> {
+    let first = ((min + SKIN) / Block::WIDTH).floor() as i64;
+    let last = ((max - SKIN) / Block::WIDTH).floor() as i64;
+    first..=last
+}
+
+/// Returns how far `hitbox` can move along `axis` (0 for x, 1 for y, 2 for z) before it hits a
+/// solid block, up to `distance`. Blocks the box already overlaps are ignored so that a body
+/// stuck inside of terrain can still get out.
+pub fn sweep_axis<T: Terrain + ?Sized>(
+    hitbox: &AABB,
+    axis: usize,
+    distance: f32,
+    terrain: &T,
+) -> f32 {
+    if distance == 0.0 {
+        return 0.0;
+    }
+
+    let min = hitbox.center_pos - hitbox.half_size;
+    let max = hitbox.center_pos + hitbox.half_size;
+
+    // the slab of space swept by the leading face of the box
+    let (sweep_min, sweep_max) = if distance > 0.0 {
+        (max[axis], max[axis] + distance)
+    } else {
+        (min[axis] + distance, min[axis])
+    };
+
+    // the other two axes only need blocks the box overlaps
+    let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+    let range_a = blocks_overlapping(min[a], max[a]);
+    let range_b = blocks_overlapping(min[b], max[b]);
+
+    // step through the swept blocks nearest first, so the first solid one is the answer
+    let swept = blocks_overlapping(sweep_min, sweep_max);
+    let steps: Box<dyn Iterator<Item = i64>> = if distance > 0.0 {
+        Box::new(swept)
+    } else {
+        Box::new(swept.rev())
+    };
+
+    for step in steps {
+        let block_min = step as f32 * Block::WIDTH;
+        let block_max = block_min + Block::WIDTH;
+
+        // already overlapping on this axis; don't trap the box
+        if block_max > min[axis] + SKIN && block_min < max[axis] - SKIN {
+            continue;
+        }
+
+        for i in range_a.clone() {
+            for j in range_b.clone() {
+                let mut block = [0; 3];
+                block[axis] = step;
+                block[a] = i;
+                block[b] = j;
+
+                if terrain.is_solid((block[0], block[1], block[2])) {
+                    let allowed = if distance > 0.0 {
+                        block_min - max[axis] - SKIN
+                    } else {
+                        block_max - min[axis] + SKIN
+                    };
+
+                    // never push the box backwards, only stop it
+                    return if distance > 0.0 {
+                        allowed.max(0.0).min(distance)
+                    } else {
+                        allowed.min(0.0).max(distance)
+                    };
+                }
+            }
+        }
+    }
+
+    distance
+}
+
+/// Returns the Surface of the ground under `hitbox`: the block under its center if that one is
+/// solid, or else the first solid block under any part of it (for bodies standing on an edge).
+/// Bodies standing on something other than blocks get `Surface::DEFAULT`.
+pub fn surface_under<T: Terrain + ?Sized>(hitbox: &AABB, terrain: &T) -> Surface {
+    let (min, max) = (hitbox.min(), hitbox.max());
+
+    // a thin layer right under the feet; resting bodies float SKIN above the blocks
+    let feet = AABB::from_min_max(
+        Vector3::new(min.x, min.y - 2.0 * SKIN, min.z),
+        Vector3::new(max.x, min.y - SKIN, max.z),
+    );
+    let j = (feet.center_pos.y / Block::WIDTH).floor() as i64;
+
+    let center = (
+        (hitbox.center_pos.x / Block::WIDTH).floor() as i64,
+        j,
+        (hitbox.center_pos.z / Block::WIDTH).floor() as i64,
+    );
+    if terrain.is_solid(center) {
+        return terrain.surface(center);
+    }
+
+    feet.cells(Block::WIDTH)
+        .find(|&block| terrain.is_solid(block))
+        .map(|block| terrain.surface(block))
+        .unwrap_or_default()
+}
+
+/// How much of a box is under fluid.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Submersion {
+    /// How much of the box's volume is under, from 0 to 1.
+    pub fraction: f32,
+
+    /// The fluid it's in. If it's in more than one kind, they're averaged by how much of the box
+    /// each one covers.
+    pub fluid: Fluid,
+}
+
+/// Returns how much of `hitbox` is in fluid blocks, or None if none of it is.
+pub fn submersion<T: Terrain + ?Sized>(hitbox: &AABB, terrain: &T) -> Option<Submersion> {
+    let volume = |b: &AABB| 8.0 * b.half_size.x * b.half_size.y * b.half_size.z;
+
+    let mut submerged = 0.0;
+    let mut density = 0.0;
+    let mut drag = 0.0;
+
+    for block in hitbox.cells(Block::WIDTH) {
+        let fluid = match terrain.fluid(block) {
+            Some(f) => f,
+            None => continue,
+        };
+
+        if let Some(overlap) = hitbox.intersection(&block_bounds(block)) {
+            let v = volume(&overlap);
+            submerged += v;
+            density += fluid.density * v;
+            drag += fluid.drag * v;
+        }
+    }
+
+    if submerged <= 0.0 {
+        return None;
+    }
+
+    Some(Submersion {
+        fraction: (submerged / volume(hitbox)).min(1.0),
+        fluid: Fluid {
+            density: density / submerged,
+            drag: drag / submerged,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::floor;
+    use cgmath::Vector3;
+
+    fn unit_box(x: f32, y: f32, z: f32) -> AABB {
+        AABB {
+            center_pos: Vector3::new(x, y, z),
+            half_size: Vector3::new(0.25, 0.25, 0.25),
+        }
+    }
+
+    #[test]
+    fn stops_on_the_floor() {
+        let hitbox = unit_box(0.1, 1.0, 0.1);
+
+        let moved = sweep_axis(&hitbox, 1, -5.0, &floor);
+        assert!((moved - -0.75).abs() < 0.001, "{}", moved);
+    }
+
+    #[test]
+    fn moves_freely_through_air() {
+        let hitbox = unit_box(0.1, 1.0, 0.1);
+
+        assert_eq!(sweep_axis(&hitbox, 1, 2.0, &floor), 2.0);
+        assert_eq!(sweep_axis(&hitbox, 0, -3.0, &floor), -3.0);
+    }
+
+    #[test]
+    fn resting_boxes_slide_along_the_floor() {
+        // sitting right on top of the floor
+        let hitbox = unit_box(0.1, 0.25 + SKIN, 0.1);
+
+        assert_eq!(sweep_axis(&hitbox, 0, 3.0, &floor), 3.0);
+        assert_eq!(sweep_axis(&hitbox, 2, -3.0, &floor), -3.0);
+        assert!(sweep_axis(&hitbox, 1, -1.0, &floor).abs() < 0.001);
+    }
+
+    #[test]
+    fn stops_at_walls() {
+        // a wall filling block column x = 4 (from 2.0 to 2.5)
+        let wall = |(i, _, _): BlockIndex| i == 4;
+        let hitbox = unit_box(0.0, 0.0, 0.0);
+
+        let moved = sweep_axis(&hitbox, 0, 10.0, &wall);
+        assert!((moved - 1.75).abs() < 0.001, "{}", moved);
+    }
+
+    /// A floor of ice below y = 0 with a hole at block column x = 0, a stone edge around the hole
+    /// at x = -1, and a strip of sand at x = 2.
+    struct IceRink;
+
+    impl Terrain for IceRink {
+        fn is_solid(&self, (i, j, _): BlockIndex) -> bool {
+            j < 0 && i != 0
+        }
+
+        fn surface(&self, (i, _, _): BlockIndex) -> Surface {
+            let friction = match i {
+                -1 => 1.0,
+                2 => 0.8,
+                _ => 0.01,
+            };
+            Surface {
+                friction,
+                ..Surface::DEFAULT
+            }
+        }
+    }
+
+    #[test]
+    fn finds_the_surface_underfoot() {
+        let on_ice = unit_box(0.6, 0.25 + SKIN, 0.1);
+        assert_eq!(surface_under(&on_ice, &IceRink).friction, 0.01);
+
+        // centered over the sand, hanging over the ice
+        let on_sand = unit_box(1.1, 0.25 + SKIN, 0.1);
+        assert_eq!(surface_under(&on_sand, &IceRink).friction, 0.8);
+
+        // centered over the hole, still standing on the stone edge
+        let on_edge = unit_box(0.1, 0.25 + SKIN, 0.1);
+        assert_eq!(surface_under(&on_edge, &IceRink).friction, 1.0);
+
+        // closures only know what's solid
+        assert_eq!(surface_under(&on_ice, &floor), Surface::DEFAULT);
+    }
+
+    /// Water filling everything from y = 0 to y = 2, except for a column of oil at block x = 1.
+    struct Pond;
+
+    impl Terrain for Pond {
+        fn is_solid(&self, _: BlockIndex) -> bool {
+            false
+        }
+
+        fn fluid(&self, (i, j, _): BlockIndex) -> Option<Fluid> {
+            match (i, j) {
+                (_, j) if !(0..4).contains(&j) => None,
+                (1, _) => Some(Fluid {
+                    density: 800.0,
+                    drag: 5.0,
+                }),
+                _ => Some(Fluid::WATER),
+            }
+        }
+    }
+
+    #[test]
+    fn measures_how_much_is_submerged() {
+        assert_eq!(submersion(&unit_box(0.1, 3.0, 0.1), &Pond), None);
+        assert_eq!(submersion(&unit_box(0.1, -0.25, 0.1), &Pond), None);
+
+        let under = submersion(&unit_box(0.1, 1.0, 0.1), &Pond).unwrap();
+        assert!((under.fraction - 1.0).abs() < 1e-5);
+        assert!((under.fluid.density - Fluid::WATER.density).abs() < 0.01);
+
+        // half in, straddling the surface
+        let half = submersion(&unit_box(-0.7, 2.0, 0.1), &Pond).unwrap();
+        assert!((half.fraction - 0.5).abs() < 1e-5, "{}", half.fraction);
+
+        // a quarter in the oil, the rest in water
+        let mixed = submersion(&unit_box(0.375, 1.0, 0.1), &Pond).unwrap();
+        assert!((mixed.fraction - 1.0).abs() < 1e-5);
+        assert!((mixed.fluid.density - 950.0).abs() < 0.01);
+        assert!((mixed.fluid.drag - 3.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn boxes_inside_blocks_can_leave() {
+        let solid = |_: BlockIndex| true;
+        let hitbox = unit_box(0.25, 0.25, 0.25);
+
+        // the block it's inside of doesn't count, but the next one does
+        assert!(sweep_axis(&hitbox, 1, 1.0, &solid).abs() < 0.001);
+
+        let inside_one = |b: BlockIndex| b == (0, 0, 0);
+        assert_eq!(sweep_axis(&hitbox, 1, 1.0, &inside_one), 1.0);
+    }
+}
@@ -0,0 +1,21 @@
+//! Terrain and bodies that the physics tests, and tests of anything built on physics, share.
+
+use super::{BlockIndex, PhysicalObject};
+use crate::maths::AABB;
+use cgmath::Vector3;
+
+/// Solid everywhere below y = 0.
+pub fn floor((_, j, _): BlockIndex) -> bool {
+    j < 0
+}
+
+/// Returns a 2 kg body about the size of a chicken, centered at the position.
+pub fn body_at(x: f32, y: f32, z: f32) -> PhysicalObject {
+    PhysicalObject::new(
+        2.0,
+        AABB {
+            center_pos: Vector3::new(x, y, z),
+            half_size: Vector3::new(0.2, 0.3, 0.2),
+        },
+    )
+}
@@ -0,0 +1,877 @@
+use super::query::closest;
+use super::{
+    overlap_terrain, raycast_terrain, shape_cast_terrain, Broadphase, HitTarget, PhysicalObject,
+    PhysicsSnapshot, QueryFilter, QueryHit, Recording, Terrain,
+};
+use crate::maths;
+use cgmath::{InnerSpace, Vector3};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How long a single physics step is, in seconds.
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// The most steps a single `update` will take. If the game falls further behind than this (say,
+/// after a long pause), the extra time is dropped instead of trying to catch up all at once.
+const MAX_STEPS_PER_UPDATE: u32 = 8;
+
+/// Identifies a body in a PhysicsWorld. Handles are never reused, so a handle to a removed body
+/// stays invalid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BodyHandle(u64);
+
+/// Which side of a body ran into something.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SurfaceContact {
+    Ground,
+    Wall,
+    Ceiling,
+}
+
+/// Something that started touching during a physics step. Contacts are only reported when they
+/// begin, not for every step they last.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContactEvent {
+    /// Two bodies ran into each other, or one landed on or ran into a frozen one. `a` is always
+    /// the lower handle.
+    Bodies { a: BodyHandle, b: BodyHandle },
+
+    /// A body ran into solid blocks. A body resting on the ground reports it once, when it
+    /// lands.
+    Terrain {
+        body: BodyHandle,
+        contact: SurfaceContact,
+    },
+}
+
+/// PhysicsWorld owns every PhysicalObject in the game and moves them in fixed-size steps, so that
+/// physics behaves the same no matter the frame rate. Bodies are reached through the BodyHandle
+/// returned by `add_body`. Nothing in here touches the GPU, so it runs fine headless.
+pub struct PhysicsWorld {
+    /// A BTreeMap so that bodies are always stepped in the same order.
+    bodies: BTreeMap<BodyHandle, PhysicalObject>,
+    next_handle: u64,
+
+    /// Where every body's hitbox is, so that bodies are only checked against nearby ones.
+    broadphase: Broadphase<BodyHandle>,
+
+    timestep: f32,
+
+    /// Time that has passed but hasn't been stepped through yet, always less than one timestep
+    /// after an update.
+    accumulator: f32,
+
+    /// Pairs of bodies touching during the last step, to tell which contacts are new.
+    touching: BTreeSet<(BodyHandle, BodyHandle)>,
+
+    /// Same as `touching`, for bodies touching terrain.
+    touching_terrain: BTreeSet<(BodyHandle, SurfaceContact)>,
+    events: Vec<ContactEvent>,
+
+    /// Set while recording, along with the forces applied since the last step.
+    recording: Option<Recording>,
+    pending_forces: Vec<(BodyHandle, Vector3<f32>)>,
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self::with_timestep(FIXED_TIMESTEP)
+    }
+
+    /// Makes a PhysicsWorld that steps every `timestep` seconds instead of the default.
+    pub fn with_timestep(timestep: f32) -> Self {
+        Self {
+            bodies: BTreeMap::new(),
+            next_handle: 0,
+            broadphase: Broadphase::new(),
+            timestep,
+            accumulator: 0.0,
+            touching: BTreeSet::new(),
+            touching_terrain: BTreeSet::new(),
+            events: Vec::new(),
+            recording: None,
+            pending_forces: Vec::new(),
+        }
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    pub fn add_body(&mut self, body: PhysicalObject) -> BodyHandle {
+        let handle = BodyHandle(self.next_handle);
+        self.next_handle += 1;
+
+        self.broadphase.insert(handle, body.hitbox);
+        self.bodies.insert(handle, body);
+        handle
+    }
+
+    /// Takes the body out of the world, returning it if it was there.
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<PhysicalObject> {
+        self.touching.retain(|&(a, b)| a != handle && b != handle);
+        self.touching_terrain.retain(|&(body, _)| body != handle);
+        self.broadphase.remove(handle);
+        self.bodies.remove(&handle)
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&PhysicalObject> {
+        self.bodies.get(&handle)
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut PhysicalObject> {
+        self.bodies.get_mut(&handle)
+    }
+
+    /// Iterates over every body, in handle order.
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &PhysicalObject)> {
+        self.bodies.iter().map(|(&h, b)| (h, b))
+    }
+
+    /// Applies a force, in newtons, to the body during the next step. Does nothing if the body
+    /// doesn't exist.
+    pub fn apply_force(&mut self, handle: BodyHandle, newtons: Vector3<f32>) {
+        if let Some(body) = self.bodies.get_mut(&handle) {
+            body.apply_force(newtons);
+
+            if self.recording.is_some() {
+                self.pending_forces.push((handle, newtons));
+            }
+        }
+    }
+
+    /// Pushes the body toward moving along the ground at `velocity` (its y is ignored): as hard as
+    /// it takes to get there by the end of the next step, but never harder than
+    /// `max_acceleration`. Does nothing if the body doesn't exist. Call it before every step, from
+    /// `update_with`, and it pushes the same however often the game updates.
+    pub fn steer(&mut self, handle: BodyHandle, velocity: Vector3<f32>, max_acceleration: f32) {
+        let (current, mass) = match self.bodies.get(&handle) {
+            Some(body) => (body.velocity(), body.mass()),
+            None => return,
+        };
+
+        let change = Vector3::new(velocity.x - current.x, 0.0, velocity.z - current.z);
+        let mut acceleration = change / self.timestep;
+        if acceleration.magnitude() > max_acceleration {
+            acceleration = acceleration.normalize_to(max_acceleration);
+        }
+
+        self.apply_force(handle, acceleration * mass);
+    }
+
+    /// Copies everything that affects how the world steps.
+    pub fn snapshot(&self) -> PhysicsSnapshot {
+        PhysicsSnapshot {
+            bodies: self.bodies.clone(),
+            next_handle: self.next_handle,
+            timestep: self.timestep,
+            accumulator: self.accumulator,
+            touching: self.touching.clone(),
+            touching_terrain: self.touching_terrain.clone(),
+        }
+    }
+
+    /// Puts the world back the way it was when `snapshot` was taken. Events are cleared, and
+    /// recording stops.
+    pub fn restore(&mut self, snapshot: &PhysicsSnapshot) {
+        self.bodies = snapshot.bodies.clone();
+        self.next_handle = snapshot.next_handle;
+        self.timestep = snapshot.timestep;
+        self.accumulator = snapshot.accumulator;
+        self.touching = snapshot.touching.clone();
+        self.touching_terrain = snapshot.touching_terrain.clone();
+
+        self.broadphase = Broadphase::new();
+        for (&handle, body) in self.bodies.iter() {
+            self.broadphase.insert(handle, body.hitbox);
+        }
+
+        self.events.clear();
+        self.recording = None;
+        self.pending_forces.clear();
+    }
+
+    /// Starts recording the forces applied before each step, from a snapshot of the world as it is
+    /// now. Starts over if it was already recording.
+    pub fn start_recording(&mut self) {
+        self.pending_forces.clear();
+        self.recording = Some(Recording {
+            start: self.snapshot(),
+            ticks: Vec::new(),
+        });
+    }
+
+    /// Stops recording, returning what was recorded. Forces applied since the last step are left
+    /// out, since they haven't done anything yet.
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.pending_forces.clear();
+        self.recording.take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Plays a recording back over `terrain`, returning the world as it was after the last
+    /// recorded step. Events from every replayed step are kept.
+    pub fn replay<T: Terrain + ?Sized>(recording: &Recording, terrain: &T) -> Self {
+        let mut world = Self::new();
+        world.restore(&recording.start);
+
+        for forces in recording.ticks.iter() {
+            for &(handle, newtons) in forces.iter() {
+                world.apply_force(handle, newtons);
+            }
+            world.step(terrain);
+        }
+
+        world
+    }
+
+    /// Casts a ray from `origin` toward `direction` (which doesn't need to be a unit vector),
+    /// returning the first block or body it hits within `max_distance` meters.
+    ///
+    /// Bodies are found where they were after the last step, or where they were added; bodies
+    /// moved through `body_mut` since then are still found at their old positions.
+    pub fn raycast<T: Terrain + ?Sized>(
+        &self,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: &QueryFilter,
+        terrain: &T,
+    ) -> Option<QueryHit> {
+        let direction = direction.normalize();
+        if !direction.x.is_finite() || !direction.y.is_finite() || !direction.z.is_finite() {
+            return None;
+        }
+
+        let point = maths::AABB {
+            center_pos: origin,
+            half_size: Vector3::new(0.0, 0.0, 0.0),
+        };
+
+        let blocks = if filter.terrain {
+            raycast_terrain(origin, direction, max_distance, terrain)
+        } else {
+            None
+        };
+
+        closest(
+            blocks,
+            self.cast_among_bodies(&point, direction, max_distance, filter),
+        )
+    }
+
+    /// Sweeps `aabb` toward `direction` (which doesn't need to be a unit vector), returning the
+    /// first block or body it hits within `max_distance` meters. Anything the box overlaps to
+    /// begin with is ignored; use `overlap` to find those. Bodies are found the same way as in
+    /// `raycast`.
+    pub fn shape_cast<T: Terrain + ?Sized>(
+        &self,
+        aabb: &maths::AABB,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: &QueryFilter,
+        terrain: &T,
+    ) -> Option<QueryHit> {
+        let direction = direction.normalize();
+        if !direction.x.is_finite() || !direction.y.is_finite() || !direction.z.is_finite() {
+            return None;
+        }
+
+        let blocks = if filter.terrain {
+            shape_cast_terrain(aabb, direction, max_distance, terrain)
+        } else {
+            None
+        };
+
+        closest(
+            blocks,
+            self.cast_among_bodies(aabb, direction, max_distance, filter),
+        )
+    }
+
+    /// Returns every block and body that `aabb` overlaps, blocks first. Touching doesn't count.
+    pub fn overlap<T: Terrain + ?Sized>(
+        &self,
+        aabb: &maths::AABB,
+        filter: &QueryFilter,
+        terrain: &T,
+    ) -> Vec<HitTarget> {
+        let mut found: Vec<HitTarget> = if filter.terrain {
+            overlap_terrain(aabb, terrain)
+                .into_iter()
+                .map(HitTarget::Block)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        found.extend(
+            self.broadphase
+                .query(aabb)
+                .into_iter()
+                .filter(|&handle| self.passes(handle, filter))
+                .filter(|handle| self.bodies[handle].hitbox.collides_with(aabb))
+                .map(HitTarget::Body),
+        );
+
+        found
+    }
+
+    /// Sweeps `aabb` among the bodies that pass the filter. A box with no size is a ray, which
+    /// also hits bodies it starts inside of.
+    fn cast_among_bodies(
+        &self,
+        aabb: &maths::AABB,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<QueryHit> {
+        let displacement = direction * max_distance;
+        let is_ray = aabb.half_size == Vector3::new(0.0, 0.0, 0.0);
+
+        // a little bigger, so that bodies only touching the path (like one a ray starts on) are
+        // still found by the broadphase
+        let mut bounds = aabb.expanded_by(displacement);
+        bounds.half_size += Vector3::new(0.001, 0.001, 0.001);
+
+        self.broadphase
+            .query(&bounds)
+            .into_iter()
+            .filter(|&handle| self.passes(handle, filter))
+            .filter_map(|handle| {
+                let hitbox = &self.bodies[&handle].hitbox;
+
+                if is_ray && hitbox.collides_with(aabb) {
+                    return Some(QueryHit {
+                        target: HitTarget::Body(handle),
+                        distance: 0.0,
+                        normal: -direction,
+                    });
+                }
+
+                aabb.sweep(displacement, hitbox).map(|hit| QueryHit {
+                    target: HitTarget::Body(handle),
+                    distance: hit.time * max_distance,
+                    normal: hit.normal,
+                })
+            })
+            .fold(None, |best, hit| closest(best, Some(hit)))
+    }
+
+    /// Returns true if the filter lets scene queries find the body.
+    fn passes(&self, handle: BodyHandle, filter: &QueryFilter) -> bool {
+        filter.ignore != Some(handle) && self.bodies[&handle].layers.intersects(filter.layers)
+    }
+
+    /// Runs as many fixed steps as fit in the time passed since the last update, plus whatever was
+    /// left over from before. Returns how many steps were taken. Contact events from those steps
+    /// can be read with `events` until the next update.
+    ///
+    /// Forces applied before an update only push during its first step; see `update_with` for
+    /// pushing during every step.
+    pub fn update<T: Terrain + ?Sized>(&mut self, delta_sec: f32, terrain: &T) -> u32 {
+        self.update_with(delta_sec, terrain, |_| {})
+    }
+
+    /// Same as `update`, but calls `before_step` with the world before every step, for anything
+    /// that keeps pushing bodies around (like characters walking) to push once per step. An update
+    /// might take no steps at all, or several.
+    pub fn update_with<T, F>(&mut self, delta_sec: f32, terrain: &T, mut before_step: F) -> u32
+    where
+        T: Terrain + ?Sized,
+        F: FnMut(&mut Self),
+    {
+        self.events.clear();
+        self.accumulator += delta_sec.max(0.0);
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep {
+            if steps == MAX_STEPS_PER_UPDATE {
+                // too far behind; let the game slow down rather than freeze trying to catch up
+                self.accumulator = 0.0;
+                break;
+            }
+
+            before_step(self);
+            self.step(terrain);
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// Contact events from the steps taken by the last `update`.
+    pub fn events(&self) -> &[ContactEvent] {
+        &self.events
+    }
+
+    /// Moves every body forward by one timestep, then pushes apart any bodies that ended up
+    /// overlapping.
+    fn step<T: Terrain + ?Sized>(&mut self, terrain: &T) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording
+                .ticks
+                .push(std::mem::take(&mut self.pending_forces));
+        }
+
+        // bodies might have been moved through `body_mut` since the last step
+        for (&handle, body) in self.bodies.iter() {
+            self.broadphase.update(handle, body.hitbox);
+        }
+
+        let mut touching = BTreeSet::new();
+        let mut touching_terrain = BTreeSet::new();
+
+        let handles: Vec<BodyHandle> = self.bodies.keys().copied().collect();
+        for handle in handles {
+            // frozen bodies never move, so they're solid obstacles for everything else
+            let (obstacle_handles, obstacles): (Vec<BodyHandle>, Vec<maths::AABB>) = {
+                let body = &self.bodies[&handle];
+                if body.frozen {
+                    continue;
+                }
+
+                self.broadphase
+                    .query(&body.swept_bounds(self.timestep))
+                    .into_iter()
+                    .filter(|&other| other != handle && self.bodies[&other].frozen)
+                    .map(|other| (other, self.bodies[&other].hitbox))
+                    .unzip()
+            };
+
+            let body = self.bodies.get_mut(&handle).unwrap();
+            let contacts = body.physics_among(self.timestep, terrain, &obstacles);
+            self.broadphase.update(handle, body.hitbox);
+
+            for &contact in contacts.terrain.iter() {
+                touching_terrain.insert((handle, contact));
+            }
+            for &i in contacts.obstacles.iter() {
+                let other = obstacle_handles[i];
+                touching.insert((handle.min(other), handle.max(other)));
+            }
+        }
+
+        for &(body, contact) in touching_terrain.iter() {
+            if !self.touching_terrain.contains(&(body, contact)) {
+                self.events.push(ContactEvent::Terrain { body, contact });
+            }
+        }
+        self.touching_terrain = touching_terrain;
+
+        self.resolve_body_contacts(&mut touching);
+
+        for &(a, b) in touching.iter() {
+            if !self.touching.contains(&(a, b)) {
+                self.events.push(ContactEvent::Bodies { a, b });
+            }
+        }
+        self.touching = touching;
+    }
+
+    /// Pushes apart bodies that overlap, adding each overlapping pair to `touching`.
+    fn resolve_body_contacts(&mut self, touching: &mut BTreeSet<(BodyHandle, BodyHandle)>) {
+        for (a, b) in self.broadphase.pairs() {
+            let mut first = self.bodies.remove(&a).unwrap();
+            let second = self.bodies.get_mut(&b).unwrap();
+
+            if (!first.frozen || !second.frozen) && first.fix_collision(second) {
+                touching.insert((a, b));
+            }
+
+            self.broadphase.update(a, first.hitbox);
+            self.broadphase.update(b, second.hitbox);
+            self.bodies.insert(a, first);
+        }
+    }
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::{body_at, floor};
+    use crate::physics::{BlockIndex, Layers};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn nothing(_: BlockIndex) -> bool {
+        false
+    }
+
+    #[test]
+    fn steps_at_a_fixed_rate() {
+        let mut world = PhysicsWorld::new();
+
+        // a 144 Hz frame rate takes a step every two or three frames
+        let steps: u32 = (0..144).map(|_| world.update(1.0 / 144.0, &nothing)).sum();
+        assert!((59..=60).contains(&steps), "{}", steps);
+
+        // and a slow frame takes several steps at once
+        let mut slow = PhysicsWorld::new();
+        assert_eq!(slow.update(FIXED_TIMESTEP * 3.5, &nothing), 3);
+    }
+
+    #[test]
+    fn does_not_try_to_catch_up_forever() {
+        let mut world = PhysicsWorld::new();
+        assert_eq!(world.update(10.0, &nothing), MAX_STEPS_PER_UPDATE);
+        assert_eq!(world.update(0.0, &nothing), 0);
+    }
+
+    #[test]
+    fn same_motion_at_any_frame_rate() {
+        let fall = |frame_rate: f32| {
+            let mut world = PhysicsWorld::new();
+            let handle = world.add_body(body_at(0.0, 100.0, 0.0));
+            for _ in 0..frame_rate as usize {
+                world.update(1.0 / frame_rate, &nothing);
+            }
+            world.body(handle).unwrap().position().y
+        };
+
+        assert!((fall(30.0) - fall(240.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn forces_move_bodies() {
+        let mut world = PhysicsWorld::new();
+        let mut still_air = body_at(0.0, 0.3 + crate::physics::SKIN, 0.0);
+        still_air.set_drag(0.0);
+        still_air.set_air_resistance(0.0);
+        let handle = world.add_body(still_air);
+
+        world.apply_force(handle, Vector3::new(120.0, 0.0, 0.0));
+        world.update(FIXED_TIMESTEP, &floor);
+
+        let body = world.body(handle).unwrap();
+        assert!((body.velocity().x - 1.0).abs() < 1e-4);
+        assert!(body.position().x > 0.0);
+    }
+
+    #[test]
+    fn reports_landing_once() {
+        let mut world = PhysicsWorld::new();
+        let handle = world.add_body(body_at(0.0, 1.0, 0.0));
+
+        let mut landings = 0;
+        for _ in 0..120 {
+            world.update(FIXED_TIMESTEP, &floor);
+            landings += world
+                .events()
+                .iter()
+                .filter(|&&e| {
+                    e == ContactEvent::Terrain {
+                        body: handle,
+                        contact: SurfaceContact::Ground,
+                    }
+                })
+                .count();
+        }
+
+        assert_eq!(landings, 1);
+    }
+
+    #[test]
+    fn bodies_push_each_other_apart() {
+        let mut world = PhysicsWorld::new();
+        let a = world.add_body(body_at(0.0, 0.3 + crate::physics::SKIN, 0.0));
+        let b = world.add_body(body_at(0.3, 0.3 + crate::physics::SKIN, 0.0));
+
+        world.update(FIXED_TIMESTEP, &floor);
+
+        assert!(world.events().contains(&ContactEvent::Bodies { a, b }));
+
+        let (first, second) = (world.body(a).unwrap(), world.body(b).unwrap());
+        assert!(!first.hitbox().collides_with(second.hitbox()));
+        assert!(first.position().x < 0.0 && second.position().x > 0.3);
+    }
+
+    #[test]
+    fn frozen_bodies_are_solid() {
+        let mut world = PhysicsWorld::new();
+        let mut platform = PhysicalObject::new(
+            100.0,
+            maths::AABB {
+                center_pos: Vector3::new(0.0, -0.5, 0.0),
+                half_size: Vector3::new(5.0, 0.5, 5.0),
+            },
+        );
+        platform.set_frozen(true);
+
+        let platform = world.add_body(platform);
+        let body = world.add_body(body_at(0.0, 2.0, 0.0));
+
+        for _ in 0..120 {
+            world.update(FIXED_TIMESTEP, &nothing);
+        }
+
+        assert!(world.body(body).unwrap().on_ground());
+        assert!((world.body(body).unwrap().position().y - 0.3).abs() < 0.01);
+        assert!(world.touching.contains(&(platform, body)));
+        assert_eq!(
+            world.body(platform).unwrap().position(),
+            Vector3::new(0.0, -0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn moved_bodies_are_found_by_the_broadphase() {
+        let mut world = PhysicsWorld::new();
+        let a = world.add_body(body_at(0.0, 0.3 + crate::physics::SKIN, 0.0));
+        let b = world.add_body(body_at(50.0, 0.3 + crate::physics::SKIN, 0.0));
+
+        world
+            .body_mut(b)
+            .unwrap()
+            .set_position(Vector3::new(0.3, 0.3, 0.0));
+        world.update(FIXED_TIMESTEP, &floor);
+
+        assert!(world.events().contains(&ContactEvent::Bodies { a, b }));
+    }
+
+    #[test]
+    fn removed_bodies_stay_gone() {
+        let mut world = PhysicsWorld::new();
+        let first = world.add_body(body_at(0.0, 0.0, 0.0));
+
+        assert!(world.remove_body(first).is_some());
+        let second = world.add_body(body_at(0.0, 0.0, 0.0));
+
+        assert_ne!(first, second);
+        assert!(world.body(first).is_none());
+    }
+
+    fn query_world() -> (PhysicsWorld, BodyHandle, BodyHandle) {
+        let mut world = PhysicsWorld::new();
+        let chicken = world.add_body(body_at(3.0, 0.3 + crate::physics::SKIN, 0.1));
+
+        let mut item = body_at(1.5, 0.3 + crate::physics::SKIN, 0.1);
+        item.set_layers(Layers::layer(2));
+        let dropped = world.add_body(item);
+
+        (world, chicken, dropped)
+    }
+
+    #[test]
+    fn raycasts_find_the_closest_thing() {
+        let (world, chicken, dropped) = query_world();
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        let origin = Vector3::new(0.0, 0.3, 0.1);
+
+        let hit = world
+            .raycast(origin, x, 10.0, &QueryFilter::ALL, &floor)
+            .unwrap();
+        assert_eq!(hit.target, HitTarget::Body(dropped));
+        assert!((hit.distance - 1.3).abs() < 1e-4, "{}", hit.distance);
+        assert_eq!(hit.normal, -x);
+
+        // only looking for things on the default layer skips the dropped item
+        let chickens = QueryFilter::bodies(Layers::DEFAULT);
+        let past_item = world.raycast(origin, x * 5.0, 10.0, &chickens, &floor);
+        assert_eq!(past_item.map(|h| h.target), Some(HitTarget::Body(chicken)));
+
+        // looking down hits the floor, not any body
+        let down = world
+            .raycast(origin, -Vector3::unit_y(), 10.0, &QueryFilter::ALL, &floor)
+            .unwrap();
+        assert_eq!(down.target, HitTarget::Block((0, -1, 0)));
+
+        // a ray from inside of a body hits it right away, unless it's ignored
+        let inside = Vector3::new(3.0, 0.3, 0.1);
+        let from_chicken = world.raycast(inside, x, 10.0, &QueryFilter::ALL, &floor);
+        assert_eq!(from_chicken.map(|h| h.distance), Some(0.0));
+        let ignoring = QueryFilter::ALL.ignoring(chicken);
+        assert_eq!(world.raycast(inside, x, 10.0, &ignoring, &floor), None);
+
+        assert_eq!(
+            world.raycast(
+                origin,
+                Vector3::new(0.0, 0.0, 0.0),
+                10.0,
+                &QueryFilter::ALL,
+                &floor
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn shape_casts_stop_at_blocks_and_bodies() {
+        let (world, chicken, dropped) = query_world();
+        let x = Vector3::new(1.0, 0.0, 0.0);
+
+        // a box sitting on the floor slides along it into the dropped item
+        let sliding = maths::AABB {
+            center_pos: Vector3::new(0.0, 0.25 + crate::physics::SKIN, 0.1),
+            half_size: Vector3::new(0.25, 0.25, 0.25),
+        };
+        let hit = world
+            .shape_cast(&sliding, x, 10.0, &QueryFilter::ALL, &floor)
+            .unwrap();
+        assert_eq!(hit.target, HitTarget::Body(dropped));
+        assert!((hit.distance - 1.05).abs() < 1e-4, "{}", hit.distance);
+
+        let chickens = QueryFilter::ALL.ignoring(dropped);
+        let next = world
+            .shape_cast(&sliding, x, 10.0, &chickens, &floor)
+            .unwrap();
+        assert_eq!(next.target, HitTarget::Body(chicken));
+
+        // falling onto the floor
+        let falling = maths::AABB {
+            center_pos: Vector3::new(-2.0, 3.0, 0.1),
+            half_size: Vector3::new(0.25, 0.25, 0.25),
+        };
+        let landing = world
+            .shape_cast(
+                &falling,
+                -Vector3::unit_y(),
+                10.0,
+                &QueryFilter::ALL,
+                &floor,
+            )
+            .unwrap();
+        assert!(matches!(landing.target, HitTarget::Block(_)));
+        assert!((landing.distance - 2.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn overlaps_find_blocks_and_bodies() {
+        let (world, chicken, dropped) = query_world();
+
+        let region = maths::AABB {
+            center_pos: Vector3::new(2.0, 0.0, 0.1),
+            half_size: Vector3::new(1.2, 0.1, 0.1),
+        };
+        let found = world.overlap(&region, &QueryFilter::ALL, &floor);
+
+        assert!(found.contains(&HitTarget::Body(chicken)));
+        assert!(found.contains(&HitTarget::Body(dropped)));
+        assert!(found.contains(&HitTarget::Block((2, -1, 0))));
+
+        let bodies_only = world.overlap(&region, &QueryFilter::bodies(Layers::ALL), &floor);
+        assert_eq!(
+            bodies_only,
+            vec![HitTarget::Body(chicken), HitTarget::Body(dropped)]
+        );
+
+        assert_eq!(
+            world.overlap(&region, &QueryFilter::bodies(Layers::layer(2)), &nothing),
+            vec![HitTarget::Body(dropped)]
+        );
+    }
+
+    /// A crowd of bodies falling into a pit with a frozen pillar in the middle, so they pile up
+    /// and push each other around.
+    fn crowded_world() -> (PhysicsWorld, Vec<BodyHandle>) {
+        let mut world = PhysicsWorld::new();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let handles = (0..40)
+            .map(|_| {
+                let mut body = body_at(
+                    rng.gen::<f32>() * 4.0,
+                    1.0 + rng.gen::<f32>() * 6.0,
+                    rng.gen::<f32>() * 4.0,
+                );
+                body.set_restitution(rng.gen::<f32>());
+                world.add_body(body)
+            })
+            .collect();
+
+        let mut pillar = body_at(2.0, 1.0, 2.0);
+        pillar.set_frozen(true);
+        world.add_body(pillar);
+
+        (world, handles)
+    }
+
+    fn pit((i, j, k): BlockIndex) -> bool {
+        j < 0 || !(0..8).contains(&i) || !(0..8).contains(&k)
+    }
+
+    /// Runs the crowded world for a while, pushing bodies around at random.
+    fn shove_around(world: &mut PhysicsWorld, handles: &[BodyHandle], seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for _ in 0..240 {
+            for &handle in handles.iter() {
+                if rng.gen::<f32>() < 0.3 {
+                    let force = Vector3::new(
+                        rng.gen::<f32>() - 0.5,
+                        rng.gen::<f32>(),
+                        rng.gen::<f32>() - 0.5,
+                    );
+                    world.apply_force(handle, force * 400.0);
+                }
+            }
+
+            // uneven frame times, so some updates take no steps and some take several
+            world.update(rng.gen::<f32>() * 0.05, &pit);
+        }
+    }
+
+    #[test]
+    fn restoring_a_snapshot_repeats_the_same_steps() {
+        let (mut world, handles) = crowded_world();
+        shove_around(&mut world, &handles, 1);
+
+        let snapshot = world.snapshot();
+        shove_around(&mut world, &handles, 2);
+        let first_run = world.snapshot();
+
+        world.restore(&snapshot);
+        assert_eq!(world.snapshot(), snapshot);
+        shove_around(&mut world, &handles, 2);
+
+        assert_eq!(world.snapshot(), first_run);
+    }
+
+    #[test]
+    fn replaying_a_recording_ends_in_the_same_state() {
+        let (mut world, handles) = crowded_world();
+        shove_around(&mut world, &handles, 3);
+
+        world.start_recording();
+        shove_around(&mut world, &handles, 4);
+
+        // finish on a step, so none of the forces are left waiting for one
+        world.update(world.timestep(), &pit);
+        let recording = world.stop_recording().unwrap();
+        assert!(!world.is_recording());
+        assert!(recording.len() > 100);
+
+        let replayed = PhysicsWorld::replay(&recording, &pit);
+        for (handle, body) in world.bodies() {
+            let other = replayed.body(handle).unwrap();
+            for axis in 0..3 {
+                assert_eq!(
+                    body.position()[axis].to_bits(),
+                    other.position()[axis].to_bits()
+                );
+                assert_eq!(
+                    body.velocity()[axis].to_bits(),
+                    other.velocity()[axis].to_bits()
+                );
+            }
+            assert_eq!(body, other);
+        }
+
+        // replaying only part of it ends somewhere else
+        let mut shorter = recording.clone();
+        shorter.truncate(recording.len() / 2);
+        let halfway = PhysicsWorld::replay(&shorter, &pit);
+        assert!(handles
+            .iter()
+            .any(|&h| halfway.body(h).unwrap() != world.body(h).unwrap()));
+    }
+}
@@ -1,7 +1,7 @@
 #version 450
 
 layout(location=0) in vec2 v_tex_coords;
-layout(location=1) flat in float v_layer;
+layout(location=1) flat in uint v_layer;
 
 layout(location=0) out vec4 f_color;
 
@@ -9,9 +9,19 @@ layout(location=0) out vec4 f_color;
 // set=0 corresponds to the first parameter in set_bind_group.
 // binding=0 relates the binding specified when creating the BindGroupLayout
 // and BindGroup.
-layout(set=0, binding=0) uniform texture3D t_diffuse;
+layout(set=0, binding=0) uniform texture2DArray t_diffuse;
 layout(set=0, binding=1) uniform sampler s_diffuse;
 
 void main() {
-    f_color = texture(sampler3D(t_diffuse, s_diffuse), vec3(v_tex_coords, v_layer));
+    // the layer is an exact index, so there's no filtering between neighboring textures
+    vec4 color = texture(sampler2DArray(t_diffuse, s_diffuse), vec3(v_tex_coords, float(v_layer)));
+
+    // cutouts like leaves are thrown away below ALPHA_CUTOFF, which is defined when the shader is
+    // compiled (src/engine/texture/mipmap.rs) and which the mip chain is made to keep coverage
+    // around
+    if (color.a < ALPHA_CUTOFF) {
+        discard;
+    }
+
+    f_color = color;
 }
@@ -10,7 +10,7 @@ uniform Uniforms {
 };
 
 layout(location=0) out vec2 v_tex_coords;
-layout(location=1) out uint v_layer;
+layout(location=1) flat out uint v_layer;
 
 void main() {
     v_tex_coords = a_tex_coords;
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) flat in uint v_layer;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2DArray t_sprite;
+layout(set=0, binding=1) uniform sampler s_sprite;
+
+void main() {
+    vec4 color = texture(sampler2DArray(t_sprite, s_sprite), vec3(v_tex_coords, float(v_layer)));
+
+    // see-through pixels are thrown away instead of blended, so they don't write depth and sprites
+    // don't need sorting
+    if (color.a < ALPHA_CUTOFF) {
+        discard;
+    }
+
+    f_color = color;
+}
@@ -0,0 +1,47 @@
+#version 450
+
+// the corners of a quad one unit wide and tall, standing on its bottom middle, as two triangles
+const vec2 CORNERS[6] = vec2[6](
+    vec2(-0.5, 0.0), vec2(0.5, 0.0), vec2(0.5, 1.0),
+    vec2(-0.5, 0.0), vec2(0.5, 1.0), vec2(-0.5, 1.0)
+);
+
+// must match the flags in SpriteInstance
+const uint FLIPPED = 1u;
+const uint AXIS_LOCKED = 2u;
+
+// one of each per sprite, not per vertex
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec2 a_size;
+layout(location=2) in uint a_layer;
+layout(location=3) in uint a_flags;
+
+layout(set=1, binding=0) 
+uniform Uniforms {
+    mat4 u_view_proj;
+    vec4 u_camera_right;
+    vec4 u_camera_up;
+};
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) flat out uint v_layer;
+
+void main() {
+    vec2 corner = CORNERS[gl_VertexIndex];
+
+    // axis-locked sprites stand straight up in the world instead of leaning back with the camera
+    vec3 up = (a_flags & AXIS_LOCKED) != 0u ? vec3(0.0, 1.0, 0.0) : u_camera_up.xyz;
+    vec3 position = a_position
+        + u_camera_right.xyz * (corner.x * a_size.x)
+        + up * (corner.y * a_size.y);
+
+    // images start at the top left
+    float u = corner.x + 0.5;
+    if ((a_flags & FLIPPED) != 0u) {
+        u = 1.0 - u;
+    }
+
+    v_tex_coords = vec2(u, 1.0 - corner.y);
+    v_layer = a_layer;
+    gl_Position = u_view_proj * vec4(position, 1.0);
+}
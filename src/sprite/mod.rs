@@ -0,0 +1,309 @@
+pub mod render;
+
+pub use render::*;
+
+use image::{GenericImageView, RgbaImage};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// SpriteSheet is a strip of animation frames laid side by side in one image, first frame on the
+/// left, like the chicken images in `assets/images/chicken`. Every frame is the same width.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    frames: Vec<RgbaImage>,
+}
+
+impl SpriteSheet {
+    /// Slices `image` into `frame_count` frames. A frame count of 0 is treated as 1, so still
+    /// images can say they have no animation frames.
+    pub fn from_image(image: RgbaImage, frame_count: u32) -> Result<Self, SpriteSheetError> {
+        let frame_count = frame_count.max(1);
+        let (width, height) = image.dimensions();
+
+        if width == 0 || width % frame_count != 0 {
+            return Err(SpriteSheetError::Frames { width, frame_count });
+        }
+
+        let frame_width = width / frame_count;
+        let frames = (0..frame_count)
+            .map(|frame| {
+                image
+                    .view(frame * frame_width, 0, frame_width, height)
+                    .to_image()
+            })
+            .collect();
+
+        Ok(Self { frames })
+    }
+
+    /// Decodes an image file's bytes and slices it into `frame_count` frames.
+    pub fn from_memory(bytes: &[u8], frame_count: u32) -> Result<Self, SpriteSheetError> {
+        let image = image::load_from_memory(bytes).map_err(SpriteSheetError::Image)?;
+        Self::from_image(image.to_rgba(), frame_count)
+    }
+
+    pub fn frames(&self) -> &[RgbaImage] {
+        &self.frames
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The size of a single frame, in pixels.
+    pub fn frame_dimensions(&self) -> (u32, u32) {
+        self.frames[0].dimensions()
+    }
+}
+
+#[derive(Debug)]
+pub enum SpriteSheetError {
+    /// The image couldn't be decoded.
+    Image(image::ImageError),
+
+    /// The image can't be split evenly into that many frames.
+    Frames { width: u32, frame_count: u32 },
+}
+
+impl Display for SpriteSheetError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Image(e) => write!(f, "couldn't load sprite sheet: {}", e),
+            Self::Frames { width, frame_count } => write!(
+                f,
+                "a sprite sheet {} pixels wide can't be split into {} frames",
+                width, frame_count
+            ),
+        }
+    }
+}
+
+impl Error for SpriteSheetError {}
+
+/// How a Sprite plays its frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Playback {
+    /// Starts over from the first frame after the last one.
+    Loop,
+
+    /// Stops on the last frame.
+    Once,
+}
+
+/// Animation describes one of a character's animations: the sprite sheet it's drawn from and how
+/// its frames are played. Sheets are built into the game.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Animation {
+    /// The sheet's name, unique among every animation's sheet, for telling sheets apart once
+    /// they're loaded.
+    pub name: &'static str,
+
+    /// The sheet's image file.
+    pub image: &'static [u8],
+
+    pub frame_count: u32,
+
+    /// How long each frame is shown, in seconds.
+    pub seconds_per_frame: f32,
+
+    pub playback: Playback,
+}
+
+impl Animation {
+    /// Loads and slices the animation's sheet.
+    pub fn sheet(&self) -> Result<SpriteSheet, SpriteSheetError> {
+        SpriteSheet::from_memory(self.image, self.frame_count)
+    }
+
+    /// Returns a Sprite that plays the animation from the start.
+    pub fn sprite(&self) -> Sprite {
+        Sprite::new(self.frame_count, self.seconds_per_frame, self.playback)
+    }
+}
+
+/// Sprite picks which frame of an animation to show as time passes. It only keeps track of time,
+/// not images, so every character can have its own without copying sheets around.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    /// How long each frame is shown, in seconds.
+    frame_durations: Vec<f32>,
+    playback: Playback,
+
+    /// How far into the animation we are, in seconds.
+    time: f32,
+}
+
+impl Sprite {
+    /// Makes a Sprite showing each of `frame_count` frames for the same time. Like sheets, 0
+    /// frames is treated as 1.
+    pub fn new(frame_count: u32, seconds_per_frame: f32, playback: Playback) -> Self {
+        let frame_count = frame_count.max(1) as usize;
+        Self::with_frame_durations(vec![seconds_per_frame; frame_count], playback)
+    }
+
+    /// Makes a Sprite that shows each frame for its own time, in seconds. Frames with no time (or
+    /// less) are skipped over.
+    pub fn with_frame_durations(frame_durations: Vec<f32>, playback: Playback) -> Self {
+        let mut frame_durations: Vec<f32> = frame_durations
+            .into_iter()
+            .map(|d| if d > 0.0 { d } else { 0.0 })
+            .collect();
+        if frame_durations.is_empty() {
+            frame_durations.push(0.0);
+        }
+
+        Self {
+            frame_durations,
+            playback,
+            time: 0.0,
+        }
+    }
+
+    /// Moves the animation forward by `delta` seconds.
+    pub fn animate(&mut self, delta: f32) {
+        let total = self.total_duration();
+        if total <= 0.0 || delta.is_nan() || delta <= 0.0 {
+            return;
+        }
+
+        self.time = match self.playback {
+            Playback::Loop => (self.time + delta) % total,
+            Playback::Once => (self.time + delta).min(total),
+        };
+    }
+
+    /// Goes back to the first frame.
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    /// Returns the index of the frame to show.
+    pub fn frame(&self) -> usize {
+        if self.is_finished() {
+            return self.frame_durations.len() - 1;
+        }
+
+        let mut start = 0.0;
+        for (frame, &duration) in self.frame_durations.iter().enumerate() {
+            start += duration;
+            if self.time < start {
+                return frame;
+            }
+        }
+
+        self.frame_durations.len() - 1
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_durations.len()
+    }
+
+    /// Returns true once a Sprite that plays once has reached its end. Looping Sprites never
+    /// finish.
+    pub fn is_finished(&self) -> bool {
+        self.playback == Playback::Once && self.time >= self.total_duration()
+    }
+
+    fn total_duration(&self) -> f32 {
+        self.frame_durations.iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// A strip of `frames` frames, each 3x2 pixels and filled with its own shade of red.
+    fn strip(frames: u32) -> RgbaImage {
+        RgbaImage::from_fn(frames * 3, 2, |x, _| Rgba([(x / 3) as u8 * 50, 0, 0, 255]))
+    }
+
+    #[test]
+    fn slices_strips_into_frames() {
+        let sheet = SpriteSheet::from_image(strip(4), 4).unwrap();
+
+        assert_eq!(sheet.frame_count(), 4);
+        assert_eq!(sheet.frame_dimensions(), (3, 2));
+        for (i, frame) in sheet.frames().iter().enumerate() {
+            assert!(frame.pixels().all(|p| p[0] == i as u8 * 50));
+        }
+
+        // still images
+        assert_eq!(
+            SpriteSheet::from_image(strip(1), 0).unwrap().frame_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn frames_have_to_fit_evenly() {
+        match SpriteSheet::from_image(strip(4), 5) {
+            Err(SpriteSheetError::Frames { width, frame_count }) => {
+                assert_eq!((width, frame_count), (12, 5))
+            }
+            other => panic!("expected a frame error, got {:?}", other.map(|_| ())),
+        }
+
+        assert!(SpriteSheet::from_memory(b"not a png", 1).is_err());
+    }
+
+    #[test]
+    fn loops() {
+        let mut sprite = Sprite::new(4, 0.2, Playback::Loop);
+        assert_eq!(sprite.frame(), 0);
+
+        sprite.animate(0.25);
+        assert_eq!(sprite.frame(), 1);
+
+        sprite.animate(0.6);
+        assert_eq!(sprite.frame(), 0);
+        assert!(!sprite.is_finished());
+
+        // a long pause doesn't take long to catch up on
+        sprite.animate(1e6 + 0.1);
+        assert!(sprite.frame() < 4);
+    }
+
+    #[test]
+    fn plays_once() {
+        let mut sprite = Sprite::new(3, 0.1, Playback::Once);
+
+        sprite.animate(0.15);
+        assert_eq!(sprite.frame(), 1);
+        assert!(!sprite.is_finished());
+
+        sprite.animate(10.0);
+        assert_eq!(sprite.frame(), 2);
+        assert!(sprite.is_finished());
+
+        sprite.restart();
+        assert_eq!(sprite.frame(), 0);
+        assert!(!sprite.is_finished());
+    }
+
+    #[test]
+    fn frames_can_have_their_own_timing() {
+        let mut sprite = Sprite::with_frame_durations(vec![0.5, 0.1, 0.0, 0.2], Playback::Loop);
+
+        sprite.animate(0.45);
+        assert_eq!(sprite.frame(), 0);
+        sprite.animate(0.1);
+        assert_eq!(sprite.frame(), 1);
+
+        // the frame with no time is never shown
+        sprite.animate(0.1);
+        assert_eq!(sprite.frame(), 3);
+        sprite.animate(0.2);
+        assert_eq!(sprite.frame(), 0);
+    }
+
+    #[test]
+    fn still_sprites_stay_put() {
+        let mut sprite = Sprite::new(0, 0.0, Playback::Loop);
+        sprite.animate(1.0);
+
+        assert_eq!(sprite.frame_count(), 1);
+        assert_eq!(sprite.frame(), 0);
+    }
+}
@@ -0,0 +1,414 @@
+use super::Animation;
+use crate::engine;
+use cgmath::Vector3;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// How a sprite turns to face the camera.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Billboard {
+    /// Faces the camera straight on, even when it's looked at from above or below.
+    CameraFacing,
+
+    /// Stands straight up, only turning around the vertical axis, so characters stay on their feet
+    /// when the camera looks down at them.
+    AxisLocked,
+}
+
+/// SpriteInstance is one sprite to draw. Every sprite is the same quad, so instead of vertices
+/// each sprite only needs where it goes and what it shows.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpriteInstance {
+    /// Where the bottom middle of the sprite goes.
+    pub position: [f32; 3],
+
+    /// How wide and tall the sprite is, in meters.
+    pub size: [f32; 2],
+
+    /// Which layer of the sprite texture array to draw.
+    pub texture_layer: u32,
+
+    /// FLIPPED and AXIS_LOCKED.
+    flags: u32,
+}
+
+impl SpriteInstance {
+    pub const SIZE: u64 = std::mem::size_of::<Self>() as wgpu::BufferAddress;
+
+    // must match the flags in sprite.vert
+    const FLIPPED: u32 = 1;
+    const AXIS_LOCKED: u32 = 1 << 1;
+
+    /// Makes a sprite standing at `position`. Flipped sprites are mirrored left to right, for
+    /// characters facing the other way.
+    pub fn new(
+        position: Vector3<f32>,
+        size: (f32, f32),
+        texture_layer: u32,
+        billboard: Billboard,
+        flipped: bool,
+    ) -> Self {
+        let mut flags = 0;
+        if flipped {
+            flags |= Self::FLIPPED;
+        }
+        if billboard == Billboard::AxisLocked {
+            flags |= Self::AXIS_LOCKED;
+        }
+
+        Self {
+            position: position.into(),
+            size: [size.0, size.1],
+            texture_layer,
+            flags,
+        }
+    }
+
+    pub fn billboard(&self) -> Billboard {
+        if self.flags & Self::AXIS_LOCKED != 0 {
+            Billboard::AxisLocked
+        } else {
+            Billboard::CameraFacing
+        }
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.flags & Self::FLIPPED != 0
+    }
+
+    pub fn vertex_buffer_descriptors<'a>() -> &'a [wgpu::VertexBufferDescriptor<'a>] {
+        use std::mem::size_of;
+
+        &[wgpu::VertexBufferDescriptor {
+            stride: Self::SIZE,
+
+            // moves on to the next sprite after each quad, not after each vertex
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float2,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Uint,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: (size_of::<[f32; 5]>() + size_of::<u32>()) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Uint,
+                },
+            ],
+        }]
+    }
+}
+
+unsafe impl bytemuck::Pod for SpriteInstance {}
+unsafe impl bytemuck::Zeroable for SpriteInstance {}
+
+/// SpriteLayers decides which layer of the sprite texture array holds each frame of each
+/// animation. An animation's frames take up layers one after another.
+#[derive(Debug, Clone, Default)]
+pub struct SpriteLayers {
+    /// The layer of each animation's first frame, and how many frames it has.
+    animations: HashMap<&'static str, (u32, u32)>,
+    count: u32,
+}
+
+impl SpriteLayers {
+    /// Lays out every animation's frames. Animations with the same name share their sheet, so
+    /// it's only given layers once.
+    pub fn new(animations: &[Animation]) -> Self {
+        let mut layers = Self::default();
+
+        for animation in animations {
+            if !layers.animations.contains_key(animation.name) {
+                let frame_count = animation.frame_count.max(1);
+                layers
+                    .animations
+                    .insert(animation.name, (layers.count, frame_count));
+                layers.count += frame_count;
+            }
+        }
+
+        layers
+    }
+
+    /// Returns the layer holding `frame` of the animation, or None if the animation wasn't laid
+    /// out or doesn't have that many frames.
+    pub fn layer(&self, animation: &Animation, frame: usize) -> Option<u32> {
+        let &(first, frame_count) = self.animations.get(animation.name)?;
+
+        if frame < frame_count as usize {
+            Some(first + frame as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Returns how many layers there are in all.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// SpriteTextures holds every frame of every character animation in one texture array, so that
+/// sprites showing any of them can be drawn together in one go.
+pub struct SpriteTextures {
+    pub textures: engine::TextureArray,
+    bind_group: wgpu::BindGroup,
+    layers: SpriteLayers,
+}
+
+impl SpriteTextures {
+    /// Sprites are small pixel art, so they're kept crisp at every distance.
+    const FILTERS: engine::FilterSettings = engine::FilterSettings {
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+    };
+
+    /// Loads every frame of every animation. Every layer of the texture array is the same size,
+    /// so frames are scaled to the size of the largest one. `bind_group_layout` is the same kind
+    /// of layout block textures use: a 2D array texture and its sampler.
+    pub fn new(
+        device: &wgpu::Device,
+        animations: &[Animation],
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<(Self, Vec<wgpu::CommandBuffer>), Box<dyn Error>> {
+        let layers = SpriteLayers::new(animations);
+
+        let mut sheets = Vec::new();
+        let mut loaded = HashSet::new();
+        for animation in animations {
+            if loaded.insert(animation.name) {
+                sheets.push((animation, animation.sheet()?));
+            }
+        }
+
+        let texture_dimensions = sheets.iter().fold((1, 1), |(w, h), (_, sheet)| {
+            let (frame_width, frame_height) = sheet.frame_dimensions();
+            (w.max(frame_width), h.max(frame_height))
+        });
+
+        let mut textures = engine::TextureArray::new(
+            device,
+            texture_dimensions,
+            layers.count().max(1),
+            Self::FILTERS,
+            Some("sprite textures"),
+        );
+
+        let mut commands = Vec::new();
+        for (animation, sheet) in sheets {
+            for (frame, image) in sheet.frames().iter().enumerate() {
+                let image = if image.dimensions() == texture_dimensions {
+                    image.clone()
+                } else {
+                    let (width, height) = texture_dimensions;
+                    image::imageops::resize(image, width, height, image::FilterType::Nearest)
+                };
+
+                // laid out by the same animations, so every frame has a layer
+                let layer = layers.layer(animation, frame).unwrap_or_default();
+                commands.push(textures.set_layer_from_image(
+                    device,
+                    layer,
+                    image::DynamicImage::ImageRgba8(image),
+                )?);
+            }
+        }
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&textures.view),
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&textures.sampler),
+                },
+            ],
+            label: Some("sprite texture bind group"),
+        });
+
+        Ok((
+            Self {
+                textures,
+                bind_group,
+                layers,
+            },
+            commands,
+        ))
+    }
+
+    pub fn get_bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn layers(&self) -> &SpriteLayers {
+        &self.layers
+    }
+}
+
+/// SpriteBatch collects the sprites to draw each frame, then draws them all with a single
+/// instanced draw call.
+#[derive(Default)]
+pub struct SpriteBatch {
+    instances: Vec<SpriteInstance>,
+
+    /// Holds the instances as of the last `upload`.
+    buffer: Option<wgpu::Buffer>,
+    uploaded_count: usize,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Forgets every sprite, ready for the next frame. What was uploaded is still drawn until the
+    /// next `upload`.
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    pub fn push(&mut self, instance: SpriteInstance) {
+        self.instances.push(instance);
+    }
+
+    pub fn instances(&self) -> &[SpriteInstance] {
+        &self.instances
+    }
+
+    /// Copies the sprites to the GPU for `render`.
+    pub fn upload(&mut self, device: &wgpu::Device) {
+        self.uploaded_count = self.instances.len();
+        self.buffer = if self.instances.is_empty() {
+            None
+        } else {
+            Some(device.create_buffer_with_data(
+                bytemuck::cast_slice(&self.instances),
+                wgpu::BufferUsage::VERTEX,
+            ))
+        };
+    }
+
+    /// Draws every uploaded sprite. The render pass needs to be using the pipeline from
+    /// `make_sprite_render_pipeline`.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if let Some(buffer) = &self.buffer {
+            render_pass.set_vertex_buffer(
+                0,
+                buffer,
+                0,
+                self.uploaded_count as u64 * SpriteInstance::SIZE,
+            );
+
+            // the six corners of the quad come from the vertex shader
+            render_pass.draw(0..6, 0..self.uploaded_count as u32);
+        }
+    }
+}
+
+/// Makes the pipeline that draws SpriteBatches. Sprites are depth tested, so terrain in front of
+/// them hides them.
+pub fn make_sprite_render_pipeline(
+    engine: &mut crate::engine::Engine,
+    sprite_texture_bind_group_layout: &wgpu::BindGroupLayout,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Result<wgpu::RenderPipeline, Box<dyn Error>> {
+    // transparent pixels are discarded in the shader, so there's nothing to blend
+    let color_states = [wgpu::ColorStateDescriptor {
+        format: engine.get_swap_chain_descriptor().format,
+        color_blend: wgpu::BlendDescriptor::REPLACE,
+        alpha_blend: wgpu::BlendDescriptor::REPLACE,
+        write_mask: wgpu::ColorWrite::ALL,
+    }];
+
+    let (vs_module, fs_module) = engine.compile_shader_modules(
+        include_str!("../shaders/sprite.vert"),
+        &engine::texture::mipmap::with_alpha_cutoff(include_str!("../shaders/sprite.frag")),
+    )?;
+
+    let render_pipeline_layout =
+        engine
+            .get_device()
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                bind_group_layouts: &[
+                    sprite_texture_bind_group_layout,
+                    uniform_bind_group_layout,
+                ],
+            });
+
+    let sprite_render_pipeline_descriptor = crate::utils::make_render_pipeline_descriptor(
+        &render_pipeline_layout,
+        &vs_module,
+        &fs_module,
+        &color_states,
+        SpriteInstance::vertex_buffer_descriptors(),
+        true,
+    );
+
+    Ok(engine
+        .get_device()
+        .create_render_pipeline(&sprite_render_pipeline_descriptor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::Playback;
+
+    fn animation(name: &'static str, frame_count: u32) -> Animation {
+        Animation {
+            name,
+            image: &[],
+            frame_count,
+            seconds_per_frame: 0.1,
+            playback: Playback::Loop,
+        }
+    }
+
+    #[test]
+    fn frames_get_their_own_layers() {
+        let walk = animation("walk", 4);
+        let stand = animation("stand", 1);
+        let layers = SpriteLayers::new(&[walk, stand, walk]);
+
+        assert_eq!(layers.count(), 5);
+        assert_eq!(layers.layer(&walk, 0), Some(0));
+        assert_eq!(layers.layer(&walk, 3), Some(3));
+        assert_eq!(layers.layer(&stand, 0), Some(4));
+
+        assert_eq!(layers.layer(&walk, 4), None);
+        assert_eq!(layers.layer(&animation("fly", 2), 0), None);
+    }
+
+    #[test]
+    fn instances_remember_how_to_turn() {
+        let position = Vector3::new(1.0, 2.0, 3.0);
+
+        let upright = SpriteInstance::new(position, (0.5, 1.0), 2, Billboard::AxisLocked, true);
+        assert_eq!(upright.billboard(), Billboard::AxisLocked);
+        assert!(upright.is_flipped());
+        assert_eq!(upright.position, [1.0, 2.0, 3.0]);
+
+        let facing = SpriteInstance::new(position, (0.5, 1.0), 2, Billboard::CameraFacing, false);
+        assert_eq!(facing.billboard(), Billboard::CameraFacing);
+        assert!(!facing.is_flipped());
+
+        // the shader reads the flags right after the layer
+        assert_eq!(SpriteInstance::SIZE, 4 * 7);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::engine::texture::mipmap;
+use image::{GenericImageView, RgbaImage};
+
+/// How long each frame of an animated block texture is shown, in seconds, if its definition
+/// doesn't say.
+pub const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+/// FrameStrip is a block texture split into its animation frames. Animated textures are square
+/// frames stacked top to bottom in one image, like a film strip; the first frame is at the top.
+/// Anything else (including a square image) is a single frame that never changes.
+#[derive(Debug, Clone)]
+pub struct FrameStrip {
+    frames: Vec<RgbaImage>,
+
+    /// In seconds.
+    frame_duration: f32,
+}
+
+impl FrameStrip {
+    pub fn from_image(image: RgbaImage, frame_duration: f32) -> Self {
+        let (width, height) = image.dimensions();
+
+        let frames = if width > 0 && height > width && height % width == 0 {
+            (0..height / width)
+                .map(|frame| image.view(0, frame * width, width, width).to_image())
+                .collect()
+        } else {
+            vec![image]
+        };
+
+        Self {
+            frames,
+            frame_duration,
+        }
+    }
+
+    pub fn frames(&self) -> &[RgbaImage] {
+        &self.frames
+    }
+
+    pub fn frame_duration(&self) -> f32 {
+        self.frame_duration
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    /// The size of a single frame.
+    pub fn frame_dimensions(&self) -> (u32, u32) {
+        self.frames[0].dimensions()
+    }
+
+    /// Scales every frame to `dimensions`. Nearest keeps pixel art crisp.
+    pub fn resized(self, dimensions: (u32, u32)) -> Self {
+        if self.frame_dimensions() == dimensions {
+            return self;
+        }
+
+        let (width, height) = dimensions;
+        let frames = self
+            .frames
+            .iter()
+            .map(|f| image::imageops::resize(f, width, height, image::FilterType::Nearest))
+            .collect();
+
+        Self {
+            frames,
+            frame_duration: self.frame_duration,
+        }
+    }
+}
+
+/// AnimatedLayer is a layer of the block texture array that shows an animation. Chunk meshes only
+/// know the layer, so instead of re-meshing we copy each new frame over the layer when it's due.
+/// Mip chains for every frame are made up front so that changing frames is just an upload.
+pub struct AnimatedLayer {
+    layer: u32,
+    mip_chains: Vec<Vec<RgbaImage>>,
+    frame_duration: f32,
+
+    /// Seconds into the animation, always less than one full loop.
+    elapsed: f32,
+    shown_frame: usize,
+}
+
+impl AnimatedLayer {
+    /// Starts the animation on its first frame.
+    pub fn new(layer: u32, strip: &FrameStrip) -> Self {
+        Self {
+            layer,
+            mip_chains: strip
+                .frames()
+                .iter()
+                .map(mipmap::generate_mip_chain)
+                .collect(),
+            // a zero duration would divide by zero; treat it as changing every update
+            frame_duration: strip.frame_duration().max(f32::EPSILON),
+            elapsed: 0.0,
+            shown_frame: 0,
+        }
+    }
+
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.mip_chains.len()
+    }
+
+    pub fn shown_frame(&self) -> usize {
+        self.shown_frame
+    }
+
+    /// The mip chain to upload for a frame.
+    pub fn mip_chain(&self, frame: usize) -> &[RgbaImage] {
+        &self.mip_chains[frame]
+    }
+
+    /// Moves the animation along. Returns the new frame if it's different from the one shown
+    /// before, meaning the layer needs to be uploaded again.
+    pub fn advance(&mut self, delta_sec: f32) -> Option<usize> {
+        let loop_duration = self.frame_duration * self.frame_count() as f32;
+
+        // wrapping keeps the time small, so it doesn't lose precision after running for hours
+        self.elapsed = (self.elapsed + delta_sec.max(0.0)) % loop_duration;
+
+        let frame = ((self.elapsed / self.frame_duration) as usize).min(self.frame_count() - 1);
+        if frame == self.shown_frame {
+            None
+        } else {
+            self.shown_frame = frame;
+            Some(frame)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// A strip of `frames` 4x4 frames, each filled with its frame number.
+    fn strip(frames: u32) -> RgbaImage {
+        RgbaImage::from_fn(4, 4 * frames, |_, y| Rgba([(y / 4) as u8, 0, 0, 255]))
+    }
+
+    #[test]
+    fn splits_vertical_strips_top_to_bottom() {
+        let s = FrameStrip::from_image(strip(3), 0.5);
+
+        assert!(s.is_animated());
+        assert_eq!(s.frames().len(), 3);
+        assert_eq!(s.frame_dimensions(), (4, 4));
+        for (i, frame) in s.frames().iter().enumerate() {
+            assert!(frame.pixels().all(|p| p[0] == i as u8));
+        }
+    }
+
+    #[test]
+    fn other_shapes_are_one_frame() {
+        assert!(!FrameStrip::from_image(RgbaImage::new(16, 16), 0.1).is_animated());
+        assert!(!FrameStrip::from_image(RgbaImage::new(16, 24), 0.1).is_animated());
+        assert!(!FrameStrip::from_image(RgbaImage::new(32, 16), 0.1).is_animated());
+    }
+
+    #[test]
+    fn resizes_every_frame() {
+        let s = FrameStrip::from_image(strip(2), 0.1).resized((8, 8));
+
+        assert_eq!(s.frames().len(), 2);
+        assert!(s.frames().iter().all(|f| f.dimensions() == (8, 8)));
+    }
+
+    #[test]
+    fn advances_frames_and_loops() {
+        let mut layer = AnimatedLayer::new(2, &FrameStrip::from_image(strip(3), 0.25));
+        assert_eq!(layer.shown_frame(), 0);
+
+        // not long enough for the next frame
+        assert_eq!(layer.advance(0.1), None);
+        assert_eq!(layer.advance(0.2), Some(1));
+        assert_eq!(layer.advance(0.25), Some(2));
+
+        // wraps back around to the first frame
+        assert_eq!(layer.advance(0.25), Some(0));
+
+        // skipping several frames at once lands on the right one
+        assert_eq!(layer.advance(0.25 * 3.0 + 0.3), Some(1));
+        assert_eq!(layer.layer(), 2);
+    }
+}
@@ -1,22 +1,71 @@
 #![allow(dead_code)]
 
+mod animation;
+mod pack;
+
+pub use animation::*;
+pub use pack::*;
+
 use crate::blocks::BlockType;
 use crate::engine;
 use crate::world::Direction;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+const BLOCK_TEXTURE_COUNT: u32 = BlockTextureIndex::ALL.len() as u32;
 
-const BLOCK_TEXTURE_COUNT: u32 = 4;
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum BlockTextureIndex {
     Dirt,
     Grass,
     Stone,
     Sand,
+    Ice,
+    Mud,
 }
 
 impl BlockTextureIndex {
+    /// Every block texture, in layer order.
+    pub const ALL: [BlockTextureIndex; 6] = [
+        Self::Dirt,
+        Self::Grass,
+        Self::Stone,
+        Self::Sand,
+        Self::Ice,
+        Self::Mud,
+    ];
+
+    /// The name of the texture in resource pack manifests.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dirt => "dirt",
+            Self::Grass => "grass",
+            Self::Stone => "stone",
+            Self::Sand => "sand",
+            Self::Ice => "ice",
+            Self::Mud => "mud",
+        }
+    }
+
+    /// The texture built into the game, used when a resource pack doesn't have one.
+    fn default_image_bytes(self) -> &'static [u8] {
+        match self {
+            Self::Dirt => include_bytes!("../../assets/images/blocks/dirt.png"),
+            Self::Grass => include_bytes!("../../assets/images/blocks/grass.png"),
+            Self::Stone => include_bytes!("../../assets/images/blocks/stone.png"),
+            Self::Sand => include_bytes!("../../assets/images/blocks/sand.png"),
+            Self::Ice => include_bytes!("../../assets/images/blocks/ice.png"),
+            Self::Mud => include_bytes!("../../assets/images/blocks/mud.png"),
+        }
+    }
+
+    fn default_image(self) -> Result<image::DynamicImage, MakeTextureError> {
+        image::load_from_memory(self.default_image_bytes())
+            .map_err(|e| MakeTextureError::new(self.name(), e))
+    }
+
     pub fn from_type_and_direction(
         ty: BlockType,
         direction: Direction,
@@ -34,6 +83,8 @@ impl BlockTextureIndex {
             BlockType::Dirt => Self::Dirt,
             BlockType::Grass => Self::Grass,
             BlockType::Stone => Self::Stone,
+            BlockType::Ice => Self::Ice,
+            BlockType::Mud => Self::Mud,
             _ => return Err(NoSuchBlockTextureError { for_type: ty }),
         })
     }
@@ -44,6 +95,8 @@ impl BlockTextureIndex {
             BlockType::Dirt => Self::Dirt,
             BlockType::Grass => Self::Dirt,
             BlockType::Stone => Self::Stone,
+            BlockType::Ice => Self::Ice,
+            BlockType::Mud => Self::Mud,
             _ => return Err(NoSuchBlockTextureError { for_type: ty }),
         })
     }
@@ -54,12 +107,15 @@ impl BlockTextureIndex {
             BlockType::Dirt => Self::Dirt,
             BlockType::Grass => Self::Grass,
             BlockType::Stone => Self::Stone,
+            BlockType::Ice => Self::Ice,
+            BlockType::Mud => Self::Mud,
             _ => return Err(NoSuchBlockTextureError { for_type: ty }),
         })
     }
 
-    pub fn to_tex_coord(self) -> f32 {
-        (self as u32) as f32 / (BLOCK_TEXTURE_COUNT as f32)
+    /// Returns the layer of the block texture array holding this texture.
+    pub fn layer(self) -> u32 {
+        self as u32
     }
 }
 
@@ -72,6 +128,8 @@ impl TryFrom<BlockType> for BlockTextureIndex {
             BlockType::Grass => Self::Grass,
             BlockType::Stone => Self::Stone,
             BlockType::Sand => Self::Sand,
+            BlockType::Ice => Self::Ice,
+            BlockType::Mud => Self::Mud,
             _ => return Err(Self::Error { for_type: t }),
         })
     }
@@ -95,56 +153,132 @@ impl Display for NoSuchBlockTextureError {
 impl Error for NoSuchBlockTextureError {}
 
 pub struct BlockTextures {
-    pub textures: engine::Texture3d,
+    pub textures: engine::TextureArray,
     bind_group: wgpu::BindGroup,
+    animations: Vec<AnimatedLayer>,
 }
 
 impl BlockTextures {
+    /// Makes the block textures built into the game, scaled to `texture_dimensions` if they aren't
+    /// that size already.
     pub fn default_textures(
         device: &wgpu::Device,
         texture_dimensions: (u32, u32),
+        filters: engine::FilterSettings,
         block_texture_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Result<(Self, Vec<wgpu::CommandBuffer>), Box<dyn Error>> {
-        let mut textures = engine::Texture3d::new(
+        let strips = BlockTextureIndex::ALL
+            .iter()
+            .map(|&index| {
+                index
+                    .default_image()
+                    .map(|image| FrameStrip::from_image(image.into_rgba(), DEFAULT_FRAME_DURATION))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::from_strips(
+            device,
+            strips,
+            texture_dimensions,
+            filters,
+            block_texture_bind_group_layout,
+        )
+    }
+
+    /// Makes block textures from a resource pack. Textures the pack doesn't have are the built-in
+    /// ones. Every texture (or animation frame) is scaled to the pack's resolution, or to the size
+    /// of the largest one if the manifest doesn't say.
+    pub fn from_resource_pack(
+        device: &wgpu::Device,
+        pack: &ResourcePack,
+        filters: engine::FilterSettings,
+        block_texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<(Self, Vec<wgpu::CommandBuffer>), Box<dyn Error>> {
+        let manifest = pack.manifest();
+        for name in manifest
+            .textures
+            .keys()
+            .chain(manifest.frame_durations.keys())
+        {
+            if !BlockTextureIndex::ALL
+                .iter()
+                .any(|index| index.name() == name)
+            {
+                eprintln!("resource pack: ignoring unknown block texture `{}`", name);
+            }
+        }
+
+        let mut strips = Vec::with_capacity(BlockTextureIndex::ALL.len());
+        for &index in BlockTextureIndex::ALL.iter() {
+            let image = match load_pack_image(pack, index)? {
+                Some(i) => i,
+                None => index.default_image()?,
+            };
+
+            let frame_duration = manifest
+                .frame_durations
+                .get(index.name())
+                .copied()
+                .unwrap_or(DEFAULT_FRAME_DURATION);
+            strips.push(FrameStrip::from_image(image.into_rgba(), frame_duration));
+        }
+
+        let texture_dimensions = match manifest.resolution {
+            Some(r) => (r, r),
+            None => strips.iter().fold((1, 1), |(w, h), strip| {
+                let (frame_width, frame_height) = strip.frame_dimensions();
+                (w.max(frame_width), h.max(frame_height))
+            }),
+        };
+
+        Self::from_strips(
+            device,
+            strips,
+            texture_dimensions,
+            filters,
+            block_texture_bind_group_layout,
+        )
+    }
+
+    /// Uploads one strip per BlockTextureIndex, in layer order. Animated strips start on their
+    /// first frame.
+    fn from_strips(
+        device: &wgpu::Device,
+        strips: Vec<FrameStrip>,
+        texture_dimensions: (u32, u32),
+        filters: engine::FilterSettings,
+        block_texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<(Self, Vec<wgpu::CommandBuffer>), Box<dyn Error>> {
+        let mut textures = engine::TextureArray::new(
             device,
             texture_dimensions,
             BLOCK_TEXTURE_COUNT,
+            filters,
             Some("block textures"),
         );
 
-        let dirt_cmd = textures
-            .set_layer_from_bytes(
-                device,
-                BlockTextureIndex::Dirt as u32,
-                include_bytes!("../../assets/images/blocks/dirt.png"),
-            )
-            .map_err(|e| MakeTextureError::new("dirt", e))?;
-
-        let grass_cmd = textures
-            .set_layer_from_bytes(
-                device,
-                BlockTextureIndex::Grass as u32,
-                include_bytes!("../../assets/images/blocks/grass.png"),
-            )
-            .map_err(|e| MakeTextureError::new("grass", e))?;
-
-        let stone_cmd = textures
-            .set_layer_from_bytes(
-                device,
-                BlockTextureIndex::Stone as u32,
-                include_bytes!("../../assets/images/blocks/stone.png"),
-            )
-            .map_err(|e| MakeTextureError::new("stone", e))?;
+        let mut animations = Vec::new();
+        let mut commands = Vec::with_capacity(strips.len());
+        for (&index, strip) in BlockTextureIndex::ALL.iter().zip(strips) {
+            // every layer of the texture array is the same size
+            let strip = strip.resized(texture_dimensions);
 
-        let sand_cmd = textures
-            .set_layer_from_bytes(
-                device,
-                BlockTextureIndex::Sand as u32,
-                include_bytes!("../../assets/images/blocks/sand.png"),
-            )
-            .map_err(|e| MakeTextureError::new("sand", e))?;
+            let command = if strip.is_animated() {
+                let animation = AnimatedLayer::new(index.layer(), &strip);
+                let command = textures.set_layer_from_mip_chain(
+                    device,
+                    index.layer(),
+                    animation.mip_chain(0),
+                );
+                animations.push(animation);
+                command
+            } else {
+                let image = image::DynamicImage::ImageRgba8(strip.frames()[0].clone());
+                textures.set_layer_from_image(device, index.layer(), image)
+            };
 
-        let commands = vec![dirt_cmd, stone_cmd, grass_cmd, sand_cmd];
+            commands.push(command.map_err(|e| MakeTextureError::new(index.name(), e))?);
+        }
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: block_texture_bind_group_layout,
@@ -165,6 +299,7 @@ impl BlockTextures {
             Self {
                 textures,
                 bind_group,
+                animations,
             },
             commands,
         ))
@@ -173,11 +308,81 @@ impl BlockTextures {
     pub fn get_bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
+
+    /// Moves animated textures along, returning commands that upload the frames that changed.
+    /// Chunk meshes keep pointing at the same layers, so nothing needs re-meshing.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        delta_sec: f32,
+    ) -> Result<Vec<wgpu::CommandBuffer>, Box<dyn Error>> {
+        let mut commands = Vec::new();
+
+        for (layer, mip_chain) in advance_animations(&mut self.animations, delta_sec) {
+            commands.push(
+                self.textures
+                    .set_layer_from_mip_chain(device, layer, mip_chain)?,
+            );
+        }
+
+        Ok(commands)
+    }
+}
+
+/// Moves every animation along. Returns the layer and the mip chain to upload over it for each one
+/// that changed frames.
+fn advance_animations(
+    animations: &mut [AnimatedLayer],
+    delta_sec: f32,
+) -> Vec<(u32, &[image::RgbaImage])> {
+    let mut changed = Vec::new();
+
+    for animation in animations.iter_mut() {
+        if let Some(frame) = animation.advance(delta_sec) {
+            let animation: &AnimatedLayer = animation;
+            changed.push((animation.layer(), animation.mip_chain(frame)));
+        }
+    }
+
+    changed
+}
+
+/// Loads a block texture from a resource pack. Returns None if the manifest doesn't name one, or
+/// names a file that isn't there.
+fn load_pack_image(
+    pack: &ResourcePack,
+    index: BlockTextureIndex,
+) -> Result<Option<image::DynamicImage>, MakeTextureError> {
+    let file = match pack.texture_file(index.name()) {
+        Some(f) => f,
+        None => return Ok(None),
+    };
+    let path = pack.path_of(file);
+
+    let bytes = match pack.read(file) {
+        Ok(Some(b)) => b,
+        Ok(None) => {
+            eprintln!(
+                "resource pack: `{}` doesn't exist; using the built-in `{}` texture",
+                path.display(),
+                index.name()
+            );
+            return Ok(None);
+        }
+        Err(e) => return Err(MakeTextureError::in_file(index.name(), &path, e)),
+    };
+
+    image::load_from_memory(&bytes)
+        .map(Some)
+        .map_err(|e| MakeTextureError::in_file(index.name(), &path, Box::new(e)))
 }
 
 #[derive(Debug)]
 struct MakeTextureError {
     block_name: String,
+
+    /// The file the texture came from, if it came from a file.
+    file: Option<PathBuf>,
     error: Box<dyn Error>,
 }
 
@@ -185,18 +390,37 @@ impl MakeTextureError {
     fn new<E: Error + 'static>(block_name: &str, error: E) -> Self {
         Self {
             block_name: String::from(block_name),
+            file: None,
             error: Box::new(error),
         }
     }
+
+    fn in_file(block_name: &str, file: &Path, error: Box<dyn Error>) -> Self {
+        Self {
+            block_name: String::from(block_name),
+            file: Some(file.to_path_buf()),
+            error,
+        }
+    }
 }
 
 impl Display for MakeTextureError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(
-            f,
-            "error making texture for `{}`: {}",
-            self.block_name, self.error
-        )
+        if let Some(file) = &self.file {
+            write!(
+                f,
+                "error making texture for `{}` from `{}`: {}",
+                self.block_name,
+                file.display(),
+                self.error
+            )
+        } else {
+            write!(
+                f,
+                "error making texture for `{}`: {}",
+                self.block_name, self.error
+            )
+        }
     }
 }
 
@@ -222,3 +446,110 @@ impl Display for TextureFromBytesError {
 }
 
 impl Error for TextureFromBytesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn every_texture_has_a_built_in_default() {
+        for &index in BlockTextureIndex::ALL.iter() {
+            assert!(index.default_image().is_ok(), "{}", index.name());
+        }
+    }
+
+    #[test]
+    fn animations_upload_frames_as_they_change() {
+        // two 4x4 frames, filled with their frame number
+        let two_frames =
+            image::RgbaImage::from_fn(4, 8, |_, y| image::Rgba([(y / 4) as u8, 0, 0, 255]));
+        let strip = FrameStrip::from_image(two_frames, DEFAULT_FRAME_DURATION);
+
+        let mut animations = vec![
+            AnimatedLayer::new(BlockTextureIndex::Sand.layer(), &strip),
+            AnimatedLayer::new(
+                BlockTextureIndex::Dirt.layer(),
+                &FrameStrip::from_image(strip.frames()[0].clone(), DEFAULT_FRAME_DURATION),
+            ),
+        ];
+
+        assert!(advance_animations(&mut animations, DEFAULT_FRAME_DURATION / 2.0).is_empty());
+
+        let changed = advance_animations(&mut animations, DEFAULT_FRAME_DURATION);
+        assert_eq!(changed.len(), 1);
+
+        let (layer, mip_chain) = changed[0];
+        assert_eq!(layer, BlockTextureIndex::Sand.layer());
+        assert_eq!(mip_chain[0].get_pixel(0, 0)[0], 1);
+        assert_eq!(mip_chain.len(), 3);
+    }
+
+    #[test]
+    fn ground_blocks_have_textures_on_every_face() {
+        let ground = [
+            BlockType::Dirt,
+            BlockType::Grass,
+            BlockType::Stone,
+            BlockType::Sand,
+            BlockType::Ice,
+            BlockType::Mud,
+        ];
+        for &ty in ground.iter() {
+            for &direction in Direction::ALL.iter() {
+                assert!(
+                    BlockTextureIndex::from_type_and_direction(ty, direction).is_ok(),
+                    "{:?} {:?}",
+                    ty,
+                    direction
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layers_match_the_order_of_all() {
+        for (layer, index) in BlockTextureIndex::ALL.iter().enumerate() {
+            assert_eq!(index.layer(), layer as u32);
+        }
+    }
+
+    #[test]
+    fn pack_images_fall_back_and_errors_name_the_file() {
+        let dir = std::env::temp_dir().join(format!("chicky-textures-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(MANIFEST_FILE_NAME),
+            "dirt = dirt.png\nsand = missing.png\nstone = stone.png",
+        )
+        .unwrap();
+        fs::write(dir.join("dirt.png"), "definitely not a png").unwrap();
+        fs::write(
+            dir.join("stone.png"),
+            BlockTextureIndex::Stone.default_image_bytes(),
+        )
+        .unwrap();
+
+        let pack = ResourcePack::open(&dir).unwrap();
+
+        let error = match load_pack_image(&pack, BlockTextureIndex::Dirt) {
+            Err(e) => e,
+            Ok(_) => panic!("garbage decoded as a png"),
+        };
+        assert!(error
+            .to_string()
+            .contains(&format!("`{}`", dir.join("dirt.png").display())));
+
+        assert!(load_pack_image(&pack, BlockTextureIndex::Sand)
+            .unwrap()
+            .is_none());
+        assert!(load_pack_image(&pack, BlockTextureIndex::Grass)
+            .unwrap()
+            .is_none());
+        assert!(load_pack_image(&pack, BlockTextureIndex::Stone)
+            .unwrap()
+            .is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
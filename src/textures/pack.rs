@@ -0,0 +1,384 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// The file at the root of every resource pack that says what's in it.
+pub const MANIFEST_FILE_NAME: &str = "manifest.txt";
+
+const FRAME_DURATION_SUFFIX: &str = ".frame_duration";
+
+/// A ResourcePack is a directory or zip archive of textures that replace the built-in ones. At its
+/// root is a `manifest.txt` made of `key = value` lines:
+///
+/// ```text
+/// # lines starting with a pound sign are ignored
+/// name = Crispy Blocks
+/// resolution = 32
+/// grass = blocks/grass_top.png
+/// dirt = blocks/dirt.png
+/// ```
+///
+/// `name` and `resolution` are optional. Every other key names a block texture (see
+/// `BlockTextureIndex::name`) and points to an image inside the pack. Textures the pack doesn't
+/// mention are left as the built-in ones.
+///
+/// An image made of square frames stacked top to bottom is an animation (see `FrameStrip`).
+/// `<texture>.frame_duration` sets how many seconds each frame is shown:
+///
+/// ```text
+/// sand = blocks/shifting_sand.png
+/// sand.frame_duration = 0.25
+/// ```
+pub struct ResourcePack {
+    location: PathBuf,
+    source: PackSource,
+    manifest: Manifest,
+}
+
+enum PackSource {
+    Directory,
+
+    /// Reading from an archive needs it borrowed mutably, even though nothing in it changes.
+    Zip(RefCell<ZipArchive<File>>),
+}
+
+impl ResourcePack {
+    /// Opens the resource pack at `path`, which is either a directory or a zip archive, and reads
+    /// its manifest.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ResourcePackError> {
+        let location = path.as_ref().to_path_buf();
+
+        let source = if location.is_dir() {
+            PackSource::Directory
+        } else {
+            let file = File::open(&location)
+                .map_err(|e| ResourcePackError::new(&location, Box::new(e)))?;
+            let archive = ZipArchive::new(file)
+                .map_err(|e| ResourcePackError::new(&location, Box::new(e)))?;
+            PackSource::Zip(RefCell::new(archive))
+        };
+
+        let mut pack = Self {
+            location,
+            source,
+            manifest: Default::default(),
+        };
+
+        let manifest_path = pack.path_of(MANIFEST_FILE_NAME);
+        let manifest_bytes = pack
+            .read(MANIFEST_FILE_NAME)
+            .map_err(|e| ResourcePackError::new(&manifest_path, e))?
+            .ok_or_else(|| {
+                ResourcePackError::from_message(&manifest_path, "resource pack has no manifest")
+            })?;
+
+        pack.manifest = Manifest::parse(&String::from_utf8_lossy(&manifest_bytes))
+            .map_err(|message| ResourcePackError::from_message(&manifest_path, &message))?;
+
+        Ok(pack)
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Returns the file the manifest names for the texture, if it names one.
+    pub fn texture_file(&self, texture_name: &str) -> Option<&str> {
+        self.manifest.textures.get(texture_name).map(String::as_str)
+    }
+
+    /// Returns a path to the file for use in messages. Files in zip archives are shown as
+    /// `pack.zip/inner/file.png`.
+    pub fn path_of(&self, file: &str) -> PathBuf {
+        self.location.join(file)
+    }
+
+    /// Reads a file from the pack. Returns None if the file doesn't exist. Files have to be named
+    /// relative to the root of the pack, without any `..`, so that a manifest can't reach outside
+    /// of it. Errors don't say which file they came from; use `path_of` for that.
+    pub fn read(&self, file: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        if !is_inside_pack(file) {
+            return Err("files must be named relative to the root of the resource pack".into());
+        }
+
+        match &self.source {
+            PackSource::Directory => {
+                let path = self.path_of(file);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                Ok(Some(fs::read(&path)?))
+            }
+            PackSource::Zip(archive) => {
+                let mut archive = archive.borrow_mut();
+                let mut entry = match archive.by_name(file) {
+                    Ok(e) => e,
+                    Err(ZipError::FileNotFound) => return Ok(None),
+                    Err(e) => return Err(Box::new(e)),
+                };
+
+                // the checksum is checked once the whole file has been read
+                let mut bytes = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut bytes)?;
+                Ok(Some(bytes))
+            }
+        }
+    }
+}
+
+/// Returns true if the file name only goes down into the pack: no `..`, `.`, root, or drive
+/// prefix.
+fn is_inside_pack(file: &str) -> bool {
+    let path = Path::new(file);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// The contents of a resource pack's `manifest.txt`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Manifest {
+    pub name: Option<String>,
+
+    /// The width and height, in pixels, that every block texture is scaled to. If None, the
+    /// largest texture in the pack decides.
+    pub resolution: Option<u32>,
+
+    /// Texture names mapped to files inside of the pack.
+    pub textures: HashMap<String, String>,
+
+    /// Texture names mapped to how long each animation frame is shown, in seconds.
+    pub frame_durations: HashMap<String, f32>,
+}
+
+impl Manifest {
+    /// Parses a manifest. Returns a message saying what's wrong (and on which line) if it can't.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut manifest = Self::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(format!("line {}: expected `key = value`", number + 1)),
+            };
+
+            if key.is_empty() || value.is_empty() {
+                return Err(format!("line {}: expected `key = value`", number + 1));
+            }
+
+            match key {
+                "name" => manifest.name = Some(String::from(value)),
+                "resolution" => {
+                    let resolution =
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|r| *r > 0)
+                            .ok_or_else(|| {
+                                format!(
+                                    "line {}: resolution must be a positive whole number",
+                                    number + 1
+                                )
+                            })?;
+                    manifest.resolution = Some(resolution);
+                }
+                key if key.ends_with(FRAME_DURATION_SUFFIX) => {
+                    let duration = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|d| d.is_finite() && *d > 0.0)
+                        .ok_or_else(|| {
+                            format!(
+                                "line {}: frame duration must be a positive number of seconds",
+                                number + 1
+                            )
+                        })?;
+
+                    let texture = &key[..key.len() - FRAME_DURATION_SUFFIX.len()];
+                    manifest
+                        .frame_durations
+                        .insert(String::from(texture), duration);
+                }
+                texture => {
+                    manifest
+                        .textures
+                        .insert(String::from(texture), String::from(value));
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Something went wrong reading a file in a resource pack. Always says which file.
+#[derive(Debug)]
+pub struct ResourcePackError {
+    file: PathBuf,
+    error: Box<dyn Error>,
+}
+
+impl ResourcePackError {
+    fn new(file: &Path, error: Box<dyn Error>) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            error,
+        }
+    }
+
+    fn from_message(file: &Path, message: &str) -> Self {
+        Self::new(file, message.into())
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+}
+
+impl Display for ResourcePackError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "`{}`: {}", self.file.display(), self.error)
+    }
+}
+
+impl Error for ResourcePackError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    /// Builds a zip archive in memory, deflating the files marked to be compressed.
+    fn make_zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        for &(name, contents, compress) in files.iter() {
+            let method = if compress {
+                CompressionMethod::Deflated
+            } else {
+                CompressionMethod::Stored
+            };
+
+            writer
+                .start_file(name, FileOptions::default().compression_method(method))
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Returns a path in the temp directory that no other test uses.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("chicky-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn parses_manifests() {
+        let manifest = Manifest::parse(
+            "# a pack\n\
+             name = Crispy Blocks\n\
+             resolution = 32\n\
+             \n\
+             grass = blocks/grass top.png\n\
+             grass.frame_duration = 0.5\n",
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name.as_deref(), Some("Crispy Blocks"));
+        assert_eq!(manifest.resolution, Some(32));
+        assert_eq!(
+            manifest.textures.get("grass").map(String::as_str),
+            Some("blocks/grass top.png")
+        );
+        assert_eq!(manifest.frame_durations.get("grass"), Some(&0.5));
+    }
+
+    #[test]
+    fn manifest_errors_say_which_line() {
+        let error = Manifest::parse("name = ok\nnonsense\n").unwrap_err();
+        assert!(error.starts_with("line 2"), "{}", error);
+
+        assert!(Manifest::parse("resolution = -4").is_err());
+        assert!(Manifest::parse("resolution = 0").is_err());
+        assert!(Manifest::parse("dirt.frame_duration = never").is_err());
+    }
+
+    #[test]
+    fn opens_zipped_packs() {
+        let path = temp_path("pack.zip");
+        fs::write(
+            &path,
+            make_zip(&[
+                (MANIFEST_FILE_NAME, b"dirt = blocks/dirt.png", false),
+                ("blocks/dirt.png", &[7; 300], true),
+            ]),
+        )
+        .unwrap();
+
+        let pack = ResourcePack::open(&path).unwrap();
+        assert_eq!(pack.texture_file("dirt"), Some("blocks/dirt.png"));
+        assert_eq!(pack.read("blocks/dirt.png").unwrap().unwrap(), vec![7; 300]);
+        assert!(pack.read("grass.png").unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_broken_archives() {
+        let garbage = temp_path("garbage.zip");
+        fs::write(&garbage, vec![0; 100]).unwrap();
+        assert!(ResourcePack::open(&garbage).is_err());
+        fs::remove_file(&garbage).unwrap();
+
+        // flipping a byte of a stored file leaves the archive readable, but the checksum won't
+        // match
+        let mut bytes = make_zip(&[
+            (MANIFEST_FILE_NAME, b"dirt = dirt.png", false),
+            ("dirt.png", b"not really a png", false),
+        ]);
+        let at = bytes.windows(6).position(|w| w == b"really").unwrap();
+        bytes[at] ^= 0xff;
+
+        let corrupt = temp_path("corrupt.zip");
+        fs::write(&corrupt, bytes).unwrap();
+        let pack = ResourcePack::open(&corrupt).unwrap();
+        assert!(pack.read("dirt.png").is_err());
+        fs::remove_file(&corrupt).unwrap();
+    }
+
+    #[test]
+    fn files_cannot_escape_the_pack() {
+        let root = temp_path("escape");
+        let dir = root.join("pack");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(MANIFEST_FILE_NAME), "dirt = ../secret.png").unwrap();
+        fs::write(dir.join("dirt.png"), "inside").unwrap();
+        fs::write(root.join("secret.png"), "outside").unwrap();
+
+        let pack = ResourcePack::open(&dir).unwrap();
+        assert_eq!(pack.read("dirt.png").unwrap().unwrap(), b"inside".to_vec());
+        assert!(pack.read(pack.texture_file("dirt").unwrap()).is_err());
+
+        let absolute = root.join("secret.png");
+        assert!(pack.read(absolute.to_str().unwrap()).is_err());
+        assert!(pack.read("./dirt.png").is_err());
+        assert!(pack.read("").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
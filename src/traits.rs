@@ -1,3 +1,4 @@
+use crate::characters::Damage;
 use crate::items;
 
 /// Flammable can be added to objects that can be ignited. Flammables will ignite any neighboring
@@ -14,6 +15,10 @@ pub trait Killable {
     /// the killable might drop when hit.
     fn hit(&mut self, with: Option<items::Item>, power: f32) -> &[items::ItemStack];
 
+    /// Called when the Killable is hurt by anything, not just a hit.
+    /// Returns any items that the killable might drop, like `hit`.
+    fn damage(&mut self, damage: Damage) -> &[items::ItemStack];
+
     /// Called when the killable should be killed. Returns any
     /// items that might be dropped with the Killable dies.
     fn kill(&mut self) -> &[items::ItemStack];
@@ -6,6 +6,11 @@ use crate::camera::Camera;
 pub struct Uniforms {
     /// The view-projection matrix.
     pub view_proj: cgmath::Matrix4<f32>,
+
+    /// Which ways are right and up on the screen, for turning sprites toward the camera. Vector4s
+    /// because that's how shaders line up vec3s in uniform blocks; `w` is unused.
+    pub camera_right: cgmath::Vector4<f32>,
+    pub camera_up: cgmath::Vector4<f32>,
 }
 
 impl Uniforms {
@@ -13,11 +18,15 @@ impl Uniforms {
         use cgmath::SquareMatrix;
         Self {
             view_proj: cgmath::Matrix4::identity(),
+            camera_right: cgmath::Vector4::unit_x(),
+            camera_up: cgmath::Vector4::unit_y(),
         }
     }
 
     fn update_view_proj(&mut self, camera: &Camera) {
         self.view_proj = *camera.get_view_projection_matrix();
+        self.camera_right = camera.get_right().extend(0.0);
+        self.camera_up = camera.get_screen_up().extend(0.0);
     }
 
     pub fn update(
@@ -0,0 +1,119 @@
+use super::ChunkIndex;
+use crate::blocks::{ChunkMesh, MeshJob};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// LodSettings decides how detailed a chunk's mesh should be based on how far it is from the
+/// camera. Far away chunks are meshed from downsampled blocks, which makes for far fewer vertices.
+#[derive(Debug, Copy, Clone)]
+pub struct LodSettings {
+    /// Chunks at most this many chunks away from the camera are meshed at full detail.
+    pub full_detail_distance: i64,
+
+    /// Every this many chunks past `full_detail_distance`, the detail is halved again, down to a
+    /// cell for every 8 blocks.
+    pub distance_per_level: i64,
+}
+
+impl LodSettings {
+    /// Returns how many blocks wide each mesh cell should be for a chunk this many chunks away.
+    pub fn lod_factor_at(&self, distance: i64) -> usize {
+        if distance <= self.full_detail_distance {
+            return 1;
+        }
+
+        let level = (distance - self.full_detail_distance - 1) / self.distance_per_level.max(1) + 1;
+
+        // 2, 4, or 8
+        1 << level.min(3)
+    }
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            full_detail_distance: 2,
+            distance_per_level: 2,
+        }
+    }
+}
+
+/// MeshWorker makes chunk meshes on a background thread so that moving around doesn't stall
+/// frames while far away chunks are re-meshed.
+pub struct MeshWorker {
+    jobs: Sender<(ChunkIndex, MeshJob)>,
+    meshes: Receiver<(ChunkIndex, ChunkMesh)>,
+
+    /// Meshes that had to be made on this thread because the worker thread was gone.
+    orphaned: Vec<(ChunkIndex, ChunkMesh)>,
+}
+
+impl MeshWorker {
+    pub fn new() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<(ChunkIndex, MeshJob)>();
+        let (mesh_sender, meshes) = mpsc::channel();
+
+        // the thread ends on its own once the worker is dropped, since `jobs` hangs up
+        thread::spawn(move || {
+            for (index, job) in job_receiver {
+                if mesh_sender.send((index, job.run())).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            meshes,
+            orphaned: Vec::new(),
+        }
+    }
+
+    /// Queues the job to be meshed.
+    pub fn submit(&mut self, index: ChunkIndex, job: MeshJob) {
+        if let Err(mpsc::SendError((_, job))) = self.jobs.send((index, job)) {
+            // the worker thread died somehow (a panic, probably). mesh here instead of losing it
+            eprintln!(
+                "mesh worker is gone; meshing chunk {:?} on this thread",
+                index
+            );
+            self.orphaned.push((index, job.run()));
+        }
+    }
+
+    /// Returns every mesh that has finished since the last call, without blocking.
+    pub fn finished(&mut self) -> Vec<(ChunkIndex, ChunkMesh)> {
+        let mut finished: Vec<_> = self.orphaned.drain(..).collect();
+        finished.extend(self.meshes.try_iter());
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detail_halves_every_band_of_distance() {
+        let settings = LodSettings {
+            full_detail_distance: 2,
+            distance_per_level: 3,
+        };
+
+        let factors: Vec<usize> = (0..=13).map(|d| settings.lod_factor_at(d)).collect();
+        assert_eq!(factors, vec![1, 1, 1, 2, 2, 2, 4, 4, 4, 8, 8, 8, 8, 8]);
+    }
+
+    #[test]
+    fn bands_are_at_least_a_chunk_wide() {
+        let settings = LodSettings {
+            full_detail_distance: 0,
+            distance_per_level: 0,
+        };
+
+        assert_eq!(settings.lod_factor_at(0), 1);
+        assert_eq!(settings.lod_factor_at(1), 2);
+        assert_eq!(settings.lod_factor_at(2), 4);
+        assert_eq!(settings.lod_factor_at(3), 8);
+    }
+}
@@ -1,59 +1,323 @@
 #![allow(dead_code)]
 
 mod climate;
+mod lod;
+mod pathfinding;
 
-use crate::blocks::Chunk;
-use crate::textures::BlockTextures;
-use std::collections::VecDeque;
+pub use lod::*;
+pub use pathfinding::*;
+
+use crate::blocks::{Block, Chunk, ChunkVisibility, CHUNK_BLOCK_WIDTH, CHUNK_SIZE};
+use crate::camera::Camera;
+use crate::maths::Frustum;
+use crate::physics::{BlockIndex, Fluid, Surface, Terrain};
+use cgmath::Point3;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 // NOTE: potentially allowing infinite worlds
 // const MAX_WORLD_SIZE: i32 = 1024; // in chunks. 1024 yields thousands upon thousands of blocks in each direction
 
+/// The position of a chunk on the chunk grid, in chunks (not blocks or meters).
+pub type ChunkIndex = (i64, i64, i64);
+
 /// A world.
 pub struct World {
-    chunks: VecDeque<VecDeque<VecDeque<Chunk>>>,
+    chunks: HashMap<ChunkIndex, Chunk>,
     seed: i64,
     render_distance: i32,
-    textures: BlockTextures,
+
+    lod_settings: LodSettings,
+    mesh_worker: MeshWorker,
+
+    /// How many chunks were drawn and skipped during the last render. A Cell because rendering
+    /// only borrows the World immutably.
+    culling_stats: Cell<CullingStats>,
+
+    /// Blocks set since the last `take_changed_blocks`.
+    changed_blocks: Vec<BlockIndex>,
 }
 
 impl World {
-    fn new(seed: i64, textures: BlockTextures) -> Self {
-        Self {
+    pub fn new(seed: i64, device: &wgpu::Device) -> Self {
+        let mut w = Self {
             chunks: Default::default(),
             seed,
             render_distance: 6,
-            textures,
+            lod_settings: Default::default(),
+            mesh_worker: MeshWorker::new(),
+            culling_stats: Default::default(),
+            changed_blocks: Vec::new(),
+        };
+
+        // TODO: generate chunks around the player as they move instead of a fixed patch
+        for i in -1..=1 {
+            for k in -1..=1 {
+                w.generate_chunk(i, 0, k, device);
+            }
+        }
+
+        w
+    }
+
+    pub fn generate_chunk(&mut self, i: i64, j: i64, k: i64, device: &wgpu::Device) {
+        self.chunks
+            .insert((i, j, k), Chunk::generate(i, j, k, device));
+    }
+
+    /// Updates chunk meshes, choosing each chunk's level of detail by its distance from the
+    /// camera, and stitching its borders to more detailed neighbors. Lower detail meshes are made
+    /// on a background thread and swapped in once they're ready.
+    pub fn logic(&mut self, device: &wgpu::Device, camera: &Camera) {
+        let camera_chunk = Self::chunk_index_at(camera.get_position());
+
+        let lod_settings = self.lod_settings;
+        let lod_factor_of = |index| lod_settings.lod_factor_at(chunk_distance(index, camera_chunk));
+
+        for (&index, chunk) in self.chunks.iter_mut() {
+            let mut neighbor_lod_factors = [0; 6];
+            for &direction in Direction::ALL.iter() {
+                let (di, dj, dk) = direction.offset();
+                neighbor_lod_factors[direction.index()] =
+                    lod_factor_of((index.0 + di, index.1 + dj, index.2 + dk));
+            }
+
+            if let Some(job) = chunk.logic(device, lod_factor_of(index), neighbor_lod_factors) {
+                self.mesh_worker.submit(index, job);
+            }
+        }
+
+        for (index, mesh) in self.mesh_worker.finished() {
+            if let Some(chunk) = self.chunks.get_mut(&index) {
+                chunk.finish_mesh(device, mesh);
+            }
+        }
+    }
+
+    pub fn lod_settings(&self) -> LodSettings {
+        self.lod_settings
+    }
+
+    /// Changes how far away chunks have to be before they're drawn with less detail. Chunks are
+    /// re-meshed to match on the next `logic`.
+    pub fn set_lod_settings(&mut self, settings: LodSettings) {
+        self.lod_settings = settings;
+    }
+
+    /// Renders every chunk that might be visible to the camera. Chunks outside of the frustum are
+    /// skipped, and so are chunks hidden behind solid terrain; see `culling_stats` for how many.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera: &Camera) {
+        let frustum = camera.get_frustum();
+        let start = Self::chunk_index_at(camera.get_position());
+        let visible = find_visible_chunks(start, &frustum, self.render_distance as i64, |index| {
+            self.chunks.get(&index).map(Chunk::visibility)
+        });
+
+        for index in visible.iter() {
+            if let Some(chunk) = self.chunks.get(index) {
+                chunk.render(render_pass);
+            }
+        }
+
+        self.culling_stats.set(count_culled(
+            self.chunks.keys().copied(),
+            &visible,
+            &frustum,
+            start,
+            self.render_distance as i64,
+        ));
+    }
+
+    /// Returns the index of the chunk containing the point.
+    pub fn chunk_index_at(point: Point3<f32>) -> ChunkIndex {
+        (
+            (point.x / CHUNK_SIZE).floor() as i64,
+            (point.y / CHUNK_SIZE).floor() as i64,
+            (point.z / CHUNK_SIZE).floor() as i64,
+        )
+    }
+
+    /// Returns the block at the position on the block grid, or None if its chunk isn't loaded.
+    pub fn block_at(&self, block: BlockIndex) -> Option<&Block> {
+        let width = CHUNK_BLOCK_WIDTH as i64;
+        let (i, j, k) = block;
+
+        let chunk = self.chunks.get(&(
+            i.div_euclid(width),
+            j.div_euclid(width),
+            k.div_euclid(width),
+        ))?;
+
+        Some(chunk.at(
+            i.rem_euclid(width) as usize,
+            j.rem_euclid(width) as usize,
+            k.rem_euclid(width) as usize,
+        ))
+    }
+
+    /// Sets the block at the position on the block grid. Returns false, changing nothing, if its
+    /// chunk isn't loaded.
+    pub fn set_block(&mut self, block: BlockIndex, b: Block) -> bool {
+        let width = CHUNK_BLOCK_WIDTH as i64;
+        let (i, j, k) = block;
+
+        let chunk = match self.chunks.get_mut(&(
+            i.div_euclid(width),
+            j.div_euclid(width),
+            k.div_euclid(width),
+        )) {
+            Some(c) => c,
+            None => return false,
+        };
+
+        chunk.set(
+            i.rem_euclid(width) as usize,
+            j.rem_euclid(width) as usize,
+            k.rem_euclid(width) as usize,
+            b,
+        );
+        self.changed_blocks.push(block);
+        true
+    }
+
+    /// Returns every block set since this was last called, for anything that needs to catch up
+    /// with them, like cached paths.
+    pub fn take_changed_blocks(&mut self) -> Vec<BlockIndex> {
+        std::mem::take(&mut self.changed_blocks)
+    }
+
+    /// Returns how many chunks were drawn and culled during the last render.
+    pub fn culling_stats(&self) -> CullingStats {
+        self.culling_stats.get()
+    }
+}
+
+/// Flood-fills outward from the camera's chunk, only stepping from one chunk to the next if the
+/// face it was entered through can see the face it would leave through, and only ever moving away
+/// from the camera. Returns the indices of every chunk reached within `render_distance` chunks, in
+/// the order they were reached (front to back). Chunks that `visibility_of` doesn't know about are
+/// treated as empty air.
+fn find_visible_chunks<F>(
+    start: ChunkIndex,
+    frustum: &Frustum,
+    render_distance: i64,
+    visibility_of: F,
+) -> Vec<ChunkIndex>
+where
+    F: Fn(ChunkIndex) -> Option<ChunkVisibility>,
+{
+    let mut visible = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    // (chunk, face it was entered through, bitmask of directions travelled so far)
+    queue.push_back((start, None, 0u8));
+    visited.insert(start);
+
+    while let Some((index, entered_through, travelled)) = queue.pop_front() {
+        visible.push(index);
+
+        let chunk_visibility = visibility_of(index).unwrap_or(ChunkVisibility::ALL);
+
+        for &direction in Direction::ALL.iter() {
+            // never turn back toward the camera; anything back there was reached already
+            if travelled & (1 << direction.opposite().index()) != 0 {
+                continue;
+            }
+
+            if let Some(from) = entered_through {
+                if !chunk_visibility.connects(from, direction) {
+                    continue;
+                }
+            }
+
+            let (di, dj, dk) = direction.offset();
+            let next = (index.0 + di, index.1 + dj, index.2 + dk);
+
+            if chunk_distance(start, next) > render_distance || visited.contains(&next) {
+                continue;
+            }
+
+            if !frustum.intersects_aabb(&Chunk::bounding_box_at(next)) {
+                continue;
+            }
+
+            visited.insert(next);
+            queue.push_back((
+                next,
+                Some(direction.opposite()),
+                travelled | (1 << direction.index()),
+            ));
         }
     }
 
-    pub fn generate_chunk(&self, _x: i32, _y: i32, _z: i32) {
-        todo!()
-    }
-
-    fn render(&self) {
-        todo!()
-        // let eye_chunk_x = c.position().x / (blocks::BLOCK_WIDTH * blocks::CHUNK_SIZE);
-        // let eye_chunk_y = c.position().y / (blocks::BLOCK_WIDTH * blocks::CHUNK_SIZE);
-        // let eye_chunk_z = c.position().z / (blocks::BLOCK_WIDTH * blocks::CHUNK_SIZE);
-
-        // for x in (eye_chunk_x - self.render_distance)..(eye_chunk_x + self.render_distance) {
-        //     if x < 0 || x >= self.terrain.len() {
-        //         continue;
-        //     }
-        //     for y in (eye_chunk_y - self.render_distance)..(eye_chunk_y + self.render_distance) {
-        //         if y < 0 || y >= self.terrain[x].len() {
-        //             continue;
-        //         }
-        //         for z in (eye_chunk_z - self.render_distance)..(eye_chunk_z + self.render_distance) {
-        //             if z < 0 || z >= self.terrain[x][y].len() {
-        //                 continue;
-        //             }
-        //             self.terrain[x][y][z].render();
-        //         }
-        //     }
-        // }
+    visible
+}
+
+/// Sorts the loaded chunks into drawn, outside of the frustum, and hidden behind terrain. Chunks
+/// that weren't drawn because they're beyond `render_distance` aren't counted at all. The camera's
+/// own chunk is always drawn, even when the frustum only just misses it.
+fn count_culled<I>(
+    loaded: I,
+    visible: &[ChunkIndex],
+    frustum: &Frustum,
+    start: ChunkIndex,
+    render_distance: i64,
+) -> CullingStats
+where
+    I: Iterator<Item = ChunkIndex>,
+{
+    let visible: HashSet<_> = visible.iter().collect();
+    let mut stats = CullingStats::default();
+
+    for index in loaded {
+        if visible.contains(&index) {
+            stats.visible += 1;
+        } else if !frustum.intersects_aabb(&Chunk::bounding_box_at(index)) {
+            stats.culled += 1;
+        } else if chunk_distance(start, index) <= render_distance {
+            stats.occluded += 1;
+        }
     }
+
+    stats
+}
+
+/// Returns how many chunks apart two chunks are along whichever axis they're furthest apart on.
+fn chunk_distance(a: ChunkIndex, b: ChunkIndex) -> i64 {
+    (a.0 - b.0)
+        .abs()
+        .max((a.1 - b.1).abs())
+        .max((a.2 - b.2).abs())
+}
+
+impl Terrain for World {
+    /// Chunks that aren't loaded are empty, the same as they are for rendering.
+    fn is_solid(&self, block: BlockIndex) -> bool {
+        self.block_at(block).map(Block::is_solid).unwrap_or(false)
+    }
+
+    fn surface(&self, block: BlockIndex) -> Surface {
+        self.block_at(block).map(Block::surface).unwrap_or_default()
+    }
+
+    fn fluid(&self, block: BlockIndex) -> Option<Fluid> {
+        self.block_at(block).and_then(Block::fluid)
+    }
+}
+
+/// Counts of chunks drawn and skipped in a single frame. Useful for debugging culling.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CullingStats {
+    /// Chunks that were drawn.
+    pub visible: usize,
+
+    /// Chunks that were skipped because they were outside of the frustum.
+    pub culled: usize,
+
+    /// Chunks inside of the frustum and the render distance that were skipped because solid
+    /// terrain hides them.
+    pub occluded: usize,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -63,7 +327,7 @@ pub enum Axis {
     Z,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Direction {
     North,
     South,
@@ -72,3 +336,109 @@ pub enum Direction {
     Up,
     Down,
 }
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::Up,
+        Direction::Down,
+    ];
+
+    /// Returns a unique number from 0 to 5 for this direction.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the step, in grid units, taken when moving in this direction. North is +z and east
+    /// is -x, matching the faces the chunk mesher produces.
+    pub fn offset(self) -> (i64, i64, i64) {
+        match self {
+            Direction::North => (0, 0, 1),
+            Direction::South => (0, 0, -1),
+            Direction::East => (-1, 0, 0),
+            Direction::West => (1, 0, 0),
+            Direction::Up => (0, 1, 0),
+            Direction::Down => (0, -1, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sees everything within a kilometer of the origin, so only occlusion decides what's culled.
+    fn everywhere() -> Frustum {
+        Frustum::from_matrix(&cgmath::ortho(
+            -1000.0, 1000.0, -1000.0, 1000.0, -1000.0, 1000.0,
+        ))
+    }
+
+    /// Finds what's visible from the origin chunk with `between`, and nothing else, loaded just
+    /// south of it.
+    fn visible_past(between: ChunkVisibility) -> Vec<ChunkIndex> {
+        find_visible_chunks((0, 0, 0), &everywhere(), 6, |index| {
+            if index == (0, 0, -1) {
+                Some(between)
+            } else {
+                None
+            }
+        })
+    }
+
+    #[test]
+    fn sealed_chunks_hide_what_is_behind_them() {
+        let visible = visible_past(ChunkVisibility::compute(|_, _, _| false));
+
+        // the solid chunk itself is still drawn
+        assert!(visible.contains(&(0, 0, -1)));
+        assert!(!visible.contains(&(0, 0, -2)));
+
+        // reaching it around the sides would mean turning back toward the camera
+        assert!(visible.contains(&(1, 0, -2)));
+    }
+
+    #[test]
+    fn corridors_can_be_seen_through() {
+        // a tunnel running north to south through the middle of a solid chunk
+        let middle = CHUNK_BLOCK_WIDTH / 2;
+        let tunnel = ChunkVisibility::compute(|i, j, _| i == middle && j == middle);
+
+        let visible = visible_past(tunnel);
+        assert!(visible.contains(&(0, 0, -2)));
+        assert!(visible.contains(&(0, 0, -6)));
+        assert!(!visible.contains(&(0, 0, -7)));
+    }
+
+    #[test]
+    fn culling_counts_each_chunk_once() {
+        // looking away from the origin chunk, which is drawn anyway
+        let ahead = Frustum::from_matrix(&cgmath::ortho(-10.0, 10.0, -10.0, 10.0, 40.0, 1000.0));
+        let start = (0, 0, 0);
+        let loaded = vec![start, (0, 0, -2), (0, 0, -3), (0, 0, 2), (0, 0, -9)];
+
+        let stats = count_culled(loaded.into_iter(), &[start, (0, 0, -2)], &ahead, start, 6);
+        assert_eq!(
+            stats,
+            CullingStats {
+                visible: 2,
+                culled: 1,
+                occluded: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,650 @@
+use crate::blocks::Block;
+use crate::physics::{BlockIndex, Terrain, SKIN};
+use cgmath::Vector3;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// How much more stepping up onto a block costs than walking one block, so that mobs would rather
+/// walk around a little than climb.
+const STEP_UP_COST: f32 = 0.5;
+
+/// How much more a jump costs than walking the same distance.
+const JUMP_COST: f32 = 1.0;
+
+/// How much each block of a drop costs on top of the step off the edge.
+const FALL_COST_PER_BLOCK: f32 = 0.1;
+
+/// How wide a gap, and how far a drop, walkers made with `Walker::fitting` will take on, in
+/// blocks.
+const DEFAULT_MAX_JUMP: u32 = 1;
+const DEFAULT_MAX_FALL: u32 = 3;
+
+/// The four ways a walker can head along the ground.
+const HEADINGS: [(i64, i64); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Walker is the shape and ability of a mob, as far as finding a path for it is concerned. Mobs
+/// stand with the corner of their footprint on a node, the block their feet are in, and take up
+/// `width` by `width` blocks along the ground and `height` blocks up from there.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Walker {
+    /// How many blocks wide and deep the mob is.
+    pub width: u32,
+
+    /// How many blocks tall the mob is.
+    pub height: u32,
+
+    /// How many blocks wide a gap the mob can jump across.
+    pub max_jump: u32,
+
+    /// How many blocks the mob is willing to drop down.
+    pub max_fall: u32,
+}
+
+impl Walker {
+    /// Returns a walker big enough for a body with the half size, in meters, that jumps and falls
+    /// as far as most mobs do.
+    pub fn fitting(half_size: Vector3<f32>) -> Self {
+        let blocks = |half: f32| ((half * 2.0 - SKIN) / Block::WIDTH).ceil().max(1.0) as u32;
+
+        Self {
+            width: blocks(half_size.x.max(half_size.z)),
+            height: blocks(half_size.y),
+            max_jump: DEFAULT_MAX_JUMP,
+            max_fall: DEFAULT_MAX_FALL,
+        }
+    }
+
+    /// Returns the node for a mob whose feet are at `feet`, in meters, in the middle of its
+    /// footprint.
+    pub fn node_at(&self, feet: Vector3<f32>) -> BlockIndex {
+        let half_width = self.width as f32 / 2.0;
+        (
+            (feet.x / Block::WIDTH - half_width).round() as i64,
+            ((feet.y + SKIN) / Block::WIDTH).floor() as i64,
+            (feet.z / Block::WIDTH - half_width).round() as i64,
+        )
+    }
+
+    /// Returns where a mob standing on the node has its feet, in meters: the bottom middle of its
+    /// footprint.
+    pub fn feet_at(&self, (i, j, k): BlockIndex) -> Vector3<f32> {
+        let half_width = self.width as f32 / 2.0;
+        Vector3::new(
+            (i as f32 + half_width) * Block::WIDTH,
+            j as f32 * Block::WIDTH,
+            (k as f32 + half_width) * Block::WIDTH,
+        )
+    }
+
+    /// Returns true if nothing solid is in the way of the mob on the node, with `headroom` more
+    /// blocks above its head.
+    fn fits<T: Terrain + ?Sized>(&self, terrain: &T, (i, j, k): BlockIndex, headroom: u32) -> bool {
+        let width = self.width as i64;
+        let height = (self.height + headroom) as i64;
+
+        for di in 0..width {
+            for dk in 0..width {
+                for dj in 0..height {
+                    if terrain.is_solid((i + di, j + dj, k + dk)) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Returns true if any part of the mob's footprint on the node has something solid under it.
+    fn has_ground<T: Terrain + ?Sized>(&self, terrain: &T, (i, j, k): BlockIndex) -> bool {
+        let width = self.width as i64;
+        (0..width).any(|di| (0..width).any(|dk| terrain.is_solid((i + di, j - 1, k + dk))))
+    }
+
+    /// Returns true if the mob can stand on the node.
+    pub fn can_stand<T: Terrain + ?Sized>(&self, terrain: &T, node: BlockIndex) -> bool {
+        self.fits(terrain, node, 0) && self.has_ground(terrain, node)
+    }
+
+    /// Returns where the mob lands if it steps off of an edge onto the node, unless it'd fall
+    /// further than `max_fall`.
+    fn landing_below<T: Terrain + ?Sized>(
+        &self,
+        terrain: &T,
+        (i, j, k): BlockIndex,
+    ) -> Option<BlockIndex> {
+        for drop in 1..=self.max_fall as i64 {
+            let below = (i, j - drop, k);
+            if !self.fits(terrain, below, 0) {
+                return None;
+            }
+
+            if self.has_ground(terrain, below) {
+                return Some(below);
+            }
+        }
+
+        None
+    }
+
+    /// Returns every node the mob can get to from `from` in one go, and what getting there costs.
+    fn moves<T: Terrain + ?Sized>(&self, terrain: &T, from: BlockIndex) -> Vec<(BlockIndex, f32)> {
+        let (i, j, k) = from;
+        let mut moves = Vec::new();
+
+        for &(di, dk) in HEADINGS.iter() {
+            let next = (i + di, j, k + dk);
+
+            if !self.fits(terrain, next, 0) {
+                // something's in the way, so try stepping up onto it
+                let up = (i + di, j + 1, k + dk);
+                if self.fits(terrain, from, 1) && self.can_stand(terrain, up) {
+                    moves.push((up, 1.0 + STEP_UP_COST));
+                }
+                continue;
+            }
+
+            if self.has_ground(terrain, next) {
+                moves.push((next, 1.0));
+                continue;
+            }
+
+            // nothing underfoot, so either drop down or jump across
+            if let Some(landing) = self.landing_below(terrain, next) {
+                let drop = (j - landing.1) as f32;
+                moves.push((landing, 1.0 + drop * FALL_COST_PER_BLOCK));
+            }
+
+            if !self.fits(terrain, from, 1) {
+                continue;
+            }
+
+            for gap in 1..=self.max_jump as i64 {
+                let over = (i + di * gap, j, k + dk * gap);
+                if !self.fits(terrain, over, 1) || self.has_ground(terrain, over) {
+                    break;
+                }
+
+                let landing = (i + di * (gap + 1), j, k + dk * (gap + 1));
+                if self.can_stand(terrain, landing) {
+                    moves.push((landing, (gap + 1) as f32 + JUMP_COST));
+                    break;
+                }
+            }
+        }
+
+        moves
+    }
+}
+
+/// Path is the way a mob can walk from one node to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    walker: Walker,
+
+    /// Every node along the way, from the start to the goal.
+    nodes: Vec<BlockIndex>,
+}
+
+impl Path {
+    pub fn walker(&self) -> Walker {
+        self.walker
+    }
+
+    pub fn nodes(&self) -> &[BlockIndex] {
+        &self.nodes
+    }
+
+    pub fn start(&self) -> BlockIndex {
+        self.nodes[0]
+    }
+
+    pub fn goal(&self) -> BlockIndex {
+        self.nodes[self.nodes.len() - 1]
+    }
+
+    /// Returns where the mob should head for next at each node along the way, in meters.
+    pub fn waypoints(&self) -> Vec<Vector3<f32>> {
+        self.nodes.iter().map(|&n| self.walker.feet_at(n)).collect()
+    }
+
+    /// Returns true if a change to the block could change whether the path can still be walked.
+    /// Every leg of the path depends on the blocks around it, from the ground under it to just
+    /// above the mob's head, which is room enough for any jumps along the way.
+    pub fn depends_on(&self, (i, j, k): BlockIndex) -> bool {
+        let width = self.walker.width as i64;
+        let height = self.walker.height as i64;
+
+        (0..self.nodes.len()).any(|n| {
+            let a = self.nodes[n];
+            let b = self.nodes[(n + 1).min(self.nodes.len() - 1)];
+
+            i >= a.0.min(b.0)
+                && i < a.0.max(b.0) + width
+                && j >= a.1.min(b.1) - 1
+                && j <= a.1.max(b.1) + height
+                && k >= a.2.min(b.2)
+                && k < a.2.max(b.2) + width
+        })
+    }
+}
+
+/// What a PathSearch has come up with so far.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchState {
+    /// The search ran out of budget before finishing. Step it again later.
+    Searching,
+
+    /// The search found the cheapest way to the goal.
+    Found(Path),
+
+    /// There's no way to the goal, or none within the search's node limit.
+    Unreachable,
+}
+
+/// A node waiting to be looked at, cheapest estimate first.
+#[derive(Debug, Copy, Clone)]
+struct Open {
+    node: BlockIndex,
+
+    /// What getting here has cost.
+    cost: f32,
+
+    /// What getting to the goal through here might cost, at the very least.
+    estimate: f32,
+}
+
+impl Ord for Open {
+    /// BinaryHeap pops the greatest first, so the cheapest estimate is the greatest. Ties go to the
+    /// smaller node so that searches always turn out the same.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+/// PathSearch is an A* search for a Path over terrain. It can be stepped a little at a time, so
+/// that a long search is spread across ticks instead of holding one up.
+pub struct PathSearch {
+    walker: Walker,
+    goal: BlockIndex,
+
+    /// The search gives up after looking at this many nodes.
+    max_nodes: usize,
+    looked_at: usize,
+
+    open: BinaryHeap<Open>,
+    came_from: HashMap<BlockIndex, BlockIndex>,
+    costs: HashMap<BlockIndex, f32>,
+
+    /// What the search came up with, once it's done.
+    outcome: Option<SearchState>,
+}
+
+impl PathSearch {
+    pub fn new(walker: Walker, start: BlockIndex, goal: BlockIndex, max_nodes: usize) -> Self {
+        let mut open = BinaryHeap::new();
+        open.push(Open {
+            node: start,
+            cost: 0.0,
+            estimate: Self::estimate(start, goal),
+        });
+
+        let mut costs = HashMap::new();
+        costs.insert(start, 0.0);
+
+        Self {
+            walker,
+            goal,
+            max_nodes,
+            looked_at: 0,
+            open,
+            came_from: HashMap::new(),
+            costs,
+            outcome: None,
+        }
+    }
+
+    /// Returns the least that walking from `from` to `to` could cost. Every move costs at least
+    /// as much as how far along the ground it goes, and drops can go down for next to nothing, so
+    /// only distance along the ground counts.
+    fn estimate(from: BlockIndex, to: BlockIndex) -> f32 {
+        ((from.0 - to.0).abs() + (from.2 - to.2).abs()) as f32
+    }
+
+    /// Returns how many nodes the search has looked at so far.
+    pub fn looked_at(&self) -> usize {
+        self.looked_at
+    }
+
+    /// Looks at up to `budget` more nodes. Once the search is done, it keeps returning what it
+    /// came up with.
+    pub fn step<T: Terrain + ?Sized>(&mut self, terrain: &T, budget: usize) -> SearchState {
+        if let Some(outcome) = &self.outcome {
+            return outcome.clone();
+        }
+
+        // nothing could ever reach somewhere the mob can't stand
+        if self.looked_at == 0 && !self.walker.can_stand(terrain, self.goal) {
+            return self.finish(SearchState::Unreachable);
+        }
+
+        for _ in 0..budget {
+            let current = match self.open.pop() {
+                Some(o) => o,
+                None => return self.finish(SearchState::Unreachable),
+            };
+
+            if current.node == self.goal {
+                let path = self.path_to(current.node);
+                return self.finish(SearchState::Found(path));
+            }
+
+            // a cheaper way here was found after this one was queued
+            if matches!(self.costs.get(&current.node), Some(&c) if current.cost > c) {
+                continue;
+            }
+
+            self.looked_at += 1;
+            if self.looked_at > self.max_nodes {
+                return self.finish(SearchState::Unreachable);
+            }
+
+            for (next, step_cost) in self.walker.moves(terrain, current.node) {
+                let cost = current.cost + step_cost;
+                if matches!(self.costs.get(&next), Some(&c) if cost >= c) {
+                    continue;
+                }
+
+                self.costs.insert(next, cost);
+                self.came_from.insert(next, current.node);
+                self.open.push(Open {
+                    node: next,
+                    cost,
+                    estimate: cost + Self::estimate(next, self.goal),
+                });
+            }
+        }
+
+        SearchState::Searching
+    }
+
+    fn finish(&mut self, outcome: SearchState) -> SearchState {
+        self.open.clear();
+        self.came_from.clear();
+        self.costs.clear();
+        self.outcome = Some(outcome.clone());
+        outcome
+    }
+
+    fn path_to(&self, goal: BlockIndex) -> Path {
+        let mut nodes = vec![goal];
+        while let Some(&previous) = self.came_from.get(&nodes[nodes.len() - 1]) {
+            nodes.push(previous);
+        }
+        nodes.reverse();
+
+        Path {
+            walker: self.walker,
+            nodes,
+        }
+    }
+}
+
+/// Finds the cheapest path from `start` to `goal` all at once, looking at no more than
+/// `max_nodes` nodes.
+pub fn find_path<T: Terrain + ?Sized>(
+    terrain: &T,
+    walker: Walker,
+    start: BlockIndex,
+    goal: BlockIndex,
+    max_nodes: usize,
+) -> Option<Path> {
+    match PathSearch::new(walker, start, goal, max_nodes).step(terrain, usize::MAX) {
+        SearchState::Found(path) => Some(path),
+        _ => None,
+    }
+}
+
+/// How many paths a PathCache keeps by default before it starts forgetting the ones that haven't
+/// been used in the longest time.
+pub const DEFAULT_MAX_CACHED_PATHS: usize = 256;
+
+/// PathCache keeps paths that have already been found, so that mobs heading the same way don't
+/// each search for them again. Paths are forgotten when blocks along them change, and once there
+/// are more than `capacity` of them the least recently used one goes.
+pub struct PathCache {
+    paths: HashMap<(BlockIndex, BlockIndex, Walker), CachedPath>,
+    capacity: usize,
+
+    /// Counts up every time a path is looked up or added, to tell which was used last.
+    clock: u64,
+}
+
+struct CachedPath {
+    path: Path,
+    last_used: u64,
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_MAX_CACHED_PATHS)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            paths: HashMap::new(),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    pub fn get(&mut self, walker: Walker, start: BlockIndex, goal: BlockIndex) -> Option<&Path> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        self.paths.get_mut(&(start, goal, walker)).map(|cached| {
+            cached.last_used = clock;
+            &cached.path
+        })
+    }
+
+    pub fn insert(&mut self, path: Path) {
+        self.clock += 1;
+        self.paths.insert(
+            (path.start(), path.goal(), path.walker),
+            CachedPath {
+                path,
+                last_used: self.clock,
+            },
+        );
+
+        while self.paths.len() > self.capacity {
+            let oldest = self
+                .paths
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(&key, _)| key);
+            match oldest {
+                Some(key) => self.paths.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Forgets every path that depends on any of the blocks, which have changed.
+    pub fn invalidate(&mut self, changed: &[BlockIndex]) {
+        if changed.is_empty() {
+            return;
+        }
+
+        self.paths
+            .retain(|_, cached| !changed.iter().any(|&b| cached.path.depends_on(b)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_NODES: usize = 500;
+
+    fn walker(width: u32, height: u32) -> Walker {
+        Walker {
+            width,
+            height,
+            max_jump: 1,
+            max_fall: 3,
+        }
+    }
+
+    #[test]
+    fn steps_up_one_block_but_not_two() {
+        let step = |(i, j, _): BlockIndex| j < 0 || (i >= 3 && j < 1);
+        let path = find_path(&step, walker(1, 1), (0, 0, 0), (5, 1, 0), MAX_NODES).unwrap();
+        assert_eq!(
+            path.nodes(),
+            &[
+                (0, 0, 0),
+                (1, 0, 0),
+                (2, 0, 0),
+                (3, 1, 0),
+                (4, 1, 0),
+                (5, 1, 0)
+            ]
+        );
+        assert_eq!(path.waypoints()[3], Vector3::new(1.75, 0.5, 0.25));
+
+        let wall = |(i, j, _): BlockIndex| j < 0 || (i >= 3 && j < 2);
+        assert!(find_path(&wall, walker(1, 1), (0, 0, 0), (5, 2, 0), MAX_NODES).is_none());
+    }
+
+    #[test]
+    fn jumps_gaps_and_drops_within_limits() {
+        let pit = |(i, j, _): BlockIndex| j < 0 && i != 2;
+        let over = find_path(&pit, walker(1, 1), (0, 0, 0), (4, 0, 0), MAX_NODES).unwrap();
+        assert_eq!(over.nodes(), &[(0, 0, 0), (1, 0, 0), (3, 0, 0), (4, 0, 0)]);
+
+        let wide_pit = |(i, j, _): BlockIndex| j < 0 && i != 2 && i != 3;
+        assert!(find_path(&wide_pit, walker(1, 1), (0, 0, 0), (5, 0, 0), MAX_NODES).is_none());
+        let jumper = Walker {
+            max_jump: 2,
+            ..walker(1, 1)
+        };
+        assert!(find_path(&wide_pit, jumper, (0, 0, 0), (5, 0, 0), MAX_NODES).is_some());
+
+        let cliff = |(i, j, _): BlockIndex| (j < 0 && i < 3) || j < -3;
+        let down = find_path(&cliff, walker(1, 1), (0, 0, 0), (5, -3, 0), MAX_NODES).unwrap();
+        assert_eq!(down.nodes()[3], (3, -3, 0));
+
+        let timid = Walker {
+            max_fall: 2,
+            ..walker(1, 1)
+        };
+        assert!(find_path(&cliff, timid, (0, 0, 0), (5, -3, 0), MAX_NODES).is_none());
+    }
+
+    #[test]
+    fn big_mobs_need_room() {
+        let ceiling = |(i, j, _): BlockIndex| j < 0 || (j == 1 && (i == 2 || i == 3));
+        assert!(find_path(&ceiling, walker(1, 1), (0, 0, 0), (5, 0, 0), MAX_NODES).is_some());
+        assert!(find_path(&ceiling, walker(1, 2), (0, 0, 0), (5, 0, 0), MAX_NODES).is_none());
+
+        let corridor = |(i, j, k): BlockIndex| j < 0 || (k != 0 && (i == 2 || i == 3));
+        assert!(find_path(&corridor, walker(1, 1), (0, 0, 0), (5, 0, 0), MAX_NODES).is_some());
+        assert!(find_path(&corridor, walker(2, 1), (0, 0, 0), (5, 0, 0), MAX_NODES).is_none());
+
+        assert_eq!(Walker::fitting(Vector3::new(0.2, 0.25, 0.2)), walker(1, 1));
+        assert_eq!(Walker::fitting(Vector3::new(0.4, 0.5, 0.3)), walker(2, 2));
+    }
+
+    #[test]
+    fn searches_spread_across_steps_find_the_same_path() {
+        let floor = |(_, j, _): BlockIndex| j < 0;
+        let whole = find_path(&floor, walker(1, 1), (0, 0, 0), (6, 0, 3), MAX_NODES).unwrap();
+
+        let mut search = PathSearch::new(walker(1, 1), (0, 0, 0), (6, 0, 3), MAX_NODES);
+        let mut steps = 0;
+        let found = loop {
+            steps += 1;
+            match search.step(&floor, 2) {
+                SearchState::Searching => continue,
+                SearchState::Found(path) => break path,
+                SearchState::Unreachable => panic!("should have found a path"),
+            }
+        };
+
+        assert!(steps > 1);
+        assert_eq!(found, whole);
+        assert_eq!(search.step(&floor, 2), SearchState::Found(whole));
+    }
+
+    #[test]
+    fn cached_paths_are_forgotten_when_blocks_along_them_change() {
+        let floor = |(_, j, _): BlockIndex| j < 0;
+        let path = find_path(&floor, walker(1, 1), (0, 0, 0), (4, 0, 0), MAX_NODES).unwrap();
+
+        let mut cache = PathCache::new();
+        cache.insert(path);
+        assert!(cache.get(walker(1, 1), (0, 0, 0), (4, 0, 0)).is_some());
+        assert!(cache.get(walker(2, 1), (0, 0, 0), (4, 0, 0)).is_none());
+
+        // nowhere near it
+        cache.invalidate(&[(2, 0, 5), (2, 4, 0)]);
+        assert_eq!(cache.len(), 1);
+
+        // the ground under it
+        cache.invalidate(&[(2, -1, 0)]);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_forgets_the_least_recently_used_path_when_full() {
+        let floor = |(_, j, _): BlockIndex| j < 0;
+        let to = |i: i64| find_path(&floor, walker(1, 1), (0, 0, 0), (i, 0, 0), MAX_NODES).unwrap();
+
+        let mut cache = PathCache::with_capacity(2);
+        cache.insert(to(2));
+        cache.insert(to(3));
+
+        // using the first one makes the second the oldest
+        assert!(cache.get(walker(1, 1), (0, 0, 0), (2, 0, 0)).is_some());
+        cache.insert(to(4));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(walker(1, 1), (0, 0, 0), (2, 0, 0)).is_some());
+        assert!(cache.get(walker(1, 1), (0, 0, 0), (3, 0, 0)).is_none());
+        assert!(cache.get(walker(1, 1), (0, 0, 0), (4, 0, 0)).is_some());
+    }
+}